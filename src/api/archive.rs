@@ -0,0 +1,189 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::core::db;
+use crate::core::sidecar::Compression;
+
+/// Outcome of an archive.
+#[derive(Debug)]
+pub struct ArchiveReport {
+    pub uid: String,
+    pub out: PathBuf,
+    /// Number of simulations in the archive
+    pub simulations: usize,
+    /// Size of the written file in bytes
+    pub size: u64,
+}
+
+impl fmt::Display for ArchiveReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Archived {} simulations of {} to {} ({})",
+            self.simulations,
+            self.uid,
+            self.out.display(),
+            format_size(self.size)
+        )
+    }
+}
+
+/// Formats a size in bytes with a binary unit, e.g. `1.5 MiB`.
+fn format_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64;
+    for unit in ["KiB", "MiB"] {
+        size /= 1024.0;
+        if size < 1024.0 {
+            return format!("{:.1} {}", size, unit);
+        }
+    }
+    format!("{:.1} GiB", size / 1024.0)
+}
+
+/// Writes a standalone database holding only the collection `uid` of `db_path`, its
+/// simulations and their sync history, to share it without the rest of the cache. The
+/// result is compressed if `out` ends in `.gz` or `.zst`; otherwise simdex opens it
+/// directly with `--db`. Fails if `out` already exists.
+pub fn archive(db_path: &Path, uid: &str, out: &Path) -> Result<ArchiveReport, String> {
+    let src = db::open_existing(db_path)?;
+    if db::is_legacy_schema(&src).map_err(|e| e.to_string())? {
+        return Err("the database uses the legacy schema, run `simdex upgrade-db` first".into());
+    }
+    if db::get_collection_path(&src, uid).is_none() {
+        return Err(format!("collection {} not in cache", uid));
+    }
+    drop(src);
+    if out.exists() {
+        return Err(format!("{} already exists", out.display()));
+    }
+
+    let compression = Compression::from_path(out);
+    let parent = out.parent().filter(|p| !p.as_os_str().is_empty());
+    // the archive is built next to `out` and moved into place once complete, so a
+    // failed archive does not leave a partial file behind
+    let dir = parent.unwrap_or(Path::new("."));
+    let temp_file = || {
+        super::export::staging_file(dir).map_err(|e| format!("cannot create temporary file: {}", e))
+    };
+    let staging = temp_file()?;
+
+    let mut conn = db::open_or_init(staging.path())?;
+    let src_path = db::find_db_path(db_path, Path::new("."));
+    conn.execute(
+        "ATTACH DATABASE ?1 AS src",
+        [src_path.to_string_lossy().as_ref()],
+    )
+    .map_err(|e| format!("cannot attach {}: {}", src_path.display(), e))?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let simulations = db::copy_attached_collection(&tx, uid)
+        .map_err(|e| format!("failed to copy collection {}: {}", uid, e))?;
+    tx.commit().map_err(|e| e.to_string())?;
+    conn.execute("DETACH DATABASE src", [])
+        .map_err(|e| e.to_string())?;
+    drop(conn);
+
+    let written = match compression {
+        Compression::None => staging,
+        _ => {
+            let compressed = temp_file()?;
+            compress(staging.path(), compressed.path(), compression)
+                .map_err(|e| format!("cannot write {}: {}", out.display(), e))?;
+            compressed
+        }
+    };
+    written
+        .persist_noclobber(out)
+        .map_err(|e| format!("cannot write {}: {}", out.display(), e.error))?;
+    let size = fs::metadata(out).map(|m| m.len()).unwrap_or(0);
+    Ok(ArchiveReport {
+        uid: uid.to_string(),
+        out: out.to_path_buf(),
+        simulations,
+        size,
+    })
+}
+
+/// Compresses the file `src` into `dest`.
+fn compress(src: &Path, dest: &Path, compression: Compression) -> io::Result<()> {
+    let mut input = fs::File::open(src)?;
+    let output = fs::File::create(dest)?;
+    match compression {
+        Compression::None => {
+            io::copy(&mut input, &mut io::BufWriter::new(output))?;
+        }
+        Compression::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(output, flate2::Compression::default());
+            io::copy(&mut input, &mut encoder)?;
+            encoder.finish()?;
+        }
+        Compression::Zstd => {
+            let mut encoder = zstd::stream::write::Encoder::new(output, 0)?;
+            io::copy(&mut input, &mut encoder)?;
+            encoder.finish()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MiB");
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024 * 1024), "5120.0 GiB");
+    }
+
+    #[test]
+    fn test_archive_keeps_only_the_collection() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let db_path = tmp_dir.path().join("simdex.db");
+        let conn = db::open_or_init(&db_path).unwrap();
+        for (uid, name) in [("C1", "run_a"), ("C1", "run_b"), ("C2", "run_c")] {
+            db::upsert_collection(&conn, uid, &format!("/data/{}", uid)).unwrap();
+            conn.execute(
+                "INSERT INTO simulations (collection_uid, name, parameters_json)
+                 VALUES (?1, ?2, '{\"dt\": 0.1}')",
+                [uid, name],
+            )
+            .unwrap();
+        }
+        drop(conn);
+
+        let out = tmp_dir.path().join("c1.db");
+        let report = archive(&db_path, "C1", &out).unwrap();
+        assert_eq!(report.simulations, 2);
+        assert!(archive(&db_path, "C1", &out).is_err());
+
+        let archived = db::open_existing(&out).unwrap();
+        let uids: Vec<String> = db::get_collections(&archived)
+            .unwrap()
+            .into_iter()
+            .map(|c| c.uid)
+            .collect();
+        assert_eq!(uids, ["C1"]);
+        let names: Vec<String> = db::get_simulations(&archived, "C1")
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(names, ["run_a", "run_b"]);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode();
+            let created = tmp_dir.path().join("created");
+            fs::File::create(&created).unwrap();
+            assert_eq!(mode(&out), mode(&created));
+        }
+    }
+}
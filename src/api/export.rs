@@ -0,0 +1,418 @@
+use serde_json::Value as JsonValue;
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::Path;
+
+use crate::core::{db, time};
+
+/// File format of `export`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    /// Same structure as JSON; multi-line strings become block scalars
+    Yaml,
+}
+
+/// Representation of `created_at` in exports.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum TimeFormat {
+    /// The stored RFC 3339 string, unchanged
+    #[default]
+    Rfc3339,
+    /// Seconds since the Unix epoch
+    Epoch,
+    /// Milliseconds since the Unix epoch
+    EpochMs,
+}
+
+impl TimeFormat {
+    /// Converts a stored `created_at` string. Unparseable timestamps become `null`, and
+    /// unknown ones (see [`time::UNKNOWN_CREATED_AT`]) `<unknown>` or `null` as epochs.
+    pub fn convert(&self, created_at: Option<&str>) -> JsonValue {
+        let Some(created_at) = created_at else {
+            return JsonValue::Null;
+        };
+        if time::is_unknown_created_at(created_at) {
+            return match self {
+                TimeFormat::Rfc3339 => JsonValue::from(time::UNKNOWN_LABEL),
+                TimeFormat::Epoch | TimeFormat::EpochMs => JsonValue::Null,
+            };
+        }
+        if *self == TimeFormat::Rfc3339 {
+            return JsonValue::from(created_at);
+        }
+        match chrono::DateTime::parse_from_rfc3339(created_at) {
+            Ok(dt) if *self == TimeFormat::Epoch => JsonValue::from(dt.timestamp()),
+            Ok(dt) => JsonValue::from(dt.timestamp_millis()),
+            Err(_) => JsonValue::Null,
+        }
+    }
+}
+
+/// Options of the `export` command.
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    pub time_format: TimeFormat,
+    /// Only export parameters whose values are all numbers, and the core columns that
+    /// are numbers (`id`, and `created_at` as epoch time), e.g. for plotting
+    pub numeric_only: bool,
+    /// Core columns exported with `numeric_only` even though they are not numbers,
+    /// e.g. `name`
+    pub keep: Vec<String>,
+    /// Export at most this many simulations
+    pub limit: Option<usize>,
+    /// Export each simulation with this probability, for a random sample
+    pub sample: Option<f64>,
+    /// Seed of the random choices of `sample`; the same seed gives the same sample
+    pub seed: u64,
+}
+
+/// Columns of every simulation besides its parameters, in export order.
+const CORE_COLUMNS: [&str; 6] = [
+    "id",
+    "name",
+    "created_at",
+    "description",
+    "status",
+    "submitted",
+];
+
+/// Returns the value of a core column of a simulation.
+fn core_value(sim: &db::Simulation, column: &str, time_format: TimeFormat) -> JsonValue {
+    match column {
+        "id" => sim.id.into(),
+        "name" => sim.name.clone().into(),
+        "created_at" => time_format.convert(sim.created_at.as_deref()),
+        "description" => sim.description.clone().into(),
+        "status" => sim.status.clone().into(),
+        "submitted" => sim.submitted.into(),
+        _ => JsonValue::Null,
+    }
+}
+
+/// Returns the core columns to export, see [`ExportOptions::numeric_only`].
+fn core_columns(opts: &ExportOptions) -> Vec<&'static str> {
+    CORE_COLUMNS
+        .into_iter()
+        .filter(|column| {
+            let numeric = match *column {
+                "id" => true,
+                "created_at" => opts.time_format != TimeFormat::Rfc3339,
+                _ => false,
+            };
+            !opts.numeric_only || numeric || opts.keep.iter().any(|k| k == column)
+        })
+        .collect()
+}
+
+/// Renders a JSON value as a CSV cell: strings unquoted, `null` as an empty cell.
+fn csv_value(value: &JsonValue) -> String {
+    match value {
+        JsonValue::Null => String::new(),
+        JsonValue::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Small seeded generator (SplitMix64) for `--sample`, so that a sample can be
+/// reproduced with the same seed.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    /// Returns a uniformly distributed number in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Number of simulations read from the cache at a time
+const PAGE_SIZE: usize = 1000;
+
+/// Writes the simulations of a collection to `out` (stdout if None) as CSV, JSON or YAML,
+/// or a sample of them, see [`ExportOptions::sample`] and [`ExportOptions::limit`]. In
+/// CSV, every parameter becomes its own column. Simulations are read and written a page
+/// at a time, so memory does not grow with the size of the collection.
+pub fn export(
+    db_path: &Path,
+    collection: &str,
+    out: Option<&Path>,
+    opts: &ExportOptions,
+) -> Result<(), String> {
+    let conn = db::open_existing(db_path)?;
+    let core = core_columns(opts);
+    // the parameter keys to export; None exports all of them
+    let numeric_keys: Option<BTreeSet<String>> = if opts.numeric_only {
+        let summary =
+            db::aggregate_parameters(&conn, collection, None).map_err(|e| e.to_string())?;
+        Some(
+            summary
+                .into_iter()
+                .filter(|(_, param)| param.is_numeric)
+                .map(|(key, _)| key)
+                .collect(),
+        )
+    } else {
+        None
+    };
+    let exported = |key: &String| numeric_keys.as_ref().is_none_or(|keys| keys.contains(key));
+
+    // a file is written next to `out` and moved into place once complete, so a failed
+    // export does not leave a truncated file behind
+    let staging = match out {
+        Some(path) => {
+            let dir = path.parent().filter(|p| !p.as_os_str().is_empty());
+            Some(
+                staging_file(dir.unwrap_or(Path::new(".")))
+                    .map_err(|e| format!("failed to write '{}': {}", path.display(), e))?,
+            )
+        }
+        None => None,
+    };
+    let mut writer: Box<dyn Write + '_> = match &staging {
+        Some(file) => Box::new(std::io::BufWriter::new(file.as_file())),
+        None => Box::new(std::io::BufWriter::new(std::io::stdout())),
+    };
+    let write_error = |e: std::io::Error| match out {
+        Some(path) => format!("failed to write '{}': {}", path.display(), e),
+        None => e.to_string(),
+    };
+
+    let keys: Vec<String> = match opts.format {
+        ExportFormat::Csv => {
+            let keys: Vec<String> = db::collection_param_keys(&conn, collection, None)
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .filter(|key| exported(key))
+                .collect();
+            let mut header = core.clone();
+            header.extend(keys.iter().map(String::as_str));
+            write_csv_record(&mut writer, header.iter().map(|h| h.to_string()))
+                .map_err(write_error)?;
+            keys
+        }
+        ExportFormat::Json => {
+            write!(writer, "[").map_err(write_error)?;
+            Vec::new()
+        }
+        ExportFormat::Yaml => Vec::new(),
+    };
+
+    let mut rng = SplitMix64(opts.seed);
+    let mut written = 0;
+    let mut last_id = 0;
+    'pages: loop {
+        let page = db::get_simulations_page(&conn, collection, last_id, PAGE_SIZE)
+            .map_err(|e| e.to_string())?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        last_id = last.id;
+        for sim in &page {
+            if opts.limit.is_some_and(|limit| written >= limit) {
+                break 'pages;
+            }
+            if opts.sample.is_some_and(|rate| rng.next_f64() >= rate) {
+                continue;
+            }
+            match opts.format {
+                ExportFormat::Csv => {
+                    let mut record: Vec<String> = core
+                        .iter()
+                        .map(|column| csv_value(&core_value(sim, column, opts.time_format)))
+                        .collect();
+                    record.extend(
+                        keys.iter()
+                            .map(|k| sim.parameters.get(k).map(csv_value).unwrap_or_default()),
+                    );
+                    write_csv_record(&mut writer, record.into_iter()).map_err(write_error)?;
+                }
+                ExportFormat::Json | ExportFormat::Yaml => {
+                    let mut record: serde_json::Map<String, JsonValue> = core
+                        .iter()
+                        .map(|column| {
+                            (
+                                column.to_string(),
+                                core_value(sim, column, opts.time_format),
+                            )
+                        })
+                        .collect();
+                    let parameters: serde_json::Map<String, JsonValue> = sim
+                        .parameters
+                        .iter()
+                        .filter(|(key, _)| exported(key))
+                        .map(|(key, value)| (key.clone(), value.clone()))
+                        .collect();
+                    record.insert("parameters".to_string(), parameters.into());
+                    let record = JsonValue::Object(record);
+                    if opts.format == ExportFormat::Json {
+                        // the elements of a pretty-printed array, indented by two spaces
+                        let pretty =
+                            serde_json::to_string_pretty(&record).map_err(|e| e.to_string())?;
+                        let separator = if written == 0 { "" } else { "," };
+                        write!(writer, "{}\n  {}", separator, pretty.replace('\n', "\n  "))
+                            .map_err(write_error)?;
+                    } else {
+                        let yaml = serde_yaml::to_string(&[record]).map_err(|e| e.to_string())?;
+                        writer.write_all(yaml.as_bytes()).map_err(write_error)?;
+                    }
+                }
+            }
+            written += 1;
+        }
+    }
+
+    match opts.format {
+        ExportFormat::Json if written == 0 => writeln!(writer, "]"),
+        ExportFormat::Json => writeln!(writer, "\n]"),
+        ExportFormat::Yaml if written == 0 => writeln!(writer, "[]"),
+        _ => Ok(()),
+    }
+    .and_then(|_| writer.flush())
+    .map_err(write_error)?;
+    drop(writer);
+    if let (Some(file), Some(path)) = (staging, out) {
+        file.persist(path)
+            .map_err(|e| format!("failed to write '{}': {}", path.display(), e.error))?;
+    }
+    Ok(())
+}
+
+/// Creates a temporary file in `dir` to be persisted as an output file. Unlike the
+/// private mode temporary files get by default, it has the permissions `File::create`
+/// would give, restricted by the umask.
+pub(crate) fn staging_file(dir: &Path) -> std::io::Result<tempfile::NamedTempFile> {
+    let mut builder = tempfile::Builder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        builder.permissions(std::fs::Permissions::from_mode(0o666));
+    }
+    builder.tempfile_in(dir)
+}
+
+fn write_csv_record(
+    writer: &mut dyn Write,
+    fields: impl Iterator<Item = String>,
+) -> std::io::Result<()> {
+    let fields: Vec<String> = fields.map(|f| super::csv_field(&f)).collect();
+    writeln!(writer, "{}", fields.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numeric_only_keeps_numeric_parameters_and_listed_columns() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let db_path = tmp_dir.path().join("simdex.db");
+        let conn = db::open_or_init(&db_path).unwrap();
+        for (name, parameters) in [
+            ("a", r#"{"dt": 0.1, "method": "cg", "n": 4}"#),
+            ("b", r#"{"dt": 0.01, "method": "gmres", "n": "auto"}"#),
+        ] {
+            conn.execute(
+                "INSERT INTO simulations (collection_uid, name, parameters_json) VALUES ('C1', ?1, ?2)",
+                [name, parameters],
+            )
+            .unwrap();
+        }
+        drop(conn);
+
+        let out = tmp_dir.path().join("out.csv");
+        let opts = ExportOptions {
+            format: ExportFormat::Csv,
+            time_format: TimeFormat::Rfc3339,
+            numeric_only: true,
+            keep: vec!["name".to_string()],
+            limit: None,
+            sample: None,
+            seed: 0,
+        };
+        export(&db_path, "C1", Some(&out), &opts).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&out).unwrap(),
+            "id,name,dt\n1,a,0.1\n2,b,0.01\n"
+        );
+
+        let limited = ExportOptions {
+            format: ExportFormat::Json,
+            limit: Some(1),
+            ..opts
+        };
+        export(&db_path, "C1", Some(&out), &limited).unwrap();
+        let json: JsonValue =
+            serde_json::from_str(&std::fs::read_to_string(&out).unwrap()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([{"id": 1, "name": "a", "parameters": {"dt": 0.1}}])
+        );
+        // the output is written to a temporary file first, which must not be left over
+        let leftovers = std::fs::read_dir(tmp_dir.path())
+            .unwrap()
+            .filter(|e| {
+                e.as_ref()
+                    .unwrap()
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with(".tmp")
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+
+    #[test]
+    fn test_yaml_writes_multiline_descriptions_as_block_scalars() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let db_path = tmp_dir.path().join("simdex.db");
+        let conn = db::open_or_init(&db_path).unwrap();
+        conn.execute(
+            "INSERT INTO simulations (collection_uid, name, description, parameters_json)
+             VALUES ('C1', 'a', ?1, '{}')",
+            ["first line\nsecond line"],
+        )
+        .unwrap();
+        drop(conn);
+
+        let out = tmp_dir.path().join("out.yaml");
+        let opts = ExportOptions {
+            format: ExportFormat::Yaml,
+            time_format: TimeFormat::Rfc3339,
+            numeric_only: false,
+            keep: Vec::new(),
+            limit: None,
+            sample: None,
+            seed: 0,
+        };
+        export(&db_path, "C1", Some(&out), &opts).unwrap();
+        let yaml = std::fs::read_to_string(&out).unwrap();
+        assert!(yaml.contains("description: |-\n    first line\n    second line\n"));
+        let records: Vec<JsonValue> = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(records[0]["description"], "first line\nsecond line");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode();
+            let created = tmp_dir.path().join("created");
+            std::fs::File::create(&created).unwrap();
+            assert_eq!(mode(&out), mode(&created));
+        }
+    }
+
+    #[test]
+    fn test_sample_is_reproducible_with_a_seed() {
+        let draws = |seed| {
+            let mut rng = SplitMix64(seed);
+            (0..1000).filter(|_| rng.next_f64() < 0.1).count()
+        };
+        assert_eq!(draws(7), draws(7));
+        assert!((50..150).contains(&draws(7)));
+    }
+}
@@ -0,0 +1,251 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::core::db;
+
+/// One checksummed data file of a simulation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    /// Name of the simulation
+    pub name: String,
+    /// Path of the file relative to the collection directory
+    pub path: PathBuf,
+    /// Size in bytes
+    pub size: u64,
+    /// Modification time when the manifest was written, RFC 3339
+    pub mtime: String,
+    /// SHA-256 of the contents, hex
+    pub sha256: String,
+}
+
+/// Checksums of the data files of a collection, see [`write_manifest`].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub collection: String,
+    pub created_at: String,
+    pub files: Vec<ManifestFile>,
+    /// SHA-256 of the other fields, so that edits of the manifest itself are detected
+    pub digest: String,
+}
+
+impl Manifest {
+    fn compute_digest(&self) -> String {
+        let content = serde_json::json!([self.collection, self.created_at, self.files]);
+        format!("{:x}", Sha256::digest(content.to_string().as_bytes()))
+    }
+}
+
+/// Returns the SHA-256 of the contents of the file at `path`, hex.
+fn file_digest(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Hashes files on up to `jobs` threads, in order.
+fn hash_files(paths: &[PathBuf], jobs: usize) -> Vec<io::Result<String>> {
+    let chunk_size = paths.len().div_ceil(jobs.max(1)).max(1);
+    std::thread::scope(|s| {
+        let handles: Vec<_> = paths
+            .chunks(chunk_size)
+            .map(|chunk| {
+                s.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|path| file_digest(path))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("hashing thread panicked"))
+            .collect()
+    })
+}
+
+/// File name of a manifest written into its collection directory.
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// Writes a manifest of the data files `data_files` of every cached simulation of the
+/// collection `uid` to `out`, by default [`MANIFEST_FILE_NAME`] in the collection
+/// directory, where [`verify_manifest`] looks for the files. Each file is listed with
+/// its size, mtime and SHA-256. Entries without any of the data files are reported and
+/// left out. Returns the manifest and the path it was written to.
+pub fn write_manifest(
+    db_path: &Path,
+    uid: &str,
+    data_files: &[String],
+    out: Option<&Path>,
+    jobs: usize,
+) -> Result<(Manifest, PathBuf), String> {
+    let conn = db::open_existing(db_path)?;
+    let c_path = db::get_collection_path(&conn, uid)
+        .ok_or_else(|| format!("collection {} not in cache", uid))?;
+    let c_path = c_path.canonicalize().unwrap_or(c_path);
+    let simulations = db::get_simulations(&conn, uid)
+        .map_err(|e| format!("failed to read simulations: {}", e))?;
+
+    let mut files = Vec::new();
+    for sim in simulations {
+        let entry_path = c_path.join(&sim.name);
+        let mut found = false;
+        for name in data_files {
+            let path = Path::new(&sim.name).join(name);
+            let Ok(metadata) = fs::metadata(c_path.join(&path)) else {
+                continue;
+            };
+            let mtime = metadata
+                .modified()
+                .map(|t| chrono::DateTime::<chrono::Local>::from(t).to_rfc3339())
+                .unwrap_or_default();
+            files.push(ManifestFile {
+                name: sim.name.clone(),
+                path,
+                size: metadata.len(),
+                mtime,
+                sha256: String::new(),
+            });
+            found = true;
+        }
+        if !found {
+            eprintln!(
+                "  [!] {}: none of {} found, skipping",
+                entry_path.display(),
+                data_files.join(", ")
+            );
+        }
+    }
+
+    let paths: Vec<PathBuf> = files.iter().map(|f| c_path.join(&f.path)).collect();
+    let digests = hash_files(&paths, jobs);
+    for ((file, path), digest) in files.iter_mut().zip(&paths).zip(digests) {
+        file.sha256 = digest.map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    }
+
+    let mut manifest = Manifest {
+        collection: uid.to_string(),
+        created_at: chrono::Local::now().to_rfc3339(),
+        files,
+        digest: String::new(),
+    };
+    manifest.digest = manifest.compute_digest();
+    let out = out.map_or_else(|| c_path.join(MANIFEST_FILE_NAME), Path::to_path_buf);
+    let json = serde_json::to_string_pretty(&manifest).expect("manifests always serialize");
+    fs::write(&out, json + "\n").map_err(|e| format!("cannot write {}: {}", out.display(), e))?;
+    Ok((manifest, out))
+}
+
+/// Re-hashes the files of a manifest written by [`write_manifest`] and returns those
+/// that are missing or changed, with the reason. The paths of the manifest are resolved
+/// against the collection directory `root`, by default the directory of the manifest.
+/// Fails if the manifest itself was edited.
+pub fn verify_manifest(
+    path: &Path,
+    root: Option<&Path>,
+    jobs: usize,
+) -> Result<Vec<(PathBuf, String)>, String> {
+    let contents =
+        fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    let manifest: Manifest = serde_json::from_str(&contents)
+        .map_err(|e| format!("{} is not a manifest: {}", path.display(), e))?;
+    if manifest.compute_digest() != manifest.digest {
+        return Err(format!(
+            "{} was modified after it was written, its digest does not match",
+            path.display()
+        ));
+    }
+
+    let root = match root {
+        Some(root) => root,
+        None => path.parent().unwrap_or(Path::new(".")),
+    };
+    let paths: Vec<PathBuf> = manifest.files.iter().map(|f| root.join(&f.path)).collect();
+    let digests = hash_files(&paths, jobs);
+    let mut drift = Vec::new();
+    for ((file, path), digest) in manifest.files.iter().zip(paths).zip(digests) {
+        let reason = match digest {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => "missing".to_string(),
+            Err(e) => format!("cannot read: {}", e),
+            Ok(digest) if digest != file.sha256 => match fs::metadata(&path).map(|m| m.len()) {
+                Ok(size) if size != file.size => {
+                    format!("size changed from {} to {} bytes", file.size, size)
+                }
+                _ => "content changed".to_string(),
+            },
+            Ok(_) => continue,
+        };
+        drift.push((path, reason));
+    }
+    Ok(drift)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_detects_changed_and_missing_files() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let c_path = tmp_dir.path().join("c1");
+        for name in ["a", "b", "c"] {
+            fs::create_dir_all(c_path.join(name)).unwrap();
+            fs::write(c_path.join(name).join("data.h5"), name).unwrap();
+        }
+        let db_path = tmp_dir.path().join("simdex.db");
+        let conn = db::open_or_init(&db_path).unwrap();
+        db::upsert_collection(&conn, "C1", &c_path.display().to_string()).unwrap();
+        for name in ["a", "b", "c", "gone"] {
+            conn.execute(
+                "INSERT INTO simulations (collection_uid, name) VALUES ('C1', ?1)",
+                [name],
+            )
+            .unwrap();
+        }
+        drop(conn);
+
+        let (manifest, out) =
+            write_manifest(&db_path, "C1", &["data.h5".to_string()], None, 2).unwrap();
+        assert_eq!(out, c_path.canonicalize().unwrap().join(MANIFEST_FILE_NAME));
+        assert_eq!(manifest.files.len(), 3);
+        assert_eq!(manifest.files[0].path, Path::new("a/data.h5"));
+        assert!(verify_manifest(&out, None, 2).unwrap().is_empty());
+        assert_eq!(
+            verify_manifest(&out, Some(tmp_dir.path()), 2)
+                .unwrap()
+                .len(),
+            3
+        );
+
+        // the paths are relative, so a moved collection still verifies
+        let moved = tmp_dir.path().join("moved");
+        fs::rename(&c_path, &moved).unwrap();
+        let (c_path, out) = (moved.clone(), moved.join(MANIFEST_FILE_NAME));
+        assert!(verify_manifest(&out, Some(&moved), 2).unwrap().is_empty());
+
+        fs::write(c_path.join("a/data.h5"), "x").unwrap();
+        fs::write(c_path.join("b/data.h5"), "bb").unwrap();
+        fs::remove_file(c_path.join("c/data.h5")).unwrap();
+        let drift = verify_manifest(&out, None, 2).unwrap();
+        let reasons: Vec<&str> = drift.iter().map(|(_, r)| r.as_str()).collect();
+        assert_eq!(
+            reasons,
+            [
+                "content changed",
+                "size changed from 1 to 2 bytes",
+                "missing"
+            ]
+        );
+
+        let edited = fs::read_to_string(&out).unwrap().replace("\"a\"", "\"z\"");
+        fs::write(&out, edited).unwrap();
+        assert!(
+            verify_manifest(&out, None, 1)
+                .unwrap_err()
+                .contains("was modified")
+        );
+    }
+}
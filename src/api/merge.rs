@@ -0,0 +1,131 @@
+use chrono::{DateTime, Utc};
+use std::path::Path;
+
+use crate::core::db;
+use crate::core::types::MetaData;
+
+/// Which database wins when both know the same collection or simulation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Prefer {
+    /// Overwrite the destination with the source
+    Src,
+    /// Keep what the destination has, only add what is new
+    #[default]
+    Dest,
+}
+
+/// Outcome of a merge.
+#[derive(Debug, Default)]
+pub struct MergeReport {
+    /// Number of collections in the source
+    pub collections: usize,
+    /// Simulations that were not in the destination
+    pub added: usize,
+    /// Simulations of the destination overwritten by the source
+    pub updated: usize,
+    /// Simulations present in both and kept as in the destination
+    pub kept: usize,
+    /// `(uid, source path, destination path)` of collections whose paths differ
+    pub conflicts: Vec<(String, String, String)>,
+}
+
+impl std::fmt::Display for MergeReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} collections: {} added, {} updated, {} kept, {} path conflicts",
+            self.collections,
+            self.added,
+            self.updated,
+            self.kept,
+            self.conflicts.len()
+        )
+    }
+}
+
+/// Copies all collections and simulations of `src_path` into `dest_path`, in a single
+/// transaction. Collections with the same uid but a different path are reported as
+/// conflicts; `prefer` decides whose path and simulations are kept.
+pub fn merge(src_path: &Path, dest_path: &Path, prefer: Prefer) -> Result<MergeReport, String> {
+    let src = db::open_existing(src_path)?;
+    let dest_path = &db::find_db_path(dest_path, Path::new("."));
+    let _lock = crate::core::lock::try_lock(dest_path).map_err(|e| {
+        format!(
+            "cannot lock {}: {}",
+            crate::core::lock::lock_path(dest_path).display(),
+            e
+        )
+    })?;
+    let mut dest = db::open_or_init(dest_path).map_err(|e| format!("failed to open DB: {}", e))?;
+    let tx = dest.transaction().map_err(|e| e.to_string())?;
+
+    let collections = db::get_collections(&src).map_err(|e| e.to_string())?;
+    let mut report = MergeReport {
+        collections: collections.len(),
+        ..Default::default()
+    };
+
+    for c in collections {
+        let known_path = db::get_collection_path(&tx, &c.uid);
+        if let Some(existing) = &known_path
+            && existing != Path::new(&c.path)
+        {
+            report.conflicts.push((
+                c.uid.clone(),
+                c.path.clone(),
+                existing.display().to_string(),
+            ));
+        }
+        if known_path.is_none() || prefer == Prefer::Src {
+            db::upsert_collection(&tx, &c.uid, &c.path).map_err(|e| e.to_string())?;
+            if let Some(description) = &c.description {
+                db::set_collection_description(&tx, &c.uid, description)
+                    .map_err(|e| e.to_string())?;
+            }
+            if c.parent_uid.is_some() {
+                db::set_collection_parent(&tx, &c.uid, c.parent_uid.as_deref())
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+
+        let sims = db::get_simulations(&src, &c.uid).map_err(|e| e.to_string())?;
+        let mut overrides = db::get_status_overrides(&src, &c.uid).map_err(|e| e.to_string())?;
+        for sim in sims {
+            let exists =
+                db::simulation_exists(&tx, &c.uid, &sim.name).map_err(|e| e.to_string())?;
+            if exists && prefer == Prefer::Dest {
+                report.kept += 1;
+                continue;
+            }
+            // the status read from the entry is kept apart from a manual one, which
+            // replaces that of the destination like the rest of the row
+            let (status, status_override) = match overrides.remove(&sim.name) {
+                Some((status, status_override)) => (status, Some(status_override)),
+                None => (sim.status, None),
+            };
+            let meta = MetaData {
+                created_at: sim
+                    .created_at
+                    .as_deref()
+                    .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                    .map(|dt| dt.with_timezone(&Utc))
+                    .unwrap_or(crate::core::time::UNKNOWN_CREATED_AT),
+                description: sim.description.unwrap_or_default(),
+                status: status.unwrap_or_default(),
+                submitted: sim.submitted,
+            };
+            db::upsert_simulation(&tx, &c.uid, &sim.name, &meta, &sim.parameters)
+                .and_then(|_| {
+                    db::set_status_override(&tx, &c.uid, &sim.name, status_override.as_deref())
+                })
+                .map_err(|e| format!("failed to write {} / {}: {}", c.uid, sim.name, e))?;
+            if exists {
+                report.updated += 1;
+            } else {
+                report.added += 1;
+            }
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(report)
+}
@@ -1,22 +1,237 @@
+pub mod archive;
+pub mod export;
+pub mod manifest;
+pub mod merge;
+pub mod serve;
+pub mod tui;
+
 use pyo3::prelude::*;
+use pyo3::types::PyDict;
 use serde_json::Value as JsonValue;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use tabled::{
-    Tabled,
-    settings::{Color, Style, object::Rows},
+    Table, Tabled,
+    settings::{
+        Color, Style, Width,
+        object::{Rows, Segment},
+    },
 };
 
-use crate::core::{collection, db, discovery, entry};
+use crate::config::{Config, DisplayConfig, TableStyle};
+use crate::core::filter::{Filter, GlobFilter};
+use crate::core::types::Parameters;
+use crate::core::{collection, db, discovery, entry, grid, time};
+
+/// Output format of commands that print tabular data.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    #[default]
+    Table,
+    Csv,
+    Html,
+    /// An array with one object per row, keys in column order
+    Json,
+}
+
+/// One row of a JSON output, serialized as an object whose keys keep the column order.
+struct JsonRecord<'a>(&'a [String], &'a [JsonValue]);
+
+impl serde::Serialize for JsonRecord<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (key, value) in self.0.iter().zip(self.1) {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+/// Renders rows as a pretty-printed JSON array of objects.
+fn json_records(header: &[String], records: &[Vec<JsonValue>]) -> String {
+    let records: Vec<JsonRecord> = records.iter().map(|r| JsonRecord(header, r)).collect();
+    serde_json::to_string_pretty(&records).expect("JSON values always serialize")
+}
+
+/// Quotes a CSV field if it contains a separator, quote or newline.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Escapes the characters that are special in HTML text and attribute values.
+fn html_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+const HTML_STYLE: &str = "<style>
+table.simdex { border-collapse: collapse; font-family: sans-serif; font-size: 0.9em; }
+table.simdex th, table.simdex td { border: 1px solid #ccc; padding: 2px 8px; text-align: left; }
+table.simdex th { background: #eee; }
+table.simdex tr.status-finished { background: #eaf7ea; }
+table.simdex tr.status-failed { background: #fbeaea; }
+table.simdex tr.status-running { background: #eaf0fb; }
+</style>";
+
+/// Renders a header and records as a standalone HTML table. If there is a `status`
+/// column, each row gets a `status-<value>` class for styling.
+fn html_table(header: &[String], records: &[Vec<String>]) -> String {
+    let status_col = header.iter().position(|h| h == "status");
+    let mut html = String::from(HTML_STYLE);
+    html.push_str("\n<table class=\"simdex\">\n<thead>\n<tr>");
+    for h in header {
+        html.push_str(&format!("<th>{}</th>", html_escape(h)));
+    }
+    html.push_str("</tr>\n</thead>\n<tbody>\n");
+    for record in records {
+        let class: Option<String> = status_col.and_then(|i| record.get(i)).map(|status| {
+            status
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() {
+                        c.to_ascii_lowercase()
+                    } else {
+                        '-'
+                    }
+                })
+                .collect()
+        });
+        match class {
+            Some(class) if !class.is_empty() => {
+                html.push_str(&format!("<tr class=\"status-{}\">", class))
+            }
+            _ => html.push_str("<tr>"),
+        }
+        for field in record {
+            html.push_str(&format!("<td>{}</td>", html_escape(field)));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</tbody>\n</table>");
+    html
+}
+
+/// Maps a color name from the config (e.g. `blue`, `bright-black`, `none`) to a `tabled` color.
+fn parse_color(name: &str) -> Option<Color> {
+    let color = match name.to_lowercase().replace('_', "-").as_str() {
+        "none" => Color::empty(),
+        "black" => Color::FG_BLACK,
+        "red" => Color::FG_RED,
+        "green" => Color::FG_GREEN,
+        "yellow" => Color::FG_YELLOW,
+        "blue" => Color::FG_BLUE,
+        "magenta" => Color::FG_MAGENTA,
+        "cyan" => Color::FG_CYAN,
+        "white" => Color::FG_WHITE,
+        "bright-black" | "gray" | "grey" => Color::FG_BRIGHT_BLACK,
+        "bright-red" => Color::FG_BRIGHT_RED,
+        "bright-green" => Color::FG_BRIGHT_GREEN,
+        "bright-yellow" => Color::FG_BRIGHT_YELLOW,
+        "bright-blue" => Color::FG_BRIGHT_BLUE,
+        "bright-magenta" => Color::FG_BRIGHT_MAGENTA,
+        "bright-cyan" => Color::FG_BRIGHT_CYAN,
+        "bright-white" => Color::FG_BRIGHT_WHITE,
+        "bold" => Color::BOLD,
+        _ => return None,
+    };
+    Some(color)
+}
+
+/// Applies the border style (`style` if given, else the config's) and the configured
+/// header color to a built table.
+fn style_table(table: &mut Table, style: Option<TableStyle>, config: &DisplayConfig) {
+    match style.or(config.style).unwrap_or_default() {
+        TableStyle::Blank => table.with(Style::blank()),
+        TableStyle::Sharp => table.with(Style::sharp()),
+        TableStyle::Rounded => table.with(Style::rounded()),
+        TableStyle::Markdown => table.with(Style::markdown()),
+        TableStyle::Ascii => table.with(Style::ascii()),
+    };
+    let header_color = match config.header_color.as_deref() {
+        None => Color::FG_BRIGHT_BLACK,
+        Some(name) => parse_color(name).unwrap_or_else(|| {
+            eprintln!("Unknown header_color '{}', using the default", name);
+            Color::FG_BRIGHT_BLACK
+        }),
+    };
+    table.modify(Rows::first(), header_color);
+}
+
+/// Renders a header and records as a table, CSV, HTML or JSON.
+fn render_records(header: Vec<String>, records: Vec<Vec<String>>, format: Format) -> String {
+    match format {
+        Format::Table => {
+            let mut builder = tabled::builder::Builder::default();
+            builder.push_record(header);
+            for record in records {
+                builder.push_record(record);
+            }
+            let mut table = builder.build();
+            style_table(&mut table, None, &Config::load().display);
+            table.to_string()
+        }
+        Format::Csv => std::iter::once(header)
+            .chain(records)
+            .map(|record| {
+                let fields: Vec<String> = record.iter().map(|f| csv_field(f)).collect();
+                fields.join(",")
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Format::Html => html_table(&header, &records),
+        Format::Json => {
+            let records: Vec<Vec<JsonValue>> = records
+                .into_iter()
+                .map(|r| r.into_iter().map(JsonValue::from).collect())
+                .collect();
+            json_records(&header, &records)
+        }
+    }
+}
+
+/// Describes a failure to write command output, e.g. to a closed pipe.
+fn write_error(e: std::io::Error) -> String {
+    format!("failed to write output: {}", e)
+}
+
+/// Prints a header and records as a table, CSV, HTML or JSON.
+fn print_records(header: Vec<String>, records: Vec<Vec<String>>, format: Format) {
+    println!("{}", render_records(header, records, format));
+}
 
+/// A simulation as shown by `display`, see [`load_rows`] and [`render_table`].
 #[derive(Tabled)]
-struct Row {
-    id: i64,
-    name: String,
-    created_at: String,
-    status: String,
-    submitted: bool,
+pub struct Row {
+    pub id: i64,
+    pub name: String,
+    pub created_at: String,
+    pub status: String,
+    pub submitted: bool,
     #[tabled(skip)]
-    parameters: std::collections::HashMap<String, String>,
+    pub parameters: Parameters,
+    /// Superblock version of the first data file, see
+    /// [`entry::read_superblock_version`]
+    #[tabled(skip)]
+    pub hdf5_superblock: Option<u8>,
+    /// True if `status` was set with `simdex set-status` rather than read from the entry
+    #[tabled(skip)]
+    pub status_overridden: bool,
 }
 
 impl Row {
@@ -27,243 +242,2732 @@ impl Row {
         status: String,
         submitted: bool,
         parameters_json: String,
-    ) -> Self {
-        let parsed: JsonValue = serde_json::from_str(&parameters_json).unwrap_or_default();
-        let parameters = parsed
-            .as_object()
-            .unwrap_or(&serde_json::Map::new())
-            .iter()
-            .map(|(k, v)| (k.clone(), v.to_string()))
-            .collect();
+        strict: bool,
+    ) -> Result<Self, String> {
+        let parameters = db::parse_parameters_json(id, &parameters_json, strict)?;
 
-        Self {
+        Ok(Self {
             id,
             name,
             created_at,
             status,
             submitted,
             parameters,
+            hdf5_superblock: None,
+            status_overridden: false,
+        })
+    }
+
+    /// Looks up a value for filtering: a parameter, or else one of the core columns.
+    fn field(&self, key: &str) -> Option<JsonValue> {
+        if let Some(value) = self.parameters.get(key) {
+            return Some(value.clone());
+        }
+        match key {
+            "id" => Some(self.id.into()),
+            "name" => Some(self.name.clone().into()),
+            "created_at" => Some(self.created_at.clone().into()),
+            "status" => Some(self.status.clone().into()),
+            "submitted" => Some(self.submitted.into()),
+            "hdf5_superblock" => self.hdf5_superblock.map(JsonValue::from),
+            _ => None,
         }
     }
+
+    /// Returns true if the row satisfies all filters.
+    fn matches(&self, filters: &[Filter]) -> bool {
+        filters
+            .iter()
+            .all(|f| f.matches(self.field(&f.key).as_ref()))
+    }
 }
 
-/// Flattens a vector of structs with a HashMap field into separate columns for each key in the HashMap.
-/// Returns a tuple of (Vec of field vectors, BTreeSet of all keys, Vec of HashMap values per key).
-fn flatten_hashmap_field(
-    rows: &[Row],
-    hashmap_field: fn(&Row) -> &std::collections::HashMap<String, String>,
+/// A flattened parameter column. The variant is the narrowest type that holds every
+/// value of the column: integers widen to floats, anything else mixed falls back to strings.
+/// Missing keys and JSON `null` are `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    Int(Vec<Option<i64>>),
+    Float(Vec<Option<f64>>),
+    Bool(Vec<Option<bool>>),
+    Str(Vec<Option<String>>),
+}
+
+impl Column {
+    fn from_values(values: &[Option<&JsonValue>]) -> Self {
+        let present = || values.iter().flatten().filter(|v| !v.is_null());
+        if present().next().is_none() {
+            return Column::Str(vec![None; values.len()]);
+        }
+        if present().all(|v| v.is_i64()) {
+            Column::Int(values.iter().map(|v| v.and_then(|v| v.as_i64())).collect())
+        } else if present().all(|v| v.is_number()) {
+            Column::Float(values.iter().map(|v| v.and_then(|v| v.as_f64())).collect())
+        } else if present().all(|v| v.is_boolean()) {
+            Column::Bool(values.iter().map(|v| v.and_then(|v| v.as_bool())).collect())
+        } else {
+            Column::Str(
+                values
+                    .iter()
+                    .map(|v| match v {
+                        None | Some(JsonValue::Null) => None,
+                        Some(JsonValue::String(s)) => Some(s.clone()),
+                        Some(other) => Some(other.to_string()),
+                    })
+                    .collect(),
+            )
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Column::Int(v) => v.len(),
+            Column::Float(v) => v.len(),
+            Column::Bool(v) => v.len(),
+            Column::Str(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, Column::Int(_) | Column::Float(_))
+    }
+}
+
+/// Flattens a vector of structs with a map field into separate columns for each key in the map.
+/// Returns the sorted union of all keys and a typed column per key, with one value per row.
+fn flatten_hashmap_field<R: std::borrow::Borrow<Row>>(
+    rows: &[R],
+    hashmap_field: fn(&Row) -> &Parameters,
 ) -> (
     std::collections::BTreeSet<String>,
-    std::collections::HashMap<String, Vec<Option<String>>>,
+    std::collections::HashMap<String, Column>,
 ) {
     let mut all_keys = std::collections::BTreeSet::new();
     for row in rows {
-        all_keys.extend(hashmap_field(row).keys().cloned());
-    }
-    let mut columns: std::collections::HashMap<String, Vec<Option<String>>> =
-        std::collections::HashMap::new();
-    for key in &all_keys {
-        columns.insert(key.clone(), Vec::with_capacity(rows.len()));
-    }
-    for row in rows {
-        let map = hashmap_field(row);
-        for key in &all_keys {
-            columns.get_mut(key).unwrap().push(map.get(key).cloned());
-        }
+        all_keys.extend(hashmap_field(row.borrow()).keys().cloned());
     }
+    let columns = all_keys
+        .iter()
+        .map(|key| {
+            let values: Vec<Option<&JsonValue>> = rows
+                .iter()
+                .map(|row| hashmap_field(row.borrow()).get(key))
+                .collect();
+            (key.clone(), Column::from_values(&values))
+        })
+        .collect();
     (all_keys, columns)
 }
 
+/// Options controlling how `display` renders a collection.
+#[derive(Default)]
+pub struct DisplayOptions {
+    /// Collapse all parameters into a single `key=val, key2=val2` column
+    pub params_inline: bool,
+    /// Truncate cells wider than this many characters, overrides the config
+    pub max_col_width: Option<usize>,
+    /// Table border style, overrides the config
+    pub style: Option<TableStyle>,
+    /// Only show rows matching all of these filters
+    pub filters: Vec<Filter>,
+    /// Output format; CSV and HTML ignore the table styling options
+    pub format: Format,
+    /// Fail on malformed `parameters_json` instead of warning and showing no parameters
+    pub strict: bool,
+    /// Read the collection from disk instead of the cache
+    pub live: bool,
+    /// Where to look for collections on disk, defaults to the current directory
+    pub root: Option<PathBuf>,
+    /// Lowercase parameter keys, merging variants that differ only in case
+    pub case_fold: bool,
+    /// Write the output to this file instead of stdout, without colors. Missing parent
+    /// directories are created
+    pub output: Option<PathBuf>,
+    /// Only show these columns, in this order. Names that are neither a fixed column nor
+    /// a parameter of the collection give an empty column
+    pub columns: Option<Vec<String>>,
+    /// Add a `hdf5_superblock` column with the superblock version of each simulation's
+    /// data file, e.g. to tell files written with old library versions apart
+    pub hdf5_version: bool,
+    /// Sort the rows by this parameter or column and, in table format, render a
+    /// subtable per value under a `key = value` heading, without the key's column. Rows
+    /// without it come last, under `<ungrouped>`
+    pub group_by: Option<String>,
+    /// Leave out parameter columns with the same value in every shown row, unless listed
+    /// in `columns`. In table format they are listed on a `fixed parameters` line above
+    /// the table instead
+    pub hide_constant: bool,
+}
+
+/// Reads a column list for [`DisplayOptions::columns`] from a file with one name per
+/// line. Blank lines and lines starting with `#` are ignored.
+pub fn read_columns_file(path: &Path) -> Result<Vec<String>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    Ok(parse_columns(&contents))
+}
+
+fn parse_columns(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+/// Reorders the header and records to `columns`. Unknown columns are reported and left
+/// empty.
+fn select_columns(
+    header: Vec<String>,
+    records: Vec<Vec<String>>,
+    columns: &[String],
+) -> (Vec<String>, Vec<Vec<String>>) {
+    let indices: Vec<Option<usize>> = columns
+        .iter()
+        .map(|column| {
+            let index = header.iter().position(|h| h == column);
+            if index.is_none() {
+                eprintln!("Warning: no column '{}', leaving it empty", column);
+            }
+            index
+        })
+        .collect();
+    let records = records
+        .into_iter()
+        .map(|record| {
+            indices
+                .iter()
+                .map(|i| i.map(|i| record[i].clone()).unwrap_or_default())
+                .collect()
+        })
+        .collect();
+    (columns.to_vec(), records)
+}
+
+/// Joins a parameter map into `key=val, key2=val2`, sorted by key.
+fn join_parameters(parameters: &Parameters) -> String {
+    let mut pairs: Vec<_> = parameters.iter().collect();
+    pairs.sort_by(|a, b| a.0.cmp(b.0));
+    pairs
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Returns the parameters that have the same value in every row, sorted by key. A
+/// single row has no constant parameters, as nothing varies.
+fn constant_parameters(rows: &[&Row]) -> Vec<(String, JsonValue)> {
+    let Some((first, rest)) = rows.split_first() else {
+        return Vec::new();
+    };
+    if rest.is_empty() {
+        return Vec::new();
+    }
+    let mut constant: Vec<(String, JsonValue)> = first
+        .parameters
+        .iter()
+        .filter(|(key, value)| {
+            rest.iter()
+                .all(|row| row.parameters.get(*key) == Some(value))
+        })
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    constant.sort_by(|a, b| a.0.cmp(&b.0));
+    constant
+}
 
-pub fn display(db_path: &Path, uid: &str) {
-    let conn = db::open_or_init(db_path).expect("failed to open DB");
+/// Reads the rows of a cached collection.
+fn cached_rows(conn: &rusqlite::Connection, uid: &str, strict: bool) -> Result<Vec<Row>, String> {
+    // caches are opened read-only, so older ones are not migrated yet
+    let column_or_null = |column| match db::has_column(conn, "simulations", column) {
+        Ok(true) => column,
+        _ => "NULL",
+    };
     let mut stmt = conn
-        .prepare(
-            "SELECT id, name, created_at, status, submitted, parameters_json
+        .prepare(&format!(
+            "SELECT id, name, COALESCE(created_at, ''), COALESCE(status, ''),
+                    COALESCE(submitted, 0), COALESCE(parameters_json, '{{}}'), {}, {}
              FROM simulations WHERE collection_uid = ?1",
-        )
+            column_or_null("hdf5_superblock"),
+            column_or_null("status_override"),
+        ))
         .unwrap();
-    let rows: Vec<Row> = stmt
-        .query_map([uid], |row| {
-            Ok(Row::new(
-                row.get(0)?,
-                row.get(1)?,
-                row.get(2)?,
-                row.get(3)?,
-                row.get(4)?,
-                row.get(5)?,
-            ))
-        })
-        .unwrap()
-        .map(|r| r.unwrap())
-        .collect();
+    stmt.query_map([uid], |row| {
+        let hdf5_superblock = row.get(6)?;
+        let status_override: Option<String> = row.get(7)?;
+        Ok(Row::new(
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+            row.get(3)?,
+            row.get(4)?,
+            row.get(5)?,
+            strict,
+        )
+        .map(|r| Row {
+            hdf5_superblock,
+            status_overridden: status_override.is_some(),
+            status: status_override.unwrap_or(r.status),
+            ..r
+        }))
+    })
+    .unwrap()
+    .map(|r| r.unwrap())
+    .collect()
+}
+
+/// Reads the rows of a collection directly from its entries on disk, without the cache.
+/// Entries are numbered in directory order; unreadable ones are reported and skipped.
+fn live_rows(uid: &str, root: &Path) -> Result<Vec<Row>, String> {
+    let c_path = discovery::find_one(uid, Some(root)).map_err(|e| e.to_string())?;
+    let mut rows = Vec::new();
+    let load_opts = entry::LoadOptions::default();
+    for entry_path in collection::find_entries(&c_path, &load_opts.data_files) {
+        match entry::try_load_entry_meta(&entry_path, &load_opts) {
+            Ok((meta, parameters)) => rows.push(Row {
+                id: rows.len() as i64 + 1,
+                name: entry_path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                created_at: meta.created_at.to_rfc3339(),
+                status: meta.status,
+                submitted: meta.submitted,
+                parameters,
+                hdf5_superblock: load_opts
+                    .data_files
+                    .iter()
+                    .map(|name| entry_path.join(name))
+                    .find(|path| path.exists())
+                    .and_then(|path| entry::read_superblock_version(&path)),
+                status_overridden: false,
+            }),
+            Err(reason) => eprintln!("  [!] Failed to read entry: {:?}: {}", entry_path, reason),
+        }
+    }
+    Ok(rows)
+}
 
-    let (all_keys, _columns) = flatten_hashmap_field(&rows, |r| &r.parameters);
+/// Reads the simulations of a collection from the cache, or from disk with
+/// [`DisplayOptions::live`], applying [`DisplayOptions::case_fold`].
+pub fn load_rows(db_path: &Path, uid: &str, opts: &DisplayOptions) -> Result<Vec<Row>, String> {
+    let root = opts.root.as_deref().unwrap_or(Path::new("."));
+    let rows = if opts.live {
+        live_rows(uid, root)?
+    } else {
+        let conn = db::open_existing(db_path)?;
+        if db::get_collection_path(&conn, uid).is_none() {
+            let hint = match discovery::find_one(uid, Some(root)) {
+                Ok(path) => format!(
+                    "; it exists at {}, run `simdex scan` or use --live",
+                    path.display()
+                ),
+                Err(_) => "; run `simdex scan`".to_string(),
+            };
+            return Err(format!("collection {} not in cache{}", uid, hint));
+        }
+        cached_rows(&conn, uid, opts.strict)?
+    };
+    if !opts.case_fold {
+        return Ok(rows);
+    }
+    Ok(rows
+        .into_iter()
+        .map(|mut row| {
+            let (folded, conflicts) = entry::fold_parameter_keys(row.parameters);
+            for key in conflicts {
+                eprintln!(
+                    "Warning: simulation '{}' has different values for the casings of parameter '{}'",
+                    row.name, key
+                );
+            }
+            row.parameters = folded;
+            row
+        })
+        .collect())
+}
 
-    use tabled::builder::Builder;
+/// Renders the rows matching [`DisplayOptions::filters`] in the chosen format, with the
+/// chosen columns and table styling. The options that select where rows come from are
+/// ignored, see [`load_rows`].
+pub fn render_table(rows: &[Row], opts: &DisplayOptions) -> String {
+    let mut rows: Vec<&Row> = rows
+        .iter()
+        .filter(|row| row.matches(&opts.filters))
+        .collect();
+    // the group of each row, with the rows sorted by it
+    let groups: Vec<String> = match &opts.group_by {
+        Some(key) => {
+            let label = |row: &Row| pivot_label(row.field(key));
+            rows.sort_by(|a, b| compare_labels(&label(a), &label(b)));
+            rows.iter().map(|row| label(row)).collect()
+        }
+        None => Vec::new(),
+    };
+    let (mut all_keys, _columns) = flatten_hashmap_field(&rows, |r| &r.parameters);
+    let mut fixed = if opts.hide_constant {
+        constant_parameters(&rows)
+    } else {
+        Vec::new()
+    };
+    // columns asked for by name are shown even if constant
+    if let Some(columns) = &opts.columns {
+        fixed.retain(|(key, _)| !columns.contains(key));
+    }
+    for (key, _) in &fixed {
+        all_keys.remove(key);
+    }
 
-    let mut builder = Builder::default();
-    let mut header = vec!["id", "status", "submitted", "created_at", "name"];
-    header.extend(all_keys.iter().map(|k| k.as_str()));
-    builder.push_record(header);
+    let mut header: Vec<String> = ["id", "status", "submitted", "created_at", "name"]
+        .map(String::from)
+        .to_vec();
+    if opts.hdf5_version {
+        header.push("hdf5_superblock".to_string());
+    }
+    if opts.params_inline {
+        header.push("parameters".to_string());
+    } else {
+        header.extend(all_keys.iter().cloned());
+    }
 
+    let mut records = Vec::with_capacity(rows.len());
     for row in rows {
+        let status = if row.status_overridden && opts.format == Format::Table {
+            format!("{} (set)", row.status)
+        } else {
+            row.status.clone()
+        };
         let mut values = vec![
             row.id.to_string(),
-            row.status.clone(),
+            status,
             row.submitted.to_string(),
-            row.created_at.clone(),
+            if time::is_unknown_created_at(&row.created_at) {
+                time::UNKNOWN_LABEL.to_string()
+            } else {
+                row.created_at.clone()
+            },
             row.name.clone(),
         ];
-        for key in &all_keys {
-            values.push(row.parameters.get(key).cloned().unwrap_or_default());
+        if opts.hdf5_version {
+            values.push(
+                row.hdf5_superblock
+                    .map(|v| v.to_string())
+                    .unwrap_or_default(),
+            );
+        }
+        if opts.params_inline {
+            values.push(join_parameters(&row.parameters));
+        } else {
+            for key in &all_keys {
+                values.push(
+                    row.parameters
+                        .get(key)
+                        .map(|v| v.to_string())
+                        .unwrap_or_default(),
+                );
+            }
+        }
+        records.push(values);
+    }
+    let (header, records) = match &opts.columns {
+        Some(columns) => select_columns(header, records, columns),
+        None => (header, records),
+    };
+
+    if opts.format != Format::Table {
+        return render_records(header, records, opts.format);
+    }
+    let mut config = Config::load().display;
+    if opts.output.is_some() {
+        config.header_color = Some("none".to_string());
+    }
+    let build = |header: Vec<String>, records: Vec<Vec<String>>| {
+        let mut builder = tabled::builder::Builder::default();
+        builder.push_record(header);
+        for record in records {
+            builder.push_record(record);
+        }
+        let mut table = builder.build();
+        if let Some(width) = opts.max_col_width.or(config.max_col_width) {
+            table.modify(Segment::all(), Width::truncate(width).suffix("..."));
+        }
+        style_table(&mut table, opts.style, &config);
+        table.to_string()
+    };
+
+    let mut rendered = String::new();
+    if !fixed.is_empty() {
+        let fixed = fixed
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join(", ");
+        rendered += &format!("fixed parameters: {}\n", fixed);
+    }
+    let Some(key) = &opts.group_by else {
+        return rendered + &build(header, records);
+    };
+    // each group's heading shows the key, so its column is left out of the subtables
+    let key_column = header.iter().position(|column| column == key);
+    let without_key = |mut record: Vec<String>| {
+        if let Some(i) = key_column {
+            record.remove(i);
+        }
+        record
+    };
+    let sub_header = without_key(header);
+    let mut tables = Vec::new();
+    let mut records = records.into_iter().zip(groups).peekable();
+    while let Some((record, group)) = records.next() {
+        let mut group_records = vec![without_key(record)];
+        while let Some((record, _)) = records.next_if(|(_, next)| *next == group) {
+            group_records.push(without_key(record));
         }
-        builder.push_record(values);
+        let heading = if group == NONE_BUCKET {
+            UNGROUPED.to_string()
+        } else {
+            format!("{} = {}", key, group)
+        };
+        tables.push(format!(
+            "{}\n{}",
+            heading,
+            build(sub_header.clone(), group_records)
+        ));
     }
+    rendered + &tables.join("\n\n")
+}
+
+/// Writes the simulations of a collection to `out`, or to [`DisplayOptions::output`].
+pub fn display(
+    db_path: &Path,
+    uid: &str,
+    opts: &DisplayOptions,
+    out: &mut dyn Write,
+) -> Result<(), String> {
+    let rows = load_rows(db_path, uid, opts)?;
+    if rows.is_empty() {
+        writeln!(out, "collection {} has no simulations", uid).map_err(write_error)?;
+        return Ok(());
+    }
+    if !rows.iter().any(|row| row.matches(&opts.filters)) {
+        writeln!(
+            out,
+            "no simulations in collection {} match the filters",
+            uid
+        )
+        .map_err(write_error)?;
+        return Ok(());
+    }
+    let rendered = render_table(&rows, opts);
+
+    match &opts.output {
+        Some(path) => {
+            if let Some(parent) = path.parent()
+                && !parent.as_os_str().is_empty()
+            {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| format!("cannot create {}: {}", parent.display(), e))?;
+            }
+            std::fs::write(path, rendered + "\n")
+                .map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+        }
+        None => writeln!(out, "{}", rendered).map_err(write_error)?,
+    }
+    Ok(())
+}
+
+/// Re-renders the simulations of a collection every `interval` until interrupted, like
+/// `watch simdex display`, reading the cache afresh each time. The screen is only
+/// redrawn when the output changed. If stdout is not a terminal, it is written once.
+pub fn follow(
+    db_path: &Path,
+    uid: &str,
+    opts: &DisplayOptions,
+    interval: std::time::Duration,
+) -> Result<(), String> {
+    use std::io::IsTerminal;
 
-    let mut table = builder.build();
-    table.with(Style::blank());
-    table.modify(Rows::first(), Color::FG_BRIGHT_BLACK);
-    println!("{}", table);
+    let mut stdout = std::io::stdout();
+    if !stdout.is_terminal() {
+        return display(db_path, uid, opts, &mut stdout);
+    }
+    let mut last = Vec::new();
+    loop {
+        let mut rendered = Vec::new();
+        display(db_path, uid, opts, &mut rendered)?;
+        if rendered != last {
+            // clear the screen and move the cursor home
+            write!(stdout, "\x1b[2J\x1b[H").map_err(write_error)?;
+            writeln!(
+                stdout,
+                "Every {}s, last change {}\n",
+                interval.as_secs_f64(),
+                chrono::Local::now().format("%H:%M:%S")
+            )
+            .map_err(write_error)?;
+            stdout.write_all(&rendered).map_err(write_error)?;
+            stdout.flush().map_err(write_error)?;
+            last = rendered;
+        }
+        std::thread::sleep(interval);
+    }
 }
 
 #[pyfunction]
 fn py_display(db_path: &str, collection: &str) -> PyResult<String> {
     let path = Path::new(db_path);
-    display(path, collection);
+    display(
+        path,
+        collection,
+        &DisplayOptions::default(),
+        &mut std::io::stdout(),
+    )
+    .map_err(pyo3::exceptions::PyRuntimeError::new_err)?;
     Ok("Display complete.".to_string())
 }
 
+/// Scans `root` into the cache and returns the `ScanReport` as a dict.
+#[pyfunction]
+fn py_scan<'py>(py: Python<'py>, root: &str, db_path: &str) -> PyResult<Bound<'py, PyDict>> {
+    let report = scan(Path::new(root), Path::new(db_path), &ScanOptions::default())
+        .map_err(pyo3::exceptions::PyRuntimeError::new_err)?;
+    let failed: Vec<(String, String)> = report
+        .failed
+        .into_iter()
+        .map(|(path, reason)| (path.display().to_string(), reason))
+        .collect();
+
+    let dict = PyDict::new(py);
+    dict.set_item("collections", report.collections)?;
+    dict.set_item("synced", report.synced)?;
+    dict.set_item("skipped", report.skipped)?;
+    dict.set_item("failed", failed)?;
+    Ok(dict)
+}
+
+/// Returns the sorted parameter keys used by the simulations of a collection.
+#[pyfunction]
+fn py_collection_param_keys(db_path: &str, collection: &str) -> PyResult<Vec<String>> {
+    let conn = db::open_existing(db_path).map_err(pyo3::exceptions::PyRuntimeError::new_err)?;
+    let keys = db::collection_param_keys(&conn, collection, None)
+        .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))?;
+    Ok(keys.into_iter().collect())
+}
+
+/// Converts a JSON value to the equivalent Python object.
+fn json_to_py<'py>(py: Python<'py>, value: &JsonValue) -> PyResult<Bound<'py, PyAny>> {
+    Ok(match value {
+        JsonValue::Null => py.None().into_bound(py),
+        JsonValue::Bool(b) => pyo3::types::PyBool::new(py, *b).to_owned().into_any(),
+        JsonValue::Number(n) => match n.as_i64() {
+            Some(i) => i.into_pyobject(py)?.into_any(),
+            None => n.as_f64().unwrap_or(f64::NAN).into_pyobject(py)?.into_any(),
+        },
+        JsonValue::String(s) => s.into_pyobject(py)?.into_any(),
+        JsonValue::Array(items) => {
+            let list = pyo3::types::PyList::empty(py);
+            for item in items {
+                list.append(json_to_py(py, item)?)?;
+            }
+            list.into_any()
+        }
+        JsonValue::Object(map) => {
+            let dict = PyDict::new(py);
+            for (key, item) in map {
+                dict.set_item(key, json_to_py(py, item)?)?;
+            }
+            dict.into_any()
+        }
+    })
+}
+
+/// Iterator over the simulations of a collection that yields one dict per simulation,
+/// reading them from the cache a page at a time instead of materializing a list.
+#[pyclass(unsendable)]
+struct SimulationIter {
+    conn: rusqlite::Connection,
+    collection: String,
+    page_size: usize,
+    last_id: i64,
+    page: std::collections::VecDeque<db::Simulation>,
+}
+
+#[pymethods]
+impl SimulationIter {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__<'py>(&mut self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyDict>>> {
+        if self.page.is_empty() {
+            let page = db::get_simulations_page(
+                &self.conn,
+                &self.collection,
+                self.last_id,
+                self.page_size,
+            )
+            .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))?;
+            self.page.extend(page);
+        }
+        let Some(sim) = self.page.pop_front() else {
+            return Ok(None);
+        };
+        self.last_id = sim.id;
+
+        let parameters = PyDict::new(py);
+        for (key, value) in &sim.parameters {
+            parameters.set_item(key, json_to_py(py, value)?)?;
+        }
+        let dict = PyDict::new(py);
+        dict.set_item("id", sim.id)?;
+        dict.set_item("name", sim.name)?;
+        dict.set_item("created_at", sim.created_at)?;
+        dict.set_item("description", sim.description)?;
+        dict.set_item("status", sim.status)?;
+        dict.set_item("submitted", sim.submitted)?;
+        dict.set_item("parameters", parameters)?;
+        Ok(Some(dict))
+    }
+}
+
+/// Returns an iterator over the simulations of a collection, ordered by id, that reads
+/// `page_size` of them from the cache at a time.
+#[pyfunction]
+#[pyo3(signature = (db_path, collection, page_size = 1000))]
+fn py_iter_simulations(
+    db_path: &str,
+    collection: &str,
+    page_size: usize,
+) -> PyResult<SimulationIter> {
+    let conn = db::open_existing(db_path).map_err(pyo3::exceptions::PyRuntimeError::new_err)?;
+    if db::get_collection_path(&conn, collection).is_none() {
+        return Err(pyo3::exceptions::PyKeyError::new_err(format!(
+            "collection {} not in cache",
+            collection
+        )));
+    }
+    Ok(SimulationIter {
+        conn,
+        collection: collection.to_string(),
+        page_size: page_size.max(1),
+        last_id: 0,
+        page: std::collections::VecDeque::new(),
+    })
+}
+
 #[pymodule]
 #[pyo3(name = "_simdex")]
 fn python_module(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(py_display, m)?)?;
+    m.add_function(wrap_pyfunction!(py_scan, m)?)?;
+    m.add_function(wrap_pyfunction!(py_collection_param_keys, m)?)?;
+    m.add_function(wrap_pyfunction!(py_iter_simulations, m)?)?;
+    m.add_class::<SimulationIter>()?;
     Ok(())
 }
 
-pub fn scan(root: &Path, db_path: &Path) {
-    let mut conn = db::open_or_init(db_path).expect("failed to open SQLite database");
+/// Options of the `scan` command.
+#[derive(Default)]
+pub struct ScanOptions {
+    /// How each collection is synced. With `batch_size` set, the scan is no longer
+    /// atomic: an interrupted scan leaves the batches committed so far in the database.
+    pub sync: collection::SyncOptions,
+    /// Skip the advisory lock that prevents concurrent scans of the same database
+    pub no_lock: bool,
+    /// Record which collection each collection is nested in, see [`tree`]. Needs the
+    /// full list of collections, so it does not combine with `stream`
+    pub nested: bool,
+    /// Sync and commit each collection as soon as the walk finds it, instead of
+    /// listing all collections first. Like `batch_size`, this makes the scan non-atomic
+    pub stream: bool,
+    /// Only sync collections whose path relative to the root passes this filter
+    pub paths: GlobFilter,
+    /// Abort on a marker file that is not valid YAML instead of warning
+    pub strict_markers: bool,
+    /// Abort on two collections resolving to the same uid instead of skipping the second
+    pub strict: bool,
+    /// Index collections under the uid written in their marker instead of the one in
+    /// its file name, see [`discovery::read_marker`]
+    pub marker_uid: bool,
+    /// Number of the slowest entries to report with `profile` set in the sync options
+    pub slowest: usize,
+    /// Sync only these entry directories instead of walking the root, see
+    /// [`group_entries`]. The collection filters, `nested` and `stream` do not apply
+    pub entries: Option<Vec<PathBuf>>,
+}
 
-    let collections = discovery::find_all(Path::new(root));
-    println!("Found {} collections:", collections.len());
+/// Outcome of a scan.
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    /// Number of collections found
+    pub collections: usize,
+    /// Number of entries read and written to the cache
+    pub synced: usize,
+    /// Number of entries skipped because they did not change since the last sync
+    pub skipped: usize,
+    /// Entries that could not be read, with the reason
+    pub failed: Vec<(PathBuf, String)>,
+    /// Entries skipped because a data file is still being written
+    pub in_use: Vec<PathBuf>,
+    /// `(collection, name)` of the entries added by this scan
+    pub added: Vec<(String, String)>,
+    /// `(collection, name)` of the known entries updated by this scan
+    pub updated: Vec<(String, String)>,
+    /// Time spent per phase, only measured with `profile` set in the sync options
+    pub profile: collection::Profile,
+    /// The entries that took longest to read, slowest first, only measured with
+    /// `profile` set in the sync options; see [`ScanOptions::slowest`]
+    pub slowest: Vec<(PathBuf, std::time::Duration)>,
+    /// Whether the scan stopped at the first failed entry, see
+    /// [`collection::SyncOptions::fail_fast`]
+    pub stopped_early: bool,
+}
 
-    let tx = conn.transaction().unwrap();
+impl ScanReport {
+    /// Writes the added, updated and failed entries, grouped.
+    pub fn write_changes(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        for (title, entries) in [("Added", &self.added), ("Updated", &self.updated)] {
+            if entries.is_empty() {
+                continue;
+            }
+            writeln!(out, "{} ({}):", title, entries.len())?;
+            for (collection, name) in entries {
+                writeln!(out, "  {}/{}", collection, name)?;
+            }
+        }
+        if !self.failed.is_empty() {
+            writeln!(out, "Failed ({}):", self.failed.len())?;
+            for (path, reason) in &self.failed {
+                writeln!(out, "  {}: {}", path.display(), reason)?;
+            }
+        }
+        Ok(())
+    }
 
-    for (c_path, c_uid) in &collections {
-        println!("Collection {}: {:?}", c_uid, c_path);
-        db::upsert_collection(&tx, c_uid, &c_path.display().to_string()).expect("db err");
-        let entries = collection::find_entries(c_path);
+    /// The added, updated and failed entries as JSON.
+    pub fn changes_json(&self) -> JsonValue {
+        let entries = |list: &[(String, String)]| -> Vec<JsonValue> {
+            list.iter()
+                .map(|(collection, name)| serde_json::json!({"collection": collection, "name": name}))
+                .collect()
+        };
+        let failed: Vec<JsonValue> = self
+            .failed
+            .iter()
+            .map(|(path, reason)| serde_json::json!({"path": path, "reason": reason}))
+            .collect();
+        serde_json::json!({
+            "added": entries(&self.added),
+            "updated": entries(&self.updated),
+            "failed": failed,
+            "in_use": self.in_use,
+            "stopped_early": self.stopped_early,
+        })
+    }
+}
 
-        for entry in entries {
-            let entry_name = entry
-                .file_name()
-                .expect("entry has no file name")
-                .to_string_lossy()
-                .to_string();
-
-            // check last sync time in db
-            let last_sync_time = db::get_sim_sync_time(&tx, c_uid, &entry_name);
-
-            // only process if changed or new
-            let mtime = match crate::core::entry::get_data_h5_mtime(&entry) {
-                Some(ut) => ut,
-                None => {
-                    eprintln!("  [!] Failed to get mtime for entry: {:?}", entry);
-                    continue;
-                }
-            };
+impl std::fmt::Display for ScanReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} collections: {} synced, {} unchanged, {} failed",
+            self.collections,
+            self.synced,
+            self.skipped,
+            self.failed.len()
+        )?;
+        if !self.in_use.is_empty() {
+            write!(f, ", {} in use", self.in_use.len())?;
+        }
+        if self.stopped_early {
+            write!(f, ", stopped at the first failure")?;
+        }
+        Ok(())
+    }
+}
 
-            // if last_sync_time is None, this will be false (not skipped)
-            if Some(mtime) < last_sync_time {
-                // unchanged -> skip
-                continue;
+pub fn scan(root: &Path, db_path: &Path, opts: &ScanOptions) -> Result<ScanReport, String> {
+    let db_path = &db::resolve_db_path(db_path, root);
+    let _lock = if opts.no_lock {
+        None
+    } else {
+        let lock = crate::core::lock::try_lock(db_path).map_err(|e| {
+            format!(
+                "cannot lock {}: {}",
+                crate::core::lock::lock_path(db_path).display(),
+                e
+            )
+        })?;
+        Some(lock)
+    };
+    let mut conn =
+        db::open_or_init(db_path).map_err(|e| format!("failed to open SQLite database: {}", e))?;
+
+    if opts.nested && opts.stream {
+        return Err("--nested cannot be combined with --stream".to_string());
+    }
+    if opts.entries.is_some() && (opts.nested || opts.stream) {
+        return Err("--entries cannot be combined with --nested or --stream".to_string());
+    }
+    let profile = opts.sync.profile;
+    let mut report = ScanReport::default();
+    let mut parents = Vec::new();
+    let selected = |path: &PathBuf| opts.paths.matches(path.strip_prefix(root).unwrap_or(path));
+    // the entries to sync per collection, with `entries` set
+    let mut targets = Vec::new();
+    let mut collections: Box<dyn Iterator<Item = (PathBuf, String)>> = if let Some(entries) =
+        &opts.entries
+    {
+        let groups = collection::Profile::time(profile, &mut report.profile.discovery, || {
+            group_entries(&conn, entries, opts, &mut report.failed)
+        })?;
+        let collections: Vec<_>;
+        (collections, targets) = groups
+            .into_iter()
+            .map(|(c_path, c_uid, entries)| ((c_path, c_uid), entries))
+            .unzip();
+        if !opts.sync.quiet {
+            println!(
+                "Syncing {} entries in {} collections:",
+                targets.iter().map(Vec::len).sum::<usize>(),
+                collections.len()
+            );
+        }
+        Box::new(collections.into_iter())
+    } else if opts.stream {
+        Box::new(discovery::iter_all(root).filter(move |(path, _)| selected(path)))
+    } else {
+        // excluded collections are kept to find the parents of nested ones
+        let all: Vec<_> = collection::Profile::time(profile, &mut report.profile.discovery, || {
+            discovery::iter_all(root).collect()
+        });
+        let mut uids = vec![None; all.len()];
+        for (i, (c_path, c_uid)) in all.iter().enumerate() {
+            if selected(c_path) {
+                let marker =
+                    discovery::read_marker(c_path, c_uid, opts.strict_markers, opts.marker_uid)?;
+                uids[i] = Some(marker.uid);
             }
+        }
+        if opts.nested {
+            let paths: Vec<PathBuf> = all.iter().map(|(path, _)| path.clone()).collect();
+            // the stored parent is left alone if it is excluded from this scan
+            parents = discovery::find_parents(&paths)
+                .into_iter()
+                .zip(&uids)
+                .filter(|(_, uid)| uid.is_some())
+                .map(|(parent, _)| match parent {
+                    Some(j) => uids[j].clone().map(Some),
+                    None => Some(None),
+                })
+                .collect();
+        }
+        let collections: Vec<_> = all
+            .into_iter()
+            .zip(uids)
+            .filter_map(|((c_path, _), uid)| Some((c_path, uid?)))
+            .collect();
+        if !opts.sync.quiet {
+            println!("Found {} collections:", collections.len());
+        }
+        Box::new(collections.into_iter())
+    };
 
-            match entry::load_entry_meta(&entry) {
-                Some((meta, params)) => {
-                    let sim_id = db::upsert_simulation(&tx, c_uid, &entry_name, &meta, &params)
-                        .expect("db insert sim");
-                    println!("  Synced entry: {:?} [{}]", entry, sim_id);
-                }
-                None => {
-                    println!("  [!] Failed to read entry: {:?}", entry);
-                }
+    let scanned_at = chrono::offset::Local::now().to_rfc3339();
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    // simulations are keyed by collection uid and name, so entries of a copied
+    // collection directory would silently overwrite those of the original
+    let mut seen: std::collections::HashMap<String, PathBuf> = std::collections::HashMap::new();
+
+    for i in 0.. {
+        // in stream mode, the walk happens while iterating
+        let next = collection::Profile::time(profile, &mut report.profile.discovery, || {
+            collections.next()
+        });
+        let Some((c_path, c_uid)) = next else {
+            break;
+        };
+        // only streamed collections are not resolved by their marker yet
+        let c_uid = if opts.stream {
+            discovery::read_marker(&c_path, &c_uid, opts.strict_markers, opts.marker_uid)?.uid
+        } else {
+            c_uid
+        };
+        report.collections += 1;
+        if let Some(first) = seen.get(&c_uid) {
+            let reason = format!("uid '{}' is already used by {}", c_uid, first.display());
+            if opts.strict {
+                return Err(format!("{}: {}", c_path.display(), reason));
+            }
+            eprintln!("  [!] Skipping collection {:?}: {}", c_path, reason);
+            report.failed.push((c_path, reason));
+            if opts.sync.fail_fast {
+                report.stopped_early = true;
+                break;
+            }
+            continue;
+        }
+        seen.insert(c_uid.clone(), c_path.clone());
+        let parent: Option<Option<&str>> = parents
+            .get(i)
+            .and_then(|p| p.as_ref())
+            .map(|p| p.as_deref());
+        if !opts.sync.quiet {
+            match parent.flatten() {
+                Some(parent) => println!("Collection {} (in {}): {:?}", c_uid, parent, c_path),
+                None => println!("Collection {}: {:?}", c_uid, c_path),
             }
         }
+        let result = match targets.get_mut(i) {
+            Some(entries) => {
+                collection::sync_entries(&tx, &c_uid, &c_path, std::mem::take(entries), &opts.sync)?
+            }
+            None => collection::sync(&tx, &c_uid, &c_path, &opts.sync)?,
+        };
+        if let Some(parent) = parent {
+            db::set_collection_parent(&tx, &c_uid, parent)
+                .map_err(|e| format!("failed to write collection {}: {}", c_uid, e))?;
+        }
+        if opts.stream {
+            tx.execute_batch("COMMIT; BEGIN")
+                .map_err(|e| format!("failed to commit collection {}: {}", c_uid, e))?;
+        }
+        report.profile.add(&result.profile);
+        report.synced += result.synced();
+        report.skipped += result.skipped;
+        report.failed.extend(result.failed);
+        report.in_use.extend(result.in_use);
+        report.slowest.extend(result.load_times);
+        report
+            .slowest
+            .sort_by_key(|(_, duration)| std::cmp::Reverse(*duration));
+        report.slowest.truncate(opts.slowest);
+        let with_uid = |name: String| (c_uid.clone(), name);
+        report.added.extend(result.added.into_iter().map(with_uid));
+        report
+            .updated
+            .extend(result.updated.into_iter().map(with_uid));
+        if opts.sync.fail_fast && !report.failed.is_empty() {
+            report.stopped_early = true;
+            break;
+        }
     }
-    tx.commit().ok();
+    let run = db::ScanRun {
+        scanned_at,
+        root: root
+            .canonicalize()
+            .unwrap_or(root.to_path_buf())
+            .display()
+            .to_string(),
+        collections: report.collections,
+        synced: report.synced,
+        failed: report.failed.len(),
+    };
+    db::record_scan_run(&tx, &run).map_err(|e| format!("failed to record scan: {}", e))?;
+    tx.commit().map_err(|e| e.to_string())?;
 
-    println!(" Sync complete.");
+    if !opts.sync.quiet {
+        println!(" Sync complete.");
+    }
+    Ok(report)
 }
 
-pub fn ls_collections(db_path: &Path) {
-    let conn = db::open_or_init(db_path).expect("failed to open DB");
-    let mut stmt = conn.prepare("SELECT uid, path FROM collections").unwrap();
-    let rows = stmt
-        .query_map([], |row| {
-            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
-        })
-        .unwrap();
+/// Reads a list of paths, one per line, e.g. from stdin. Blank lines are ignored.
+pub fn read_path_list(reader: impl std::io::BufRead) -> Result<Vec<PathBuf>, String> {
+    let mut paths = Vec::new();
+    for line in reader.lines() {
+        let line = line.map_err(|e| format!("cannot read path list: {}", e))?;
+        let line = line.trim();
+        if !line.is_empty() {
+            paths.push(PathBuf::from(line));
+        }
+    }
+    Ok(paths)
+}
 
-    println!("Collections:");
-    for row in rows {
-        let (uid, path) = row.unwrap();
-        println!(" - {} @ {}", uid, path);
+/// Groups the entry directories of [`ScanOptions::entries`] by collection, in the order
+/// they are listed, and returns each collection's path, uid and entries. The uid is read
+/// from the marker of the entry's parent directory, or else taken from the collection
+/// with that path in the database. Paths that are not a directory in a known collection
+/// are reported, added to `failed` and skipped.
+fn group_entries(
+    conn: &rusqlite::Connection,
+    entries: &[PathBuf],
+    opts: &ScanOptions,
+    failed: &mut Vec<(PathBuf, String)>,
+) -> Result<Vec<(PathBuf, String, Vec<PathBuf>)>, String> {
+    let known = db::get_collections(conn).map_err(|e| e.to_string())?;
+    let mut groups: Vec<(PathBuf, String, Vec<PathBuf>)> = Vec::new();
+    for entry_path in entries {
+        let parent = match entry_path.parent() {
+            _ if !entry_path.is_dir() || entry_path.file_name().is_none() => None,
+            Some(parent) if parent.as_os_str().is_empty() => Some(Path::new(".")),
+            parent => parent,
+        };
+        let Some(parent) = parent else {
+            let reason = "not an entry directory".to_string();
+            eprintln!("  [!] Skipping {:?}: {}", entry_path, reason);
+            failed.push((entry_path.clone(), reason));
+            continue;
+        };
+        if let Some(group) = groups.iter_mut().find(|(c_path, _, _)| c_path == parent) {
+            if !group.2.contains(entry_path) {
+                group.2.push(entry_path.clone());
+            }
+            continue;
+        }
+        let uid = match discovery::get_uid(parent) {
+            Ok(uid) => {
+                discovery::read_marker(parent, &uid, opts.strict_markers, opts.marker_uid)?.uid
+            }
+            Err(_) => {
+                let canonical = parent.canonicalize().ok();
+                let found = known.iter().find(|c| {
+                    canonical.is_some() && Path::new(&c.path).canonicalize().ok() == canonical
+                });
+                match found {
+                    Some(c) => c.uid.clone(),
+                    None => {
+                        let reason = format!("{:?} is not a known collection", parent);
+                        eprintln!("  [!] Skipping {:?}: {}", entry_path, reason);
+                        failed.push((entry_path.clone(), reason));
+                        continue;
+                    }
+                }
+            }
+        };
+        groups.push((parent.to_path_buf(), uid, vec![entry_path.clone()]));
     }
+    Ok(groups)
 }
 
-pub fn ls_params(db_path: &Path, collection: &str) {
-    let conn = db::open_or_init(db_path).expect("failed to open DB");
-    let mut stmt = conn
-        .prepare("SELECT parameters_json FROM simulations WHERE collection_uid = ?1")
-        .unwrap();
-    let mut rows = stmt.query([collection]).unwrap();
-
-    let mut all_keys = std::collections::HashSet::new();
-    let mut examples = std::collections::HashMap::new();
-
-    while let Some(row) = rows.next().unwrap() {
-        let json: String = row.get(0).unwrap();
-        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap_or_default();
-        if let Some(obj) = parsed.as_object() {
-            for (k, v) in obj {
-                all_keys.insert(k.clone());
-                examples.entry(k.clone()).or_insert_with(|| v.to_string());
+/// Tries to read every entry below `root` without touching any database and prints
+/// a pass/fail summary per collection. Returns the total number of failed entries.
+pub fn check(root: &Path, load_opts: &entry::LoadOptions) -> usize {
+    let collections = discovery::find_all(root);
+    println!("Checking {} collections:", collections.len());
+
+    let (mut total_ok, mut total_failed) = (0, 0);
+    for (c_path, c_uid) in &collections {
+        let mut failures = Vec::new();
+        let entries = collection::find_entries(c_path, &load_opts.data_files);
+        let n_entries = entries.len();
+        for entry in entries {
+            if let Err(reason) = entry::try_load_entry_meta(&entry, load_opts) {
+                failures.push((entry, reason));
             }
         }
+
+        println!(
+            "Collection {} @ {:?}: {} ok, {} failed",
+            c_uid,
+            c_path,
+            n_entries - failures.len(),
+            failures.len()
+        );
+        for (entry, reason) in &failures {
+            println!("  [!] {:?}: {}", entry, reason);
+        }
+        total_ok += n_entries - failures.len();
+        total_failed += failures.len();
     }
 
-    println!("Parameter space of '{}':", collection);
-    for key in all_keys {
-        let placeholder = "<none>".to_string();
-        let example = examples.get(&key).unwrap_or(&placeholder);
-        println!(" - {:20} e.g. {}", key, example);
+    println!("Total: {} ok, {} failed", total_ok, total_failed);
+    total_failed
+}
+
+/// Forces a re-sync of one entry: clears its last sync time so the next scan re-reads it,
+/// or with `now` re-reads it immediately.
+pub fn touch(
+    db_path: &Path,
+    collection: &str,
+    name: &str,
+    now: bool,
+    load_opts: &entry::LoadOptions,
+) -> Result<(), String> {
+    let db_path = &db::find_db_path(db_path, Path::new("."));
+    if !db_path.is_file() {
+        return Err(format!("no such database: {}", db_path.display()));
+    }
+    // re-reading the entry writes like a scan, so it waits for none
+    let _lock = if now {
+        let lock = crate::core::lock::try_lock(db_path).map_err(|e| {
+            format!(
+                "cannot lock {}: {}",
+                crate::core::lock::lock_path(db_path).display(),
+                e
+            )
+        })?;
+        Some(lock)
+    } else {
+        None
+    };
+    let conn = db::open_or_init(db_path).map_err(|e| format!("failed to open DB: {}", e))?;
+    let reset = db::reset_sync_time(&conn, collection, name).map_err(|e| e.to_string())?;
+    if reset == 0 && !now {
+        return Err(format!(
+            "no simulation '{}' in collection '{}'",
+            name, collection
+        ));
     }
+    if !now {
+        println!(
+            "Marked {} / {} for re-sync on the next scan",
+            collection, name
+        );
+        return Ok(());
+    }
+
+    let c_path = db::get_collection_path(&conn, collection).ok_or_else(|| {
+        format!(
+            "collection '{}' not in cache; run `simdex scan`",
+            collection
+        )
+    })?;
+    let entry_path = c_path.join(name);
+    let (meta, params) = entry::try_load_entry_meta(&entry_path, load_opts)
+        .map_err(|e| format!("failed to read {:?}: {}", entry_path, e))?;
+    let upserted = db::upsert_simulation(&conn, collection, name, &meta, &params)
+        .map_err(|e| e.to_string())?;
+    println!("  Synced entry: {:?} [{}]", entry_path, upserted.id);
+    Ok(())
 }
 
-pub fn migrate(root: &Path) {
-    use crate::core::entry::load_entry_meta;
-    use std::fs::write;
+/// Reports simulations whose stored `parameters_json` is not valid JSON, in all
+/// collections or only in `collection`. These show no parameters elsewhere. With `fix`,
+/// each one is re-read from its entry like [`touch`]; entries that cannot be read are
+/// reported, or with `clear_unreadable` have their `parameters_json` set to NULL.
+/// Returns the number of simulations left with invalid JSON.
+pub fn repair_json(
+    db_path: &Path,
+    collection: Option<&str>,
+    fix: bool,
+    clear_unreadable: bool,
+    load_opts: &entry::LoadOptions,
+) -> Result<usize, String> {
+    let db_path = &db::find_db_path(db_path, Path::new("."));
+    // repairs write to the cache, so they wait for no scan and create no database
+    let _lock = if fix {
+        if !db_path.is_file() {
+            return Err(format!("no such database: {}", db_path.display()));
+        }
+        let lock = crate::core::lock::try_lock(db_path).map_err(|e| {
+            format!(
+                "cannot lock {}: {}",
+                crate::core::lock::lock_path(db_path).display(),
+                e
+            )
+        })?;
+        Some(lock)
+    } else {
+        None
+    };
+    let mut conn = if fix {
+        db::open_or_init(db_path).map_err(|e| format!("failed to open DB: {}", e))?
+    } else {
+        db::open_existing(db_path)?
+    };
+    let invalid = db::find_invalid_parameters(&conn, collection)
+        .map_err(|e| format!("failed to read simulations: {}", e))?;
+    if invalid.is_empty() {
+        println!("No simulations with invalid parameters_json");
+        return Ok(0);
+    }
+    println!(
+        "{} simulations with invalid parameters_json:",
+        invalid.len()
+    );
+    for sim in &invalid {
+        println!(
+            "  [!] {} {} / {}: {}",
+            sim.id, sim.collection_uid, sim.name, sim.error
+        );
+    }
+    if !fix {
+        return Ok(invalid.len());
+    }
 
-    let collections = discovery::find_all(root);
-    for (c_path, _) in &collections {
-        let entries = collection::find_entries(c_path);
-        for entry in entries {
-            if let Some((meta, params)) = load_entry_meta(&entry) {
-                let yaml_out = serde_yaml::to_string(&serde_json::json!({
-                    "metadata": {
-                        "created_at": meta.created_at.to_rfc3339(),
-                        "description": meta.description,
-                        "status": meta.status,
-                        "submitted": meta.submitted,
-                    },
-                    "parameters": params
-                }))
-                .unwrap();
-                let out_path = entry.join("meta.yml");
-                write(out_path, yaml_out).expect("write failed");
-                println!("Migrated {:?}", entry);
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let mut remaining = 0;
+    for sim in &invalid {
+        let loaded = db::get_collection_path(&tx, &sim.collection_uid)
+            .ok_or_else(|| format!("collection '{}' not in cache", sim.collection_uid))
+            .and_then(|c_path| {
+                let entry_path = c_path.join(&sim.name);
+                entry::try_load_entry_meta(&entry_path, load_opts)
+                    .map_err(|e| format!("failed to read {:?}: {}", entry_path, e))
+            });
+        match loaded {
+            Ok((meta, params)) => {
+                db::upsert_simulation(&tx, &sim.collection_uid, &sim.name, &meta, &params)
+                    .map_err(|e| e.to_string())?;
+                println!("  Re-read {} / {}", sim.collection_uid, sim.name);
+            }
+            Err(e) if clear_unreadable => {
+                db::clear_parameters_json(&tx, sim.id).map_err(|e| e.to_string())?;
+                println!(
+                    "  Cleared the parameters of {} / {}: {}",
+                    sim.collection_uid, sim.name, e
+                );
+            }
+            Err(e) => {
+                println!(
+                    "  [!] {} / {} not repaired: {}",
+                    sim.collection_uid, sim.name, e
+                );
+                remaining += 1;
             }
         }
     }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(remaining)
+}
+
+/// Renders a stored path relative to `base`, so output stays meaningful on other
+/// mounts. Paths that are not below `base` are returned unchanged.
+fn relative_path(path: &str, base: Option<&Path>) -> String {
+    let Some(base) = base else {
+        return path.to_string();
+    };
+    let path = Path::new(path);
+    if let Ok(rel) = path.strip_prefix(base) {
+        return rel.display().to_string();
+    }
+    // stored paths may be relative to where scan ran, compare the resolved paths too
+    if let (Ok(path), Ok(base)) = (path.canonicalize(), base.canonicalize())
+        && let Ok(rel) = path.strip_prefix(&base)
+    {
+        return rel.display().to_string();
+    }
+    path.display().to_string()
+}
+
+/// Writes the cached collections to `out`, one ` - uid @ path` line each.
+pub fn ls_collections(
+    db_path: &Path,
+    relative_to: Option<&Path>,
+    out: &mut dyn Write,
+) -> Result<(), String> {
+    let conn = db::open_existing(db_path)?;
+    let collections =
+        db::get_collections(&conn).map_err(|e| format!("failed to read collections: {}", e))?;
+
+    writeln!(out, "Collections:").map_err(write_error)?;
+    for c in collections {
+        let path = relative_path(&c.path, relative_to);
+        match c.description {
+            Some(description) => writeln!(out, " - {} @ {}  {}", c.uid, path, description),
+            None => writeln!(out, " - {} @ {}", c.uid, path),
+        }
+        .map_err(write_error)?;
+    }
+    Ok(())
+}
+
+/// Prints the uids of the cached collections starting with `prefix`, one per line, for
+/// shell completion scripts (`simdex __complete-uids`).
+pub fn complete_uids(db_path: &Path, prefix: &str) -> Result<(), String> {
+    let conn = db::open_existing(db_path)?;
+    let collections =
+        db::get_collections(&conn).map_err(|e| format!("failed to read collections: {}", e))?;
+    for c in collections.iter().filter(|c| c.uid.starts_with(prefix)) {
+        println!("{}", c.uid);
+    }
+    Ok(())
+}
+
+/// Prints the cached collections as a tree of nested collections, as recorded by
+/// `scan --nested`. Collections whose parent is not cached are shown at the top level.
+pub fn tree(db_path: &Path, relative_to: Option<&Path>) -> Result<(), String> {
+    fn print_subtree(
+        parent: &db::CollectionRow,
+        collections: &[db::CollectionRow],
+        depth: usize,
+        relative_to: Option<&Path>,
+    ) {
+        println!(
+            " {}- {} @ {}",
+            "  ".repeat(depth),
+            parent.uid,
+            relative_path(&parent.path, relative_to)
+        );
+        for child in collections
+            .iter()
+            .filter(|c| c.parent_uid.as_deref() == Some(parent.uid.as_str()))
+        {
+            print_subtree(child, collections, depth + 1, relative_to);
+        }
+    }
+
+    let conn = db::open_existing(db_path)?;
+    let collections =
+        db::get_collections(&conn).map_err(|e| format!("failed to read collections: {}", e))?;
+
+    println!("Collections:");
+    let is_cached = |uid: &str| collections.iter().any(|c| c.uid == uid);
+    for c in &collections {
+        if !c.parent_uid.as_deref().is_some_and(is_cached) {
+            print_subtree(c, &collections, 0, relative_to);
+        }
+    }
+    Ok(())
+}
+
+/// Output format of the `schema` command.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SchemaFormat {
+    #[default]
+    Table,
+    /// A draft-07 JSON Schema of the parameters object
+    Json,
+}
+
+/// Returns the types of a parameter, treating a mix of integers and floats as float.
+fn schema_types(types: &std::collections::BTreeSet<String>) -> Vec<&str> {
+    let numeric_mix = types.contains("int") && types.contains("float");
+    types
+        .iter()
+        .map(String::as_str)
+        .filter(|t| !(numeric_mix && *t == "int"))
+        .collect()
+}
+
+/// Maps a type from [`db::ParamSummary::types`] to its JSON Schema name.
+fn json_schema_type(t: &str) -> &str {
+    match t {
+        "int" => "integer",
+        "float" => "number",
+        "bool" => "boolean",
+        other => other,
+    }
+}
+
+/// Converts a bound of [`db::ParamSummary`] to JSON, keeping whole numbers integral.
+fn schema_number(value: f64) -> serde_json::Value {
+    if value.fract() == 0.0 && value.abs() < i64::MAX as f64 {
+        serde_json::json!(value as i64)
+    } else {
+        serde_json::json!(value)
+    }
+}
+
+/// Prints the inferred type of each parameter of a collection, flagging keys with
+/// inconsistent types and keys that are missing in some simulations.
+///
+/// With [`SchemaFormat::Json`], a parameter with at most `max_enum` distinct values is
+/// restricted to those values, and numeric parameters to their observed range, so the
+/// schema can validate new runs of the same sweep.
+pub fn schema(
+    db_path: &Path,
+    collection: &str,
+    format: SchemaFormat,
+    max_enum: usize,
+) -> Result<(), String> {
+    let conn = db::open_existing(db_path)?;
+    let total = db::count_simulations(&conn, collection)
+        .map_err(|e| format!("failed to query simulations: {}", e))?;
+    if total == 0 {
+        return Err(format!("collection {} has no simulations", collection));
+    }
+    let summary = db::aggregate_parameters(&conn, collection, None)
+        .map_err(|e| format!("failed to query parameters: {}", e))?;
+
+    match format {
+        SchemaFormat::Table => {
+            let header = ["key", "type", "present", "optional", "consistent"]
+                .map(String::from)
+                .to_vec();
+            let records = summary
+                .iter()
+                .map(|(key, param)| {
+                    let types = schema_types(&param.types);
+                    vec![
+                        key.clone(),
+                        types.join("|"),
+                        format!("{}/{}", param.count, total),
+                        (param.count < total).to_string(),
+                        (types.len() == 1).to_string(),
+                    ]
+                })
+                .collect();
+            print_records(header, records, Format::Table);
+        }
+        SchemaFormat::Json => {
+            let enums = db::low_cardinality_values(&conn, collection, max_enum)
+                .map_err(|e| format!("failed to query parameters: {}", e))?;
+            let mut properties = serde_json::Map::new();
+            let mut required = Vec::new();
+            for (key, param) in &summary {
+                let types: Vec<&str> = schema_types(&param.types)
+                    .into_iter()
+                    .map(json_schema_type)
+                    .collect();
+                let type_value = match types.as_slice() {
+                    [t] => serde_json::json!(t),
+                    _ => serde_json::json!(types),
+                };
+                let mut property = serde_json::json!({ "type": type_value });
+                if let Some(values) = enums.get(key) {
+                    property["enum"] = serde_json::json!(values);
+                }
+                if param.is_numeric
+                    && let (Some(min), Some(max)) = (param.min, param.max)
+                {
+                    property["minimum"] = schema_number(min);
+                    property["maximum"] = schema_number(max);
+                }
+                properties.insert(key.clone(), property);
+                if param.count == total {
+                    required.push(key.clone());
+                }
+            }
+            let schema = serde_json::json!({
+                "$schema": "http://json-schema.org/draft-07/schema#",
+                "title": collection,
+                "type": "object",
+                "properties": properties,
+                "required": required,
+            });
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&schema).map_err(|e| e.to_string())?
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Reports parameters whose values have different JSON types across the simulations
+/// of a collection, e.g. `seed` written as `42` in one run and `"42"` in another.
+/// Integers and floats both count as numbers. Returns the number of such parameters.
+pub fn lint(db_path: &Path, collection: &str) -> Result<usize, String> {
+    let conn = db::open_existing(db_path)?;
+    let usage = db::param_type_usage(&conn, collection)
+        .map_err(|e| format!("failed to query parameters: {}", e))?;
+
+    let mut by_key: std::collections::BTreeMap<&str, Vec<&db::ParamTypeUsage>> =
+        std::collections::BTreeMap::new();
+    for u in &usage {
+        by_key.entry(u.key.as_str()).or_default().push(u);
+    }
+
+    let mut n_conflicts = 0;
+    for (key, types) in by_key {
+        let is_number = |t: &str| t == "int" || t == "float";
+        let n_kinds = types.iter().filter(|u| !is_number(&u.json_type)).count()
+            + usize::from(types.iter().any(|u| is_number(&u.json_type)));
+        if n_kinds < 2 {
+            continue;
+        }
+        n_conflicts += 1;
+        println!("[!] parameter '{}' has inconsistent types:", key);
+        for u in types {
+            println!(
+                "    {:8} {} simulation(s), e.g. id {}",
+                u.json_type, u.count, u.example_id
+            );
+        }
+    }
+    if n_conflicts == 0 {
+        println!("No type conflicts in collection {}", collection);
+    }
+    Ok(n_conflicts)
+}
+
+/// Returns whether `query` starts with `SELECT` or `WITH`, the only statements `sql` runs.
+fn is_read_query(query: &str) -> bool {
+    let keyword: String = query
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect();
+    keyword.eq_ignore_ascii_case("select") || keyword.eq_ignore_ascii_case("with")
+}
+
+/// Counts the rows of a query result per value of `key`: a column of the result, or
+/// else a parameter in its `parameters_json` column. Returns the `key`/`count` header
+/// and records, most frequent values first.
+fn count_records_by(
+    header: &[String],
+    records: &[Vec<JsonValue>],
+    key: &str,
+) -> Result<(Vec<String>, Vec<Vec<JsonValue>>), String> {
+    let column = |name: &str| header.iter().position(|h| h == name);
+    let (i, from_parameters) = match column(key) {
+        Some(i) => (i, false),
+        None => match column("parameters_json") {
+            Some(i) => (i, true),
+            None => return Err(format!("no column or parameter '{}' in the result", key)),
+        },
+    };
+
+    // keyed by the JSON text of the value, as JSON values cannot be hashed
+    let mut counts: std::collections::HashMap<String, (JsonValue, i64)> =
+        std::collections::HashMap::new();
+    for record in records {
+        let value = if from_parameters {
+            record[i]
+                .as_str()
+                .and_then(|json| serde_json::from_str::<Parameters>(json).ok())
+                .and_then(|mut parameters| parameters.remove(key))
+                .unwrap_or(JsonValue::Null)
+        } else {
+            record[i].clone()
+        };
+        counts.entry(value.to_string()).or_insert((value, 0)).1 += 1;
+    }
+    let mut counts: Vec<(String, (JsonValue, i64))> = counts.into_iter().collect();
+    counts.sort_by(|(a_text, (_, a)), (b_text, (_, b))| b.cmp(a).then_with(|| a_text.cmp(b_text)));
+    let records = counts
+        .into_iter()
+        .map(|(_, (value, n))| vec![value, n.into()])
+        .collect();
+    Ok((vec![key.to_string(), "count".to_string()], records))
+}
+
+/// What `sql` prints instead of the rows.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SqlCount {
+    /// The number of rows
+    Total,
+    /// The number of rows per value of a column or parameter, see `count_records_by`
+    By(String),
+}
+
+/// Runs a read-only SQL query against the cache and prints the resulting rows, or only
+/// their number with `count`.
+pub fn sql(
+    db_path: &Path,
+    query: &str,
+    format: Format,
+    count: Option<SqlCount>,
+) -> Result<(), String> {
+    use rusqlite::types::ValueRef;
+
+    if !is_read_query(query) {
+        return Err("only SELECT and WITH queries are allowed".to_string());
+    }
+    let conn = db::open_existing(db_path)?;
+    let mut stmt = conn.prepare(query).map_err(|e| e.to_string())?;
+    if !stmt.readonly() {
+        return Err("only read-only queries are allowed".to_string());
+    }
+
+    let header: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+    let n_columns = header.len();
+    let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+    let mut records = Vec::new();
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let mut record = Vec::with_capacity(n_columns);
+        for i in 0..n_columns {
+            let field = match row.get_ref(i).map_err(|e| e.to_string())? {
+                ValueRef::Null => JsonValue::Null,
+                ValueRef::Integer(i) => i.into(),
+                ValueRef::Real(f) => f.into(),
+                ValueRef::Text(t) => String::from_utf8_lossy(t).into(),
+                ValueRef::Blob(b) => format!("<blob, {} bytes>", b.len()).into(),
+            };
+            record.push(field);
+        }
+        records.push(record);
+    }
+
+    let (header, records) = match count {
+        None => (header, records),
+        Some(SqlCount::Total) => {
+            println!("{}", records.len());
+            return Ok(());
+        }
+        Some(SqlCount::By(key)) => count_records_by(&header, &records, &key)?,
+    };
+
+    // JSON keeps the SQL types, the other formats show text
+    if format == Format::Json {
+        println!("{}", json_records(&header, &records));
+        return Ok(());
+    }
+    let records = records
+        .into_iter()
+        .map(|record| {
+            record
+                .into_iter()
+                .map(|field| match field {
+                    JsonValue::Null => String::new(),
+                    JsonValue::String(s) => s,
+                    other => other.to_string(),
+                })
+                .collect()
+        })
+        .collect();
+    print_records(header, records, format);
+    Ok(())
+}
+
+/// Timestamp by which `latest` orders simulations.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LatestBy {
+    #[default]
+    Created,
+    Synced,
+}
+
+/// Prints the `n` most recently created or synced simulations across all collections.
+pub fn latest(db_path: &Path, n: usize, by: LatestBy) -> Result<(), String> {
+    let conn = db::open_existing(db_path)?;
+    let by_sync = by == LatestBy::Synced;
+    let sims = db::get_recent_simulations(&conn, by_sync, n)
+        .map_err(|e| format!("failed to query simulations: {}", e))?;
+    if sims.is_empty() {
+        println!("No simulations in {}", db_path.display());
+        return Ok(());
+    }
+
+    let time_header = if by_sync { "synced_at" } else { "created_at" };
+    let header = ["collection", "id", "name", "status", time_header]
+        .map(String::from)
+        .to_vec();
+    let records = sims
+        .into_iter()
+        .map(|sim| {
+            let time = if by_sync {
+                sim.synced_at
+            } else {
+                sim.created_at
+            };
+            vec![
+                sim.collection_uid,
+                sim.id.to_string(),
+                sim.name,
+                sim.status.unwrap_or_default(),
+                time.unwrap_or_default(),
+            ]
+        })
+        .collect();
+    print_records(header, records, Format::Table);
+    Ok(())
+}
+
+/// Migrates a database built with the legacy, path-keyed schema to the current one.
+pub fn upgrade_db(db_path: &Path) -> Result<(), String> {
+    let db_path = &db::find_db_path(db_path, Path::new("."));
+    if !db_path.is_file() {
+        return Err(format!("no such database: {}", db_path.display()));
+    }
+    let conn = rusqlite::Connection::open(db_path).map_err(|e| e.to_string())?;
+    if let Some(mismatch) = db::schema_mismatch(&conn).map_err(|e| e.to_string())? {
+        return Err(mismatch);
+    }
+    match db::upgrade_legacy_simulations(&conn).map_err(|e| e.to_string())? {
+        Some(n) => println!("Migrated {} simulations to the current schema", n),
+        None => println!("{} already uses the current schema", db_path.display()),
+    }
+    // adds the remaining tables and columns
+    db::open_or_init(db_path)?;
+    Ok(())
+}
+
+/// Sets the description of a collection shown by `ls`.
+pub fn describe(db_path: &Path, uid: &str, description: &str) -> Result<(), String> {
+    let db_path = &db::find_db_path(db_path, Path::new("."));
+    if !db_path.is_file() {
+        return Err(format!("no such database: {}", db_path.display()));
+    }
+    let conn = db::open_or_init(db_path).map_err(|e| format!("failed to open DB: {}", e))?;
+    let found = db::set_collection_description(&conn, uid, description)
+        .map_err(|e| format!("failed to update collection: {}", e))?;
+    if !found {
+        return Err(format!("collection '{}' not found in the database", uid));
+    }
+    Ok(())
+}
+
+/// Sets the status of the simulation named `pattern`, or else of those whose name matches
+/// the glob `pattern`, by hand, overriding the one read from their entries until cleared
+/// with `status = None`. All matches are updated in one transaction; with `dry_run`,
+/// nothing is changed. Returns the names of the matched simulations. `display` marks
+/// overridden statuses.
+pub fn set_status(
+    db_path: &Path,
+    uid: &str,
+    pattern: &str,
+    status: Option<&str>,
+    dry_run: bool,
+) -> Result<Vec<String>, String> {
+    let db_path = &db::find_db_path(db_path, Path::new("."));
+    if !db_path.is_file() {
+        return Err(format!("no such database: {}", db_path.display()));
+    }
+    let mut conn = db::open_or_init(db_path).map_err(|e| format!("failed to open DB: {}", e))?;
+    let all_names = db::simulation_names(&conn, uid)
+        .map_err(|e| format!("failed to read simulations: {}", e))?;
+    // a name such as `run[1]` is taken as is rather than as a glob
+    let names: Vec<String> = if all_names.iter().any(|name| name == pattern) {
+        vec![pattern.to_string()]
+    } else {
+        let matcher = globset::Glob::new(pattern)
+            .map_err(|e| format!("invalid pattern '{}': {}", pattern, e))?
+            .compile_matcher();
+        all_names
+            .into_iter()
+            .filter(|name| matcher.is_match(name))
+            .collect()
+    };
+    if names.is_empty() {
+        return Err(format!(
+            "no simulations in collection '{}' match '{}'",
+            uid, pattern
+        ));
+    }
+    if dry_run {
+        return Ok(names);
+    }
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for name in &names {
+        db::set_status_override(&tx, uid, name, status)
+            .map_err(|e| format!("failed to update simulation {}: {}", name, e))?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(names)
+}
+
+/// Prints the most recent scans recorded in the database.
+pub fn scan_history(db_path: &Path, relative_to: Option<&Path>) -> Result<(), String> {
+    let conn = db::open_existing(db_path)?;
+    let runs =
+        db::get_scan_runs(&conn).map_err(|e| format!("failed to read scan history: {}", e))?;
+
+    if runs.is_empty() {
+        println!("No scans recorded in {}", db_path.display());
+        return Ok(());
+    }
+    println!("Recent scans:");
+    for run in runs {
+        println!(
+            " - {}  {}: {} collections, {} synced, {} failed",
+            run.scanned_at,
+            relative_path(&run.root, relative_to),
+            run.collections,
+            run.synced,
+            run.failed
+        );
+    }
+    Ok(())
+}
+
+/// Prints the sync history (status transitions and parameter changes) of a simulation.
+pub fn history(db_path: &Path, collection: &str, name: &str) -> Result<(), String> {
+    let conn = db::open_existing(db_path)?;
+    let log = db::get_sync_log(&conn, collection, name)
+        .map_err(|e| format!("failed to read sync log: {}", e))?;
+
+    if log.is_empty() {
+        println!("No history for '{}' in collection '{}'", name, collection);
+        return Ok(());
+    }
+    println!("History of '{}' in collection '{}':", name, collection);
+    for entry in log {
+        let transition = match entry.old_status {
+            None => format!("new ({})", entry.new_status.unwrap_or_default()),
+            Some(old) => format!("{} -> {}", old, entry.new_status.unwrap_or_default()),
+        };
+        let params = if entry.params_changed {
+            ", parameters changed"
+        } else {
+            ""
+        };
+        println!(" - {}  {}{}", entry.synced_at, transition, params);
+    }
+    Ok(())
+}
+
+/// Writes the parameter keys of a collection with example values and ranges to `out`,
+/// only considering simulations with `status` if given.
+pub fn ls_params(
+    db_path: &Path,
+    collection: &str,
+    keys_only: bool,
+    status: Option<&str>,
+    out: &mut dyn Write,
+) -> Result<(), String> {
+    let conn = db::open_existing(db_path)?;
+    if keys_only {
+        let keys = db::collection_param_keys(&conn, collection, status)
+            .map_err(|e| format!("failed to query parameters: {}", e))?;
+        for key in keys {
+            writeln!(out, "{}", key).map_err(write_error)?;
+        }
+        return Ok(());
+    }
+    let summary = db::aggregate_parameters(&conn, collection, status)
+        .map_err(|e| format!("failed to query parameters: {}", e))?;
+    let first_seen: std::collections::HashMap<String, String> =
+        db::tracked_param_keys(&conn, collection)
+            .map_err(|e| format!("failed to query parameters: {}", e))?
+            .into_iter()
+            .map(|k| (k.key, k.first_seen))
+            .collect();
+
+    match status {
+        Some(status) => writeln!(
+            out,
+            "Parameter space of '{}', status {}:",
+            collection, status
+        ),
+        None => writeln!(out, "Parameter space of '{}':", collection),
+    }
+    .map_err(write_error)?;
+    for (key, param) in &summary {
+        let range = match (param.min, param.max) {
+            (Some(min), Some(max)) if param.distinct_count > 1 => {
+                format!(", range {}..{}", min, max)
+            }
+            _ => String::new(),
+        };
+        // the date part of the RFC 3339 time
+        let since = match first_seen.get(key) {
+            Some(time) => format!(", since {}", time.get(..10).unwrap_or(time)),
+            None => String::new(),
+        };
+        writeln!(
+            out,
+            " - {:20} e.g. {} ({} distinct{}{})",
+            key, param.example, param.distinct_count, range, since
+        )
+        .map_err(write_error)?;
+    }
+    Ok(())
+}
+
+/// Prints groups of simulations with identical parameters, see `params_hash`.
+pub fn dupes(db_path: &Path, collection: &str) -> Result<(), String> {
+    let conn = db::open_existing(db_path)?;
+    let groups = db::get_duplicate_params(&conn, collection)
+        .map_err(|e| format!("failed to query parameters: {}", e))?;
+
+    if groups.is_empty() {
+        println!("No duplicate parameters in '{}'", collection);
+    }
+    for names in groups {
+        println!(" - {}", names.join(", "));
+    }
+    Ok(())
+}
+
+/// Label of the bucket for simulations that lack the grouped or counted key.
+const NONE_BUCKET: &str = "<none>";
+
+/// Heading of the last subtable of `display --group-by`, for rows without the key.
+const UNGROUPED: &str = "<ungrouped>";
+
+/// Formats a row value as a pivot label, strings without quotes.
+fn pivot_label(value: Option<JsonValue>) -> String {
+    match value {
+        None | Some(JsonValue::Null) => NONE_BUCKET.to_string(),
+        Some(JsonValue::String(s)) => s,
+        Some(other) => other.to_string(),
+    }
+}
+
+/// Orders pivot labels numerically where both are numbers, `<none>` last.
+fn compare_labels(a: &str, b: &str) -> std::cmp::Ordering {
+    match (a == NONE_BUCKET, b == NONE_BUCKET) {
+        (true, false) => return std::cmp::Ordering::Greater,
+        (false, true) => return std::cmp::Ordering::Less,
+        _ => {}
+    }
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(x), Ok(y)) => x.total_cmp(&y),
+        _ => a.cmp(b),
+    }
+}
+
+/// Tallies the values of `count_by` per value of `group_by`, both parameters or core
+/// columns. Rows are the distinct groups, columns the distinct counted values and a
+/// total; without `group_by` there is a single row for the whole collection.
+fn pivot_counts(
+    rows: &[Row],
+    group_by: Option<&str>,
+    count_by: &str,
+) -> (Vec<String>, Vec<Vec<String>>) {
+    let mut counts: std::collections::BTreeMap<String, std::collections::BTreeMap<String, usize>> =
+        std::collections::BTreeMap::new();
+    let mut values = Vec::new();
+    for row in rows {
+        let group = match group_by {
+            Some(key) => pivot_label(row.field(key)),
+            None => "all".to_string(),
+        };
+        let value = pivot_label(row.field(count_by));
+        if !values.contains(&value) {
+            values.push(value.clone());
+        }
+        *counts.entry(group).or_default().entry(value).or_default() += 1;
+    }
+    values.sort_by(|a, b| compare_labels(a, b));
+    let mut groups: Vec<_> = counts.into_iter().collect();
+    groups.sort_by(|a, b| compare_labels(&a.0, &b.0));
+
+    let mut header = vec![group_by.unwrap_or("collection").to_string()];
+    header.extend(values.iter().cloned());
+    header.push("total".to_string());
+    let records = groups
+        .into_iter()
+        .map(|(group, tally)| {
+            let mut record = vec![group];
+            record.extend(
+                values
+                    .iter()
+                    .map(|v| tally.get(v).copied().unwrap_or(0).to_string()),
+            );
+            record.push(tally.values().sum::<usize>().to_string());
+            record
+        })
+        .collect();
+    (header, records)
+}
+
+/// Prints how many simulations of a collection have each value of `count_by`, per
+/// value of `group_by` if given.
+pub fn stats(
+    db_path: &Path,
+    collection: &str,
+    group_by: Option<&str>,
+    count_by: &str,
+    format: Format,
+) -> Result<(), String> {
+    let conn = db::open_existing(db_path)?;
+    if db::get_collection_path(&conn, collection).is_none() {
+        return Err(format!("collection {} not in cache", collection));
+    }
+    let rows = cached_rows(&conn, collection, false)?;
+    if rows.is_empty() {
+        println!("collection {} has no simulations", collection);
+        return Ok(());
+    }
+    let (header, records) = pivot_counts(&rows, group_by, count_by);
+    print_records(header, records, format);
+    Ok(())
+}
+
+/// Writes each entry's metadata and parameters to a YAML sidecar file named `file_name`.
+/// The file is compressed if the name ends in `.gz` or `.zst`.
+pub fn migrate(root: &Path, file_name: &str, load_opts: &entry::LoadOptions) {
+    use crate::core::entry::load_entry_meta;
+    use crate::core::sidecar;
+    use crate::core::types::MetaFileContent;
+
+    let collections = discovery::find_all(root);
+    for (c_path, _) in &collections {
+        let entries = collection::find_entries(c_path, &load_opts.data_files);
+        for entry in entries {
+            if let Some((metadata, parameters)) = load_entry_meta(&entry, load_opts) {
+                let yaml_out = serde_yaml::to_string(&MetaFileContent {
+                    metadata,
+                    parameters,
+                })
+                .unwrap();
+                let out_path = entry.join(file_name);
+                sidecar::write(&out_path, &yaml_out).expect("write failed");
+                println!("Migrated {:?}", entry);
+            }
+        }
+    }
+}
+
+/// Prints the combinations of the parameter grid `spec` that have no simulation in the
+/// collection yet, i.e. what still needs to be launched.
+pub fn missing(db_path: &Path, collection: &str, spec: &str, format: Format) -> Result<(), String> {
+    let axes = grid::parse_spec(spec)?;
+    let conn = db::open_existing(db_path)?;
+    let existing = db::get_parameters(&conn, collection)
+        .map_err(|e| format!("failed to read simulations: {}", e))?;
+
+    let missing = grid::missing(&axes, &existing);
+    if format == Format::Table {
+        println!(
+            "{} of {} combinations missing in '{}':",
+            missing.len(),
+            grid::product(&axes).len(),
+            collection
+        );
+    }
+    let header = axes.iter().map(|(key, _)| key.clone()).collect();
+    print_records(header, missing, format);
+    Ok(())
+}
+
+/// Options of the `doctor` command.
+#[derive(Default)]
+pub struct DoctorOptions {
+    /// Delete simulations with status `failed`
+    pub prune_failed: bool,
+    /// Only prune simulations created longer ago than this age, e.g. `30d`
+    pub older_than: Option<String>,
+    /// Delete without asking for confirmation
+    pub yes: bool,
+    /// Only report what would be deleted
+    pub dry_run: bool,
+}
+
+/// Asks a yes/no question on stdin, defaulting to no.
+fn confirm(prompt: &str) -> bool {
+    use std::io::Write;
+
+    print!("{} [y/N] ", prompt);
+    std::io::stdout().flush().ok();
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Reports problems with the cache database and optionally prunes stale failed simulations.
+pub fn doctor(db_path: &Path, opts: &DoctorOptions) -> Result<(), String> {
+    let conn = db::open_existing(db_path)?;
+
+    let mut stmt = conn
+        .prepare("SELECT uid, path FROM collections")
+        .map_err(|e| e.to_string())?;
+    let collections: Vec<(String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .and_then(|rows| rows.collect())
+        .map_err(|e| e.to_string())?;
+    println!("Checked {} collections:", collections.len());
+    for (uid, path) in &collections {
+        if !Path::new(path).exists() {
+            println!(" [!] {} @ {}: path does not exist", uid, path);
+        }
+    }
+
+    // counted by the status users see, which may be set manually
+    let status = db::status_sql(&conn).map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT COALESCE({0}, ''), COUNT(*) FROM simulations s GROUP BY {0} ORDER BY {0}",
+            status
+        ))
+        .map_err(|e| e.to_string())?;
+    let counts: Vec<(String, i64)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .and_then(|rows| rows.collect())
+        .map_err(|e| e.to_string())?;
+    println!("Simulations by status:");
+    for (status, count) in &counts {
+        println!(" - {:20} {}", status, count);
+    }
+
+    let mut stmt = conn
+        .prepare("SELECT collection_uid, created_at FROM simulations ORDER BY collection_uid")
+        .map_err(|e| e.to_string())?;
+    let mut unknown: std::collections::BTreeMap<String, usize> = Default::default();
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+        })
+        .map_err(|e| e.to_string())?;
+    for row in rows {
+        let (uid, created_at) = row.map_err(|e| e.to_string())?;
+        if created_at.is_some_and(|c| time::is_unknown_created_at(&c)) {
+            *unknown.entry(uid).or_default() += 1;
+        }
+    }
+    if !unknown.is_empty() {
+        println!("Simulations with an unparseable created_at:");
+        for (uid, count) in &unknown {
+            println!(" [!] {:20} {}", uid, count);
+        }
+    }
+
+    if opts.prune_failed {
+        // only pruning writes, so only it waits for no scan
+        let db_path = &db::find_db_path(db_path, Path::new("."));
+        let _lock = crate::core::lock::try_lock(db_path).map_err(|e| {
+            format!(
+                "cannot lock {}: {}",
+                crate::core::lock::lock_path(db_path).display(),
+                e
+            )
+        })?;
+        let conn = db::open_or_init(db_path).map_err(|e| format!("failed to open DB: {}", e))?;
+        prune_failed(&conn, opts)?;
+    }
+    Ok(())
+}
+
+fn prune_failed(conn: &rusqlite::Connection, opts: &DoctorOptions) -> Result<(), String> {
+    let cutoff = match &opts.older_than {
+        Some(age) => Some(chrono::Utc::now() - crate::core::time::parse_age(age)?),
+        None => None,
+    };
+
+    let failed = db::get_simulations_by_status(conn, "failed").map_err(|e| e.to_string())?;
+    let stale: Vec<_> = failed
+        .into_iter()
+        .filter(|sim| match cutoff {
+            None => true,
+            // rows whose creation time is unknown are never considered stale
+            Some(cutoff) => {
+                !time::is_unknown_created_at(&sim.created_at)
+                    && chrono::DateTime::parse_from_rfc3339(&sim.created_at)
+                        .is_ok_and(|created| created < cutoff)
+            }
+        })
+        .collect();
+
+    if stale.is_empty() {
+        println!("No failed simulations to prune.");
+        return Ok(());
+    }
+
+    println!("Failed simulations to prune:");
+    for sim in &stale {
+        println!(
+            " - {} / {} (created {})",
+            sim.collection_uid, sim.name, sim.created_at
+        );
+    }
+    if opts.dry_run {
+        println!("Dry run: {} simulations would be deleted.", stale.len());
+        return Ok(());
+    }
+    if !opts.yes && !confirm(&format!("Delete {} simulations?", stale.len())) {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    let ids: Vec<i64> = stale.iter().map(|sim| sim.id).collect();
+    let deleted = db::delete_simulations(conn, &ids).map_err(|e| e.to_string())?;
+    println!("Deleted {} simulations.", deleted);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(parameters_json: &str) -> Row {
+        Row::new(
+            0,
+            String::new(),
+            String::new(),
+            String::new(),
+            false,
+            parameters_json.to_string(),
+            false,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_ls_and_display_write_to_the_given_output() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let db_path = tmp_dir.path().join("simdex.db");
+        let conn = db::open_or_init(&db_path).unwrap();
+        db::upsert_collection(&conn, "C1", "/data/c1").unwrap();
+        let meta = crate::core::types::MetaData {
+            created_at: chrono::DateTime::from_timestamp_nanos(0),
+            description: String::new(),
+            status: "finished".to_string(),
+            submitted: true,
+        };
+        let parameters: Parameters = serde_json::from_str(r#"{"dt": 0.1}"#).unwrap();
+        db::upsert_simulation(&conn, "C1", "run_a", &meta, &parameters).unwrap();
+        drop(conn);
+
+        let mut out = Vec::new();
+        ls_collections(&db_path, None, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Collections:\n - C1 @ /data/c1\n"
+        );
+
+        let mut out = Vec::new();
+        let opts = DisplayOptions {
+            format: Format::Csv,
+            ..Default::default()
+        };
+        display(&db_path, "C1", &opts, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "id,status,submitted,created_at,name,dt\n\
+             1,finished,true,<unknown>,run_a,0.1\n"
+        );
+    }
+
+    #[test]
+    fn test_pivot_counts_buckets_missing_keys() {
+        let mut rows = vec![
+            row(r#"{"mesh": 64}"#),
+            row(r#"{"mesh": 8}"#),
+            row(r#"{"mesh": 64}"#),
+            row("{}"),
+        ];
+        for (row, status) in rows
+            .iter_mut()
+            .zip(["finished", "failed", "finished", "failed"])
+        {
+            row.status = status.to_string();
+        }
+        let (header, records) = pivot_counts(&rows, Some("mesh"), "status");
+        assert_eq!(header, ["mesh", "failed", "finished", "total"]);
+        assert_eq!(
+            records,
+            [
+                ["8", "1", "0", "1"],
+                ["64", "0", "2", "2"],
+                ["<none>", "1", "0", "1"],
+            ]
+        );
+
+        let (header, records) = pivot_counts(&rows, None, "mesh");
+        assert_eq!(header, ["collection", "8", "64", "<none>", "total"]);
+        assert_eq!(records, [["all", "1", "2", "1", "4"]]);
+    }
+
+    #[test]
+    fn test_render_table_filters_and_selects_columns() {
+        let mut rows = vec![row(r#"{"dt": 0.1}"#), row(r#"{"dt": 0.01}"#)];
+        rows[0].name = "a".to_string();
+        rows[1].name = "b".to_string();
+        let opts = DisplayOptions {
+            filters: vec![Filter::parse("dt<0.05").unwrap()],
+            format: Format::Csv,
+            columns: Some(vec!["name".to_string(), "dt".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(render_table(&rows, &opts), "name,dt\nb,0.01");
+    }
+
+    #[test]
+    fn test_render_table_groups_rows_by_parameter() {
+        let rows = vec![
+            row(r#"{"method": "gmres"}"#),
+            row(r#"{"method": "cg"}"#),
+            row("{}"),
+            row(r#"{"method": "cg"}"#),
+        ];
+        let opts = DisplayOptions {
+            format: Format::Csv,
+            columns: Some(vec!["method".to_string()]),
+            group_by: Some("method".to_string()),
+            ..Default::default()
+        };
+        let csv = render_table(&rows, &opts);
+        assert_eq!(
+            csv,
+            "method\n\"\"\"cg\"\"\"\n\"\"\"cg\"\"\"\n\"\"\"gmres\"\"\"\n"
+        );
+
+        let table = render_table(
+            &rows,
+            &DisplayOptions {
+                format: Format::Table,
+                columns: None,
+                ..opts
+            },
+        );
+        let position = |label: &str| table.find(label).unwrap();
+        assert!(position("method = cg") < position("method = gmres"));
+        assert!(position("method = gmres") < position("<ungrouped>"));
+        // one header per subtable, and the grouped column only in the headings
+        assert_eq!(table.matches("status").count(), 3);
+        assert_eq!(table.matches("method").count(), 2);
+    }
+
+    #[test]
+    fn test_render_table_hides_constant_parameters() {
+        let rows = vec![
+            row(r#"{"dt": 0.1, "mesh": 8, "solver": "cg"}"#),
+            row(r#"{"dt": 0.1, "mesh": 16}"#),
+        ];
+        let opts = DisplayOptions {
+            format: Format::Csv,
+            hide_constant: true,
+            ..Default::default()
+        };
+        let csv = render_table(&rows, &opts);
+        assert_eq!(
+            csv.lines().next(),
+            Some("id,status,submitted,created_at,name,mesh,solver")
+        );
+
+        let table = render_table(
+            &rows,
+            &DisplayOptions {
+                format: Format::Table,
+                ..opts
+            },
+        );
+        assert!(table.starts_with("fixed parameters: dt=0.1\n"));
+        assert!(!table.lines().nth(1).unwrap().contains("dt"));
+
+        let listed = DisplayOptions {
+            format: Format::Csv,
+            hide_constant: true,
+            columns: Some(vec!["name".to_string(), "dt".to_string()]),
+            ..Default::default()
+        };
+        let csv = render_table(&rows, &listed);
+        assert_eq!(csv.lines().nth(1), Some(",0.1"));
+    }
+
+    #[test]
+    fn test_flatten_mixed_type_columns() {
+        let rows = vec![
+            row(r#"{"n": 1, "dt": 0.5, "flag": true, "mixed": 1}"#),
+            row(r#"{"n": 2, "dt": 1, "flag": false, "mixed": "a"}"#),
+        ];
+        let (_, columns) = flatten_hashmap_field(&rows, |r| &r.parameters);
+
+        assert_eq!(columns["n"], Column::Int(vec![Some(1), Some(2)]));
+        assert_eq!(columns["dt"], Column::Float(vec![Some(0.5), Some(1.0)]));
+        assert_eq!(columns["flag"], Column::Bool(vec![Some(true), Some(false)]));
+        assert_eq!(
+            columns["mixed"],
+            Column::Str(vec![Some("1".to_string()), Some("a".to_string())])
+        );
+    }
+
+    #[test]
+    fn test_flatten_missing_keys() {
+        let rows = vec![row(r#"{"a": 1, "b": null}"#), row("{}"), row("not json")];
+        let (_, columns) = flatten_hashmap_field(&rows, |r| &r.parameters);
+
+        assert_eq!(columns["a"], Column::Int(vec![Some(1), None, None]));
+        assert_eq!(columns["b"], Column::Str(vec![None, None, None]));
+        assert!(columns.values().all(|c| c.len() == rows.len()));
+    }
+
+    #[test]
+    fn test_flatten_key_union_is_sorted() {
+        let rows = vec![row(r#"{"zeta": 1, "alpha": 2}"#), row(r#"{"mid": 3}"#)];
+        let (keys, _) = flatten_hashmap_field(&rows, |r| &r.parameters);
+
+        assert_eq!(
+            keys.into_iter().collect::<Vec<_>>(),
+            ["alpha", "mid", "zeta"]
+        );
+    }
+
+    #[test]
+    fn test_html_table_escapes_and_classes_rows() {
+        let header = vec!["name".to_string(), "status".to_string()];
+        let records = vec![vec!["a<b>&c".to_string(), "Finished".to_string()]];
+        let html = html_table(&header, &records);
+        assert!(html.contains("<tr class=\"status-finished\"><td>a&lt;b&gt;&amp;c</td>"));
+        assert!(html.contains("<th>status</th>"));
+    }
+
+    #[test]
+    fn test_relative_path_strips_base_or_falls_back() {
+        let base = Path::new("/data/project");
+        assert_eq!(relative_path("/data/project/c1", Some(base)), "c1");
+        assert_eq!(relative_path("/scratch/c2", Some(base)), "/scratch/c2");
+        assert_eq!(relative_path("/data/project/c1", None), "/data/project/c1");
+    }
+
+    #[test]
+    fn test_columns_file_selects_and_orders() {
+        let columns = parse_columns("# report columns\nname\n\n  dt  \n# status\nmissing\n");
+        assert_eq!(columns, ["name", "dt", "missing"]);
+
+        let header = ["id", "name", "dt"].map(String::from).to_vec();
+        let records = vec![["1", "run_a", "0.1"].map(String::from).to_vec()];
+        let (header, records) = select_columns(header, records, &columns);
+        assert_eq!(header, columns);
+        assert_eq!(records, [["run_a", "0.1", ""]]);
+    }
+
+    #[test]
+    fn test_count_records_by_column_or_parameter() {
+        let header = vec!["status".to_string(), "parameters_json".to_string()];
+        let records: Vec<Vec<JsonValue>> = [
+            ("finished", r#"{"dt": 0.1}"#),
+            ("failed", r#"{"dt": 0.01}"#),
+            ("finished", r#"{"dt": 0.01}"#),
+            ("finished", "{}"),
+        ]
+        .into_iter()
+        .map(|(status, params)| vec![status.into(), params.into()])
+        .collect();
+
+        let (_, by_status) = count_records_by(&header, &records, "status").unwrap();
+        assert_eq!(
+            by_status,
+            [
+                vec![JsonValue::from("finished"), 3.into()],
+                vec![JsonValue::from("failed"), 1.into()],
+            ]
+        );
+        let (counted_header, by_dt) = count_records_by(&header, &records, "dt").unwrap();
+        assert_eq!(counted_header, ["dt", "count"]);
+        assert_eq!(by_dt[0], [JsonValue::from(0.01), 2.into()]);
+        assert_eq!(by_dt.len(), 3);
+        assert!(count_records_by(&header[..1], &records, "dt").is_err());
+    }
+
+    #[test]
+    fn test_json_records_keep_column_order() {
+        let header = ["name", "id", "dt"].map(String::from).to_vec();
+        let records = vec![vec![
+            JsonValue::from("run_a"),
+            JsonValue::from(3),
+            JsonValue::Null,
+        ]];
+        let json: String = json_records(&header, &records).split_whitespace().collect();
+        assert_eq!(json, r#"[{"name":"run_a","id":3,"dt":null}]"#);
+    }
+
+    #[test]
+    fn test_read_path_list_skips_blank_lines() {
+        let list = "c1/run_a\n\n  c1/run_b  \n";
+        let paths = read_path_list(list.as_bytes()).unwrap();
+        assert_eq!(
+            paths,
+            [PathBuf::from("c1/run_a"), PathBuf::from("c1/run_b")]
+        );
+    }
+
+    #[test]
+    fn test_group_entries_by_collection() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let (marked, unmarked) = (tmp_dir.path().join("c1"), tmp_dir.path().join("c2"));
+        for entry in [marked.join("a"), marked.join("b"), unmarked.join("c")] {
+            std::fs::create_dir_all(entry).unwrap();
+        }
+        std::fs::write(
+            marked.join(format!("{}C1.yml", crate::config::META_FILE_PREFIX)),
+            "uid: C1\n",
+        )
+        .unwrap();
+        let conn = db::open_or_init(":memory:").unwrap();
+        db::upsert_collection(&conn, "C2", &unmarked.display().to_string()).unwrap();
+
+        let entries = [
+            marked.join("a"),
+            unmarked.join("c"),
+            marked.join("b"),
+            marked.join("a"),
+            marked.join("missing"),
+            tmp_dir.path().join("c3"),
+        ];
+        let mut failed = Vec::new();
+        let groups = group_entries(&conn, &entries, &ScanOptions::default(), &mut failed).unwrap();
+        assert_eq!(
+            groups,
+            [
+                (
+                    marked.clone(),
+                    "C1".to_string(),
+                    vec![marked.join("a"), marked.join("b")]
+                ),
+                (unmarked.clone(), "C2".to_string(), vec![unmarked.join("c")]),
+            ]
+        );
+        let failed: Vec<_> = failed.into_iter().map(|(path, _)| path).collect();
+        assert_eq!(failed, [marked.join("missing"), tmp_dir.path().join("c3")]);
+    }
+
+    #[test]
+    fn test_nested_scan_uses_marker_uids_and_keeps_excluded_parents() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let root = tmp_dir.path().join("root");
+        let (outer, inner) = (root.join("outer"), root.join("outer/inner"));
+        std::fs::create_dir_all(&inner).unwrap();
+        for (path, file_uid, uid) in [(&outer, "OLD", "OUTER"), (&inner, "INNER", "INNER")] {
+            let marker = path.join(format!(
+                "{}{}.yml",
+                crate::config::META_FILE_PREFIX,
+                file_uid
+            ));
+            std::fs::write(marker, format!("uid: {}\n", uid)).unwrap();
+        }
+        let db_path = tmp_dir.path().join("simdex.db");
+        let parent_of = |uid: &str| {
+            let conn = db::open_existing(&db_path).unwrap();
+            db::get_collections(&conn)
+                .unwrap()
+                .into_iter()
+                .find(|c| c.uid == uid)
+                .unwrap()
+                .parent_uid
+        };
+        let opts = ScanOptions {
+            sync: collection::SyncOptions {
+                quiet: true,
+                ..Default::default()
+            },
+            nested: true,
+            marker_uid: true,
+            ..Default::default()
+        };
+        scan(&root, &db_path, &opts).unwrap();
+        assert_eq!(parent_of("INNER").as_deref(), Some("OUTER"));
+
+        let exclude = [globset::Glob::new("outer").unwrap()];
+        let without_outer = ScanOptions {
+            paths: GlobFilter::new(&[], &exclude).unwrap(),
+            ..opts
+        };
+        let report = scan(&root, &db_path, &without_outer).unwrap();
+        assert_eq!(report.collections, 1);
+        assert_eq!(parent_of("INNER").as_deref(), Some("OUTER"));
+    }
+
+    #[test]
+    fn test_scan_skips_collections_sharing_a_uid() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let root = tmp_dir.path().join("root");
+        for dir in ["original", "copy"] {
+            let c_path = root.join(dir);
+            std::fs::create_dir_all(&c_path).unwrap();
+            let marker = c_path.join(format!("{}C1.yml", crate::config::META_FILE_PREFIX));
+            std::fs::write(marker, "uid: C1\n").unwrap();
+            entry::create_example_entry(&c_path).unwrap();
+        }
+        let db_path = tmp_dir.path().join("simdex.db");
+        let opts = ScanOptions {
+            sync: collection::SyncOptions {
+                quiet: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let report = scan(&root, &db_path, &opts).unwrap();
+        assert_eq!(report.collections, 2);
+        assert_eq!(report.synced, 1);
+        assert_eq!(report.failed.len(), 1);
+        assert!(report.failed[0].1.contains("already used by"));
+
+        let strict = ScanOptions {
+            strict: true,
+            ..opts
+        };
+        let err = scan(&root, &db_path, &strict).unwrap_err();
+        assert!(err.contains("uid 'C1' is already used by"));
+    }
+
+    #[test]
+    fn test_repair_json_rereads_or_clears_invalid_parameters() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let c_path = tmp_dir.path().join("c1");
+        std::fs::create_dir(&c_path).unwrap();
+        entry::create_example_entry(&c_path).unwrap();
+        let db_path = tmp_dir.path().join("simdex.db");
+        let load_opts = entry::LoadOptions::default();
+        assert!(repair_json(&db_path, None, true, false, &load_opts).is_err());
+        assert!(!db_path.exists());
+
+        let conn = db::open_or_init(&db_path).unwrap();
+        db::upsert_collection(&conn, "C1", &c_path.display().to_string()).unwrap();
+        for name in ["example", "gone"] {
+            conn.execute(
+                "INSERT INTO simulations (collection_uid, name, parameters_json)
+                 VALUES ('C1', ?1, '{\"dt\": 0.')",
+                [name],
+            )
+            .unwrap();
+        }
+        drop(conn);
+
+        assert_eq!(repair_json(&db_path, None, false, false, &load_opts), Ok(2));
+        assert_eq!(repair_json(&db_path, None, true, false, &load_opts), Ok(1));
+        let conn = db::open_existing(&db_path).unwrap();
+        let invalid = db::find_invalid_parameters(&conn, None).unwrap();
+        assert_eq!(invalid.len(), 1);
+        assert_eq!(invalid[0].name, "gone");
+        let params = db::get_simulations(&conn, "C1").unwrap();
+        assert!(params[0].parameters.contains_key("dt"));
+        drop(conn);
+
+        assert!(
+            touch(
+                &tmp_dir.path().join("none.db"),
+                "C1",
+                "example",
+                true,
+                &load_opts
+            )
+            .is_err()
+        );
+        assert!(!tmp_dir.path().join("none.db").exists());
+        assert!(touch(&db_path, "C1", "example", true, &load_opts).is_ok());
+
+        assert_eq!(repair_json(&db_path, None, true, true, &load_opts), Ok(0));
+        let conn = db::open_existing(&db_path).unwrap();
+        assert!(db::find_invalid_parameters(&conn, None).unwrap().is_empty());
+        // the cleared row counts as a simulation without parameters
+        assert_eq!(db::get_parameters(&conn, "C1").unwrap().len(), 2);
+        assert!(missing(&db_path, "C1", "dt=0.1,0.2", Format::Json).is_ok());
+    }
+
+    #[test]
+    fn test_prune_failed_keeps_rows_with_unknown_creation_time() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let conn = db::open_or_init(tmp_dir.path().join("simdex.db")).unwrap();
+        let unknown = crate::core::time::UNKNOWN_CREATED_AT.to_rfc3339();
+        for (name, created_at) in [
+            ("old", "2000-01-01T00:00:00+00:00"),
+            ("unknown", unknown.as_str()),
+        ] {
+            conn.execute(
+                "INSERT INTO simulations (collection_uid, name, status, created_at)
+                 VALUES ('C1', ?1, 'failed', ?2)",
+                [name, created_at],
+            )
+            .unwrap();
+        }
+
+        let opts = DoctorOptions {
+            prune_failed: true,
+            older_than: Some("1d".to_string()),
+            yes: true,
+            dry_run: false,
+        };
+        prune_failed(&conn, &opts).unwrap();
+        let left = db::get_simulations_by_status(&conn, "failed").unwrap();
+        assert_eq!(left.len(), 1);
+        assert_eq!(left[0].name, "unknown");
+    }
+
+    #[test]
+    fn test_set_status_prefers_an_exact_name_over_a_glob() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let db_path = tmp_dir.path().join("simdex.db");
+        let conn = db::open_or_init(&db_path).unwrap();
+        for name in ["run1", "run[1]", "run2"] {
+            conn.execute(
+                "INSERT INTO simulations (collection_uid, name) VALUES ('C1', ?1)",
+                [name],
+            )
+            .unwrap();
+        }
+        drop(conn);
+
+        let matched = |pattern| set_status(&db_path, "C1", pattern, Some("failed"), true);
+        assert_eq!(matched("run[1]").unwrap(), ["run[1]"]);
+        assert_eq!(matched("run[12]").unwrap(), ["run1", "run2"]);
+        assert!(matched("run3").is_err());
+
+        set_status(&db_path, "C1", "run[1]", Some("failed"), false).unwrap();
+        let conn = db::open_existing(&db_path).unwrap();
+        let failed = db::get_simulations_by_status(&conn, "failed").unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].name, "run[1]");
+    }
+
+    #[test]
+    fn test_is_read_query_checks_leading_keyword() {
+        assert!(is_read_query("  select * from simulations"));
+        assert!(is_read_query("WITH s AS (SELECT 1) SELECT * FROM s"));
+        assert!(!is_read_query("DELETE FROM simulations"));
+        assert!(!is_read_query("selectx"));
+    }
 }
@@ -0,0 +1,244 @@
+use serde_json::{Value as JsonValue, json};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::api::{DisplayOptions, Format, load_rows, render_table};
+use crate::core::db;
+
+/// How long a client may take to send its request before the connection is dropped.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a client may take to accept the response before the connection is dropped.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Longest request or header line read, in bytes. A longer request line is refused and
+/// reading stops at a longer header line.
+const MAX_LINE: u64 = 8 * 1024;
+
+/// Serves the cache at `db_path` read-only over HTTP on `addr` until the process is
+/// stopped. Only `GET` is answered, with JSON:
+///
+/// * `/collections` - the cached collections
+/// * `/collections/{uid}` - the simulations of a collection, as `display --format json`
+/// * `/collections/{uid}/params` - the parameter space of a collection, see `ls-params`
+///
+/// Requests are handled one at a time; the database is opened anew for each of them,
+/// so scans running meanwhile are picked up.
+pub fn serve(db_path: &Path, addr: SocketAddr) -> Result<(), String> {
+    let db_path = &db::find_db_path(db_path, Path::new("."));
+    if !db_path.is_file() {
+        return Err(format!("no such database: {}", db_path.display()));
+    }
+    let listener =
+        TcpListener::bind(addr).map_err(|e| format!("cannot listen on {}: {}", addr, e))?;
+    println!("Serving {} on http://{}", db_path.display(), addr);
+    for stream in listener.incoming() {
+        let result = stream.and_then(|stream| handle(db_path, stream));
+        if let Err(e) = result {
+            eprintln!("[!] request failed: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Reads one request from `stream` and writes the response.
+fn handle(db_path: &Path, mut stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader
+        .by_ref()
+        .take(MAX_LINE)
+        .read_line(&mut request_line)?;
+    // the headers are not needed, but are read so the client sees a complete exchange
+    let mut header = String::new();
+    while reader.by_ref().take(MAX_LINE).read_line(&mut header)? > 2 && header.ends_with('\n') {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let (status, body) = match method {
+        _ if !request_line.ends_with('\n') => (414, error_body("request line too long")),
+        "GET" | "HEAD" => route(db_path, target),
+        _ => (405, error_body("only GET is supported")),
+    };
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        414 => "URI Too Long",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        reason,
+        body.len()
+    )?;
+    if method != "HEAD" {
+        stream.write_all(body.as_bytes())?;
+    }
+    stream.flush()
+}
+
+fn error_body(message: &str) -> String {
+    json!({ "error": message }).to_string()
+}
+
+/// Answers a `GET` of `target` with a status code and a JSON body.
+fn route(db_path: &Path, target: &str) -> (u16, String) {
+    let path = target.split(['?', '#']).next().unwrap_or("");
+    let segments: Option<Vec<String>> = path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(percent_decode)
+        .collect();
+    let Some(segments) = segments else {
+        return (400, error_body(&format!("invalid path: {}", path)));
+    };
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    let result = match segments.as_slice() {
+        ["collections"] => collections(db_path),
+        ["collections", uid] => simulations(db_path, uid),
+        ["collections", uid, "params"] => parameters(db_path, uid),
+        _ => return (404, error_body(&format!("no such endpoint: {}", path))),
+    };
+    match result {
+        Ok(Some(body)) => (200, body),
+        Ok(None) => (404, error_body("collection not found")),
+        Err(e) => (500, error_body(&e)),
+    }
+}
+
+/// Decodes the `%XX` escapes of a path segment, e.g. `a%20b` to `a b`. Returns None if
+/// an escape is malformed or the result is not UTF-8.
+fn percent_decode(segment: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(segment.len());
+    let mut rest = segment.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail
+                .get(..2)
+                .filter(|h| h.iter().all(u8::is_ascii_hexdigit))?;
+            bytes.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+fn collections(db_path: &Path) -> Result<Option<String>, String> {
+    let conn = db::open_existing(db_path)?;
+    let collections: Vec<JsonValue> = db::get_collections(&conn)
+        .map_err(|e| format!("failed to read collections: {}", e))?
+        .into_iter()
+        .map(|c| {
+            json!({
+                "uid": c.uid,
+                "path": c.path,
+                "description": c.description,
+                "parent_uid": c.parent_uid,
+            })
+        })
+        .collect();
+    Ok(Some(JsonValue::from(collections).to_string()))
+}
+
+fn simulations(db_path: &Path, uid: &str) -> Result<Option<String>, String> {
+    let conn = db::open_existing(db_path)?;
+    if db::get_collection_path(&conn, uid).is_none() {
+        return Ok(None);
+    }
+    let opts = DisplayOptions {
+        format: Format::Json,
+        ..Default::default()
+    };
+    let rows = load_rows(db_path, uid, &opts)?;
+    if rows.is_empty() {
+        return Ok(Some("[]".to_string()));
+    }
+    Ok(Some(render_table(&rows, &opts)))
+}
+
+fn parameters(db_path: &Path, uid: &str) -> Result<Option<String>, String> {
+    let conn = db::open_existing(db_path)?;
+    if db::get_collection_path(&conn, uid).is_none() {
+        return Ok(None);
+    }
+    let summary = db::aggregate_parameters(&conn, uid, None)
+        .map_err(|e| format!("failed to query parameters: {}", e))?;
+    let parameters: serde_json::Map<String, JsonValue> = summary
+        .into_iter()
+        .map(|(key, param)| {
+            let value = json!({
+                "example": param.example,
+                "count": param.count,
+                "distinct_count": param.distinct_count,
+                "is_numeric": param.is_numeric,
+                "min": param.min,
+                "max": param.max,
+                "types": param.types,
+            });
+            (key, value)
+        })
+        .collect();
+    Ok(Some(JsonValue::from(parameters).to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("a%20b%2Fc").as_deref(), Some("a b/c"));
+        assert_eq!(percent_decode("%C3%A9").as_deref(), Some("é"));
+        assert_eq!(percent_decode("%zz"), None);
+        assert_eq!(percent_decode("%+1"), None);
+        assert_eq!(percent_decode("%ff"), None);
+    }
+
+    #[test]
+    fn test_route_answers_the_endpoints() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let db_path = tmp_dir.path().join("simdex.db");
+        let conn = db::open_or_init(&db_path).unwrap();
+        db::upsert_collection(&conn, "C1", "/data/c1").unwrap();
+        conn.execute(
+            "INSERT INTO simulations (collection_uid, name, status, parameters_json)
+             VALUES ('C1', 'run_a', 'finished', '{\"dt\": 0.1}')",
+            [],
+        )
+        .unwrap();
+        drop(conn);
+
+        let (status, body) = route(&db_path, "/collections");
+        assert_eq!(status, 200);
+        let collections: JsonValue = serde_json::from_str(&body).unwrap();
+        assert_eq!(collections[0]["uid"], "C1");
+
+        let (status, body) = route(&db_path, "/collections/C1?ignored=1");
+        assert_eq!(status, 200);
+        let simulations: JsonValue = serde_json::from_str(&body).unwrap();
+        assert_eq!(simulations[0]["name"], "run_a");
+
+        let (status, body) = route(&db_path, "/collections/C1/params");
+        assert_eq!(status, 200);
+        let parameters: JsonValue = serde_json::from_str(&body).unwrap();
+        assert_eq!(parameters["dt"]["is_numeric"], true);
+
+        assert_eq!(route(&db_path, "/collections/C2").0, 404);
+        assert_eq!(route(&db_path, "/collections/C%31/params").0, 200);
+        assert_eq!(route(&db_path, "/collections/C%3").0, 400);
+        assert_eq!(route(&db_path, "/other").0, 404);
+    }
+}
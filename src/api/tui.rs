@@ -0,0 +1,509 @@
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{
+    Block, Borders, Cell, Clear, List, ListItem, ListState, Paragraph, Row as TableRow, Table,
+    TableState, Wrap,
+};
+use ratatui::{DefaultTerminal, Frame};
+use rusqlite::Connection;
+use serde_json::Value as JsonValue;
+use std::cmp::Ordering;
+use std::path::Path;
+
+use crate::core::db;
+
+/// Columns shown before the parameters
+const CORE_COLUMNS: [&str; 4] = ["id", "status", "name", "created_at"];
+
+/// Widest a table column gets, longer values are cut off
+const MAX_COLUMN_WIDTH: usize = 30;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Collections,
+    Simulations,
+}
+
+struct App {
+    conn: Connection,
+    collections: Vec<db::CollectionRow>,
+    list: ListState,
+    sims: Vec<db::Simulation>,
+    /// Core columns followed by the union of the parameter keys
+    columns: Vec<String>,
+    /// Indices into `sims` of the rows passing the filter, in display order
+    visible: Vec<usize>,
+    table: TableState,
+    focus: Focus,
+    filter: String,
+    editing_filter: bool,
+    /// Column index and whether the order is descending
+    sort: Option<(usize, bool)>,
+    detail: bool,
+}
+
+/// Renders a cell of a simulation; strings are shown without quotes.
+fn cell(sim: &db::Simulation, column: &str) -> String {
+    match column {
+        "id" => sim.id.to_string(),
+        "status" => sim.status.clone().unwrap_or_default(),
+        "name" => sim.name.clone(),
+        "created_at" => sim.created_at.clone().unwrap_or_default(),
+        key => match sim.parameters.get(key) {
+            Some(JsonValue::String(s)) => s.clone(),
+            Some(value) => value.to_string(),
+            None => String::new(),
+        },
+    }
+}
+
+/// Orders cells numerically if both are numbers, else as strings.
+fn compare_cells(a: &str, b: &str) -> Ordering {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.total_cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+/// Returns the indices of the simulations containing `filter` (case-insensitive) in any
+/// column, sorted by `sort` if given.
+fn visible_rows(
+    sims: &[db::Simulation],
+    columns: &[String],
+    filter: &str,
+    sort: Option<(usize, bool)>,
+) -> Vec<usize> {
+    let filter = filter.to_lowercase();
+    let mut visible: Vec<usize> = (0..sims.len())
+        .filter(|&i| {
+            filter.is_empty()
+                || columns
+                    .iter()
+                    .any(|c| cell(&sims[i], c).to_lowercase().contains(&filter))
+        })
+        .collect();
+    if let Some((column, descending)) = sort
+        && let Some(column) = columns.get(column)
+    {
+        visible.sort_by(|&a, &b| {
+            let order = compare_cells(&cell(&sims[a], column), &cell(&sims[b], column));
+            if descending { order.reverse() } else { order }
+        });
+    }
+    visible
+}
+
+impl App {
+    fn new(conn: Connection, collections: Vec<db::CollectionRow>) -> Result<Self, String> {
+        let mut app = Self {
+            conn,
+            collections,
+            list: ListState::default().with_selected(Some(0)),
+            sims: Vec::new(),
+            columns: Vec::new(),
+            visible: Vec::new(),
+            table: TableState::default(),
+            focus: Focus::Collections,
+            filter: String::new(),
+            editing_filter: false,
+            sort: None,
+            detail: false,
+        };
+        app.load_collection()?;
+        Ok(app)
+    }
+
+    /// Reads the simulations of the selected collection.
+    fn load_collection(&mut self) -> Result<(), String> {
+        let Some(collection) = self.list.selected().and_then(|i| self.collections.get(i)) else {
+            return Ok(());
+        };
+        self.sims = db::get_simulations(&self.conn, &collection.uid).map_err(|e| e.to_string())?;
+        let keys: std::collections::BTreeSet<&String> =
+            self.sims.iter().flat_map(|s| s.parameters.keys()).collect();
+        self.columns = CORE_COLUMNS.iter().map(|c| c.to_string()).collect();
+        self.columns.extend(keys.into_iter().cloned());
+        self.sort = None;
+        self.table.select_column(Some(0));
+        self.refresh();
+        Ok(())
+    }
+
+    /// Re-applies filter and sort, keeping a valid selection.
+    fn refresh(&mut self) {
+        self.visible = visible_rows(&self.sims, &self.columns, &self.filter, self.sort);
+        let selected = match self.table.selected() {
+            _ if self.visible.is_empty() => None,
+            Some(i) => Some(i.min(self.visible.len() - 1)),
+            None => Some(0),
+        };
+        self.table.select(selected);
+    }
+
+    fn selected_sim(&self) -> Option<&db::Simulation> {
+        let i = *self.visible.get(self.table.selected()?)?;
+        self.sims.get(i)
+    }
+
+    fn sort_by(&mut self, column: usize) {
+        if column >= self.columns.len() {
+            return;
+        }
+        self.sort = match self.sort {
+            Some((c, descending)) if c == column => Some((c, !descending)),
+            _ => Some((column, false)),
+        };
+        self.refresh();
+    }
+
+    /// Moves the column cursor, which selects the column sorted by with `s`.
+    fn move_column(&mut self, right: bool) {
+        let last = self.columns.len().saturating_sub(1);
+        let column = self.table.selected_column().unwrap_or(0);
+        let column = if right {
+            (column + 1).min(last)
+        } else {
+            column.saturating_sub(1)
+        };
+        self.table.select_column(Some(column));
+    }
+
+    fn move_selection(&mut self, down: bool) -> Result<(), String> {
+        match self.focus {
+            Focus::Collections => {
+                if down {
+                    self.list.select_next();
+                } else {
+                    self.list.select_previous();
+                }
+                let last = self.collections.len() - 1;
+                self.list
+                    .select(Some(self.list.selected().unwrap_or(0).min(last)));
+                self.table.select(None);
+                self.load_collection()?;
+            }
+            Focus::Simulations => {
+                if down {
+                    self.table.select_next();
+                } else {
+                    self.table.select_previous();
+                }
+                self.refresh();
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles a key press, returns false to quit.
+    fn handle_key(&mut self, key: KeyCode) -> Result<bool, String> {
+        if self.editing_filter {
+            match key {
+                KeyCode::Enter => self.editing_filter = false,
+                KeyCode::Esc => {
+                    self.editing_filter = false;
+                    self.filter.clear();
+                }
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Char(c) => self.filter.push(c),
+                _ => return Ok(true),
+            }
+            self.refresh();
+            return Ok(true);
+        }
+        if self.detail {
+            if matches!(key, KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q')) {
+                self.detail = false;
+            }
+            return Ok(true);
+        }
+
+        match key {
+            KeyCode::Char('q') => return Ok(false),
+            KeyCode::Tab | KeyCode::Left | KeyCode::Right => {
+                self.focus = match self.focus {
+                    Focus::Collections => Focus::Simulations,
+                    Focus::Simulations => Focus::Collections,
+                }
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(true)?,
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(false)?,
+            KeyCode::Enter => match self.focus {
+                Focus::Collections => self.focus = Focus::Simulations,
+                Focus::Simulations => self.detail = self.selected_sim().is_some(),
+            },
+            KeyCode::Char('/') => {
+                self.editing_filter = true;
+                self.focus = Focus::Simulations;
+            }
+            KeyCode::Esc => {
+                self.filter.clear();
+                self.refresh();
+            }
+            KeyCode::Char('<') => self.move_column(false),
+            KeyCode::Char('>') => self.move_column(true),
+            KeyCode::Char('s') => self.sort_by(self.table.selected_column().unwrap_or(0)),
+            KeyCode::Char(c @ '1'..='9') => self.sort_by(c as usize - '1' as usize),
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, help] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Length(28), Constraint::Min(0)]).areas(main);
+
+        let border = |focus: Focus| {
+            if self.focus == focus {
+                Style::default().bold()
+            } else {
+                Style::default().dim()
+            }
+        };
+
+        let items: Vec<ListItem> = self
+            .collections
+            .iter()
+            .map(|c| ListItem::new(c.uid.clone()))
+            .collect();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title("Collections")
+                    .borders(Borders::ALL)
+                    .border_style(border(Focus::Collections)),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, left, &mut self.list);
+
+        let header = TableRow::new(self.columns.iter().enumerate().map(|(i, c)| {
+            let marker = match self.sort {
+                Some((s, false)) if s == i => " ▲",
+                Some((s, true)) if s == i => " ▼",
+                _ => "",
+            };
+            let header = Cell::from(format!("{}{}", c, marker));
+            if self.table.selected_column() == Some(i) {
+                header.underlined()
+            } else {
+                header
+            }
+        }))
+        .bold();
+        let rows: Vec<Vec<String>> = self
+            .visible
+            .iter()
+            .map(|&i| {
+                self.columns
+                    .iter()
+                    .map(|c| cell(&self.sims[i], c))
+                    .collect()
+            })
+            .collect();
+        let widths: Vec<Constraint> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                let width = rows
+                    .iter()
+                    .map(|r| r[i].chars().count())
+                    .chain([c.chars().count() + 2])
+                    .max()
+                    .unwrap_or(0);
+                Constraint::Length(width.min(MAX_COLUMN_WIDTH) as u16)
+            })
+            .collect();
+        let title = match (&self.filter, self.editing_filter) {
+            (_, true) => format!("Simulations (filter: {}_)", self.filter),
+            (f, false) if !f.is_empty() => format!("Simulations (filter: {})", f),
+            _ => "Simulations".to_string(),
+        };
+        let table = Table::new(rows.into_iter().map(TableRow::new), widths)
+            .header(header)
+            .block(
+                Block::default()
+                    .title(format!(
+                        "{} [{}/{}]",
+                        title,
+                        self.visible.len(),
+                        self.sims.len()
+                    ))
+                    .borders(Borders::ALL)
+                    .border_style(border(Focus::Simulations)),
+            )
+            .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .column_highlight_style(Style::default().add_modifier(Modifier::UNDERLINED));
+        frame.render_stateful_widget(table, right, &mut self.table);
+
+        frame.render_widget(
+            Paragraph::new(
+                " q quit  tab switch pane  ↑↓ move  enter open  / filter  esc clear  < > column  s or 1-9 sort",
+            )
+            .dim(),
+            help,
+        );
+
+        if self.detail
+            && let Some(sim) = self.selected_sim()
+        {
+            let area = popup_area(frame.area());
+            frame.render_widget(Clear, area);
+            frame.render_widget(
+                Paragraph::new(detail_lines(sim))
+                    .block(
+                        Block::default()
+                            .title(sim.name.clone())
+                            .borders(Borders::ALL),
+                    )
+                    .wrap(Wrap { trim: false }),
+                area,
+            );
+        }
+    }
+}
+
+/// The full metadata and parameters of a simulation, for the detail view.
+fn detail_lines(sim: &db::Simulation) -> Vec<Line<'static>> {
+    let mut lines = vec![
+        Line::from(format!("id:          {}", sim.id)),
+        Line::from(format!("status:      {}", cell(sim, "status"))),
+        Line::from(format!("submitted:   {}", sim.submitted)),
+        Line::from(format!("created_at:  {}", cell(sim, "created_at"))),
+        Line::from(""),
+        Line::from("description:".bold()),
+    ];
+    lines.extend(
+        sim.description
+            .as_deref()
+            .unwrap_or_default()
+            .lines()
+            .map(|l| Line::from(format!("  {}", l))),
+    );
+    lines.push(Line::from(""));
+    lines.push(Line::from("parameters:".bold()));
+    let mut keys: Vec<&String> = sim.parameters.keys().collect();
+    keys.sort();
+    for key in keys {
+        lines.push(Line::from(format!("  {} = {}", key, sim.parameters[key])));
+    }
+    lines
+}
+
+/// A centered rectangle covering most of `area`.
+fn popup_area(area: Rect) -> Rect {
+    let [_, area, _] = Layout::vertical([
+        Constraint::Percentage(10),
+        Constraint::Percentage(80),
+        Constraint::Percentage(10),
+    ])
+    .areas(area);
+    let [_, area, _] = Layout::horizontal([
+        Constraint::Percentage(10),
+        Constraint::Percentage(80),
+        Constraint::Percentage(10),
+    ])
+    .areas(area);
+    area
+}
+
+fn run(terminal: &mut DefaultTerminal, app: &mut App) -> Result<(), String> {
+    loop {
+        terminal
+            .draw(|frame| app.draw(frame))
+            .map_err(|e| e.to_string())?;
+        if let Event::Key(key) = event::read().map_err(|e| e.to_string())?
+            && key.kind == KeyEventKind::Press
+            && !app.handle_key(key.code)?
+        {
+            return Ok(());
+        }
+    }
+}
+
+/// Opens an interactive browser of the cache: collections on the left, the selected
+/// collection's simulations on the right, with filtering, sorting and a detail view.
+pub fn tui(db_path: &Path) -> Result<(), String> {
+    let conn = db::open_existing(db_path)?;
+    let collections =
+        db::get_collections(&conn).map_err(|e| format!("failed to read collections: {}", e))?;
+    if collections.is_empty() {
+        return Err(format!("no collections in {}", db_path.display()));
+    }
+    let mut app = App::new(conn, collections)?;
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut app);
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visible_rows_filters_and_sorts_numerically() {
+        let sim = |id: i64, name: &str, n: i64| db::Simulation {
+            id,
+            name: name.to_string(),
+            created_at: None,
+            description: None,
+            status: Some("finished".to_string()),
+            submitted: false,
+            parameters: [("n".to_string(), JsonValue::from(n))].into(),
+        };
+        let sims = vec![
+            sim(1, "run_a", 10),
+            sim(2, "run_b", 9),
+            sim(3, "other", 100),
+        ];
+        let columns: Vec<String> = ["id", "name", "n"].map(String::from).to_vec();
+
+        assert_eq!(visible_rows(&sims, &columns, "RUN", None), [0, 1]);
+        assert_eq!(
+            visible_rows(&sims, &columns, "", Some((2, false))),
+            [1, 0, 2]
+        );
+        assert_eq!(
+            visible_rows(&sims, &columns, "", Some((1, true))),
+            [1, 0, 2]
+        );
+    }
+
+    #[test]
+    fn test_column_cursor_sorts_by_any_column() {
+        let conn = db::open_or_init(":memory:").unwrap();
+        db::upsert_collection(&conn, "C1", "/data/c1").unwrap();
+        let keys: Vec<String> = (0..12).map(|i| format!("p{:02}", i)).collect();
+        let parameters: serde_json::Map<String, JsonValue> = keys
+            .iter()
+            .map(|k| (k.clone(), JsonValue::from(1)))
+            .collect();
+        conn.execute(
+            "INSERT INTO simulations (collection_uid, name, parameters_json) VALUES ('C1', 'a', ?1)",
+            [JsonValue::Object(parameters).to_string()],
+        )
+        .unwrap();
+        let collections = db::get_collections(&conn).unwrap();
+        let mut app = App::new(conn, collections).unwrap();
+        assert_eq!(app.columns.len(), CORE_COLUMNS.len() + 12);
+
+        app.handle_key(KeyCode::Char('<')).unwrap();
+        assert_eq!(app.table.selected_column(), Some(0));
+        for _ in 0..20 {
+            app.handle_key(KeyCode::Char('>')).unwrap();
+        }
+        let last = app.columns.len() - 1;
+        assert_eq!(app.table.selected_column(), Some(last));
+        app.handle_key(KeyCode::Char('s')).unwrap();
+        assert_eq!(app.sort, Some((last, false)));
+        app.handle_key(KeyCode::Char('s')).unwrap();
+        assert_eq!(app.sort, Some((last, true)));
+    }
+}
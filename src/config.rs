@@ -1,3 +1,79 @@
+use serde::Deserialize;
+use std::path::PathBuf;
+
 pub const DEFAULT_DB_PATH: &str = "simdex.db";
 pub const META_FILE_PREFIX: &str = ".bamboost-collection-";
+pub const CONFIG_FILE_NAME: &str = "simdex.toml";
+
+/// User configuration, read from `simdex.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub display: DisplayConfig,
+    pub entry: EntryConfig,
+}
+
+/// The `[entry]` section of the configuration: defaults for reading entries' data files.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EntryConfig {
+    /// HDF5 group holding the metadata attributes, overridden by `--meta-group`
+    pub meta_group: Option<String>,
+}
+
+/// The `[display]` section of the configuration.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    /// Table style, overridden by `--style`
+    pub style: Option<TableStyle>,
+    /// Color of the header row, e.g. `bright-black` or `blue`
+    pub header_color: Option<String>,
+    /// Truncate cells wider than this many characters, overridden by `--max-col-width`
+    pub max_col_width: Option<usize>,
+}
+
+/// Border style of rendered tables.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TableStyle {
+    #[default]
+    Blank,
+    Sharp,
+    Rounded,
+    Markdown,
+    Ascii,
+}
+
+impl Config {
+    /// Returns the paths searched for a config file, in order of precedence:
+    /// `./simdex.toml`, then `$XDG_CONFIG_HOME/simdex/config.toml` (or `~/.config/...`).
+    pub fn search_paths() -> Vec<PathBuf> {
+        let mut paths = vec![PathBuf::from(CONFIG_FILE_NAME)];
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")));
+        if let Some(dir) = config_home {
+            paths.push(dir.join("simdex").join("config.toml"));
+        }
+        paths
+    }
 
+    /// Loads the first config file found in [`Config::search_paths`].
+    /// Falls back to the defaults if there is none or it cannot be parsed.
+    pub fn load() -> Self {
+        for path in Self::search_paths() {
+            let Ok(contents) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            return match toml::from_str(&contents) {
+                Ok(config) => config,
+                Err(e) => {
+                    eprintln!("Ignoring invalid config '{}': {}", path.display(), e);
+                    Self::default()
+                }
+            };
+        }
+        Self::default()
+    }
+}
@@ -1,19 +1,28 @@
+use chrono::{DateTime, DurationRound, Local, TimeDelta};
+use rusqlite::Connection;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
-/// Finds entry directories within a collection directory that contain a "data.h5" file.
+use crate::core::db;
+use crate::core::entry::{self, LoadOptions};
+
+/// Finds entry directories within a collection directory that contain at least one of
+/// the data files. Broken symlinks count, so that such entries are reported on load
+/// instead of silently dropped.
 ///
 /// # Arguments
 ///
 /// * `collection_path` - The path to the collection directory to search.
+/// * `data_files` - The names of the entry's data files, see [`LoadOptions::data_files`].
 ///
 /// # Returns
 ///
 /// A vector of `PathBuf` objects, each representing a directory inside the collection
-/// that contains a "data.h5" file. Any errors encountered while reading the directory
+/// that contains one of the data files. Any errors encountered while reading the directory
 /// or its entries are printed to stderr, and those entries are skipped.
-pub fn find_entries(collection_path: &Path) -> Vec<PathBuf> {
+pub fn find_entries(collection_path: &Path, data_files: &[String]) -> Vec<PathBuf> {
     let entries = match fs::read_dir(collection_path) {
         Ok(entries) => entries,
         Err(err) => {
@@ -50,51 +59,463 @@ pub fn find_entries(collection_path: &Path) -> Vec<PathBuf> {
                 None
             }
         })
-        .filter(|e| e.path().join("data.h5").exists())
+        .filter(|e| {
+            data_files
+                .iter()
+                .any(|name| e.path().join(name).symlink_metadata().is_ok())
+        })
         .map(|e| e.path())
         .collect()
 }
 
-/*
-pub fn sync(collection_path: &Path) -> Result<(), String> {
-    let entries = find_entries(collection_path);
+/// Options of [`sync`].
+#[derive(Clone, Debug)]
+pub struct SyncOptions {
+    /// Where to find metadata inside each entry
+    pub load: LoadOptions,
+    /// Only index entries that have no record in the database yet
+    pub new_only: bool,
+    /// Datasets whose storage layout is recorded for each synced entry
+    pub dataset_storage: Vec<String>,
+    /// Do not print progress per entry
+    pub quiet: bool,
+    /// Commit every this many upserts. Requires `conn` to be inside a transaction,
+    /// which is committed and immediately reopened after each batch.
+    pub batch_size: Option<usize>,
+    /// Measure the time spent in each phase, see [`Profile`], and reading each entry,
+    /// which is also stored with the simulation
+    pub profile: bool,
+    /// Data file mtimes and last sync times are truncated to this resolution before
+    /// comparing them, so that entries written in the same interval as their last sync
+    /// are re-read even on file systems with coarse mtimes. Zero compares them exactly
+    pub mtime_resolution: Duration,
+    /// Stop at the first entry that cannot be read instead of recording the failure and
+    /// continuing with the next one
+    pub fail_fast: bool,
+    /// How often to retry an entry whose data file another process holds open before
+    /// skipping it until the next sync, see [`entry::is_in_use`]
+    pub lock_retries: usize,
+    /// Wait between these retries
+    pub lock_retry_delay: Duration,
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        Self {
+            load: LoadOptions::default(),
+            new_only: false,
+            dataset_storage: Vec::new(),
+            quiet: false,
+            batch_size: None,
+            profile: false,
+            mtime_resolution: Duration::from_secs(1),
+            fail_fast: false,
+            lock_retries: 0,
+            lock_retry_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Truncates a timestamp to a multiple of `resolution` since the Unix epoch; a zero
+/// resolution keeps it unchanged.
+fn truncate_time(time: DateTime<Local>, resolution: Duration) -> DateTime<Local> {
+    match TimeDelta::from_std(resolution) {
+        Ok(delta) if !delta.is_zero() => time.duration_trunc(delta).unwrap_or(time),
+        _ => time,
+    }
+}
+
+/// Wall-clock time spent in each phase of a scan, see `scan --profile`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Profile {
+    /// Walking the root for collection markers
+    pub discovery: Duration,
+    /// Listing the entries of each collection
+    pub enumeration: Duration,
+    /// Reading metadata and parameters from the data files
+    pub loading: Duration,
+    /// Writing to the database
+    pub writing: Duration,
+}
+
+impl Profile {
+    /// Runs `f`, adding its duration to `phase` if `enabled`.
+    pub fn time<T>(enabled: bool, phase: &mut Duration, f: impl FnOnce() -> T) -> T {
+        if !enabled {
+            return f();
+        }
+        let start = Instant::now();
+        let out = f();
+        *phase += start.elapsed();
+        out
+    }
+
+    pub fn add(&mut self, other: &Profile) {
+        self.discovery += other.discovery;
+        self.enumeration += other.enumeration;
+        self.loading += other.loading;
+        self.writing += other.writing;
+    }
+}
+
+impl std::fmt::Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let phases = [
+            ("discovery", self.discovery),
+            ("enumeration", self.enumeration),
+            ("loading", self.loading),
+            ("writing", self.writing),
+        ];
+        let total: Duration = phases.iter().map(|(_, d)| *d).sum();
+        writeln!(f, "Profile:")?;
+        for (name, duration) in phases {
+            let share = if total.is_zero() {
+                0.0
+            } else {
+                100.0 * duration.as_secs_f64() / total.as_secs_f64()
+            };
+            writeln!(
+                f,
+                "  {:12} {:>10.3}s {:>5.1}%",
+                name,
+                duration.as_secs_f64(),
+                share
+            )?;
+        }
+        write!(f, "  {:12} {:>10.3}s", "total", total.as_secs_f64())
+    }
+}
+
+/// Outcome of syncing one collection.
+#[derive(Debug, Default)]
+pub struct SyncResult {
+    /// Names of the entries that were not in the database before
+    pub added: Vec<String>,
+    /// Names of known entries that were re-read because they changed
+    pub updated: Vec<String>,
+    /// Number of entries skipped because they did not change since the last sync
+    pub skipped: usize,
+    /// Entries that could not be read, with the reason
+    pub failed: Vec<(PathBuf, String)>,
+    /// Entries skipped because a data file is still being written; they are read again
+    /// on the next sync
+    pub in_use: Vec<PathBuf>,
+    /// Time spent per phase, only measured with [`SyncOptions::profile`]
+    pub profile: Profile,
+    /// Time spent reading each entry, only measured with [`SyncOptions::profile`]
+    pub load_times: Vec<(PathBuf, Duration)>,
+}
+
+impl SyncResult {
+    /// Number of entries written to the database
+    pub fn synced(&self) -> usize {
+        self.added.len() + self.updated.len()
+    }
+}
+
+/// Syncs the entries of the collection `uid` at `collection_path` into the database.
+/// Entries whose data files are older than their last sync are skipped, everything else
+/// is read and upserted. Unreadable entries are reported in the result; only database
+/// errors abort the sync. With `fail_fast`, the sync ends after the first unreadable entry.
+pub fn sync(
+    conn: &Connection,
+    uid: &str,
+    collection_path: &Path,
+    opts: &SyncOptions,
+) -> Result<SyncResult, String> {
+    let mut enumeration = Duration::ZERO;
+    let entries = Profile::time(opts.profile, &mut enumeration, || {
+        find_entries(collection_path, &opts.load.data_files)
+    });
+    let mut result = sync_entries(conn, uid, collection_path, entries, opts)?;
+    result.profile.enumeration += enumeration;
+    Ok(result)
+}
+
+/// Like [`sync`], but syncs only the given entry directories of the collection instead
+/// of listing it, e.g. the entries a job scheduler reports as changed.
+pub fn sync_entries(
+    conn: &Connection,
+    uid: &str,
+    collection_path: &Path,
+    entries: Vec<PathBuf>,
+    opts: &SyncOptions,
+) -> Result<SyncResult, String> {
+    let mut result = SyncResult::default();
+    Profile::time(opts.profile, &mut result.profile.writing, || {
+        db::upsert_collection(conn, uid, &collection_path.display().to_string())
+    })
+    .map_err(|e| format!("failed to write collection {}: {}", uid, e))?;
 
     for entry in entries {
+        if opts.fail_fast && !result.failed.is_empty() {
+            break;
+        }
         let entry_name = entry
             .file_name()
             .expect("entry has no file name")
             .to_string_lossy()
             .to_string();
-
         // check last sync time in db
-        let last_sync_time = db::get_sim_sync_time(&tx, c_uid, &entry_name);
+        let last_sync_time = db::get_sim_sync_time(conn, uid, &entry_name);
+
+        // with new_only, anything already known is skipped without opening it, even
+        // if it has no sync time, e.g. after `simdex touch`
+        if opts.new_only {
+            let known = db::simulation_exists(conn, uid, &entry_name)
+                .map_err(|e| format!("failed to read simulation {}: {}", entry_name, e))?;
+            if known {
+                result.skipped += 1;
+                continue;
+            }
+        }
 
         // only process if changed or new
-        let mtime = match crate::core::entry::get_data_h5_mtime(&entry) {
+        let mtime = match entry::get_data_mtime(&entry, &opts.load) {
             Some(ut) => ut,
             None => {
-                eprintln!("  [!] Failed to get mtime for entry: {:?}", entry);
+                let reason = opts
+                    .load
+                    .data_files
+                    .iter()
+                    .find_map(|name| entry::broken_symlink(&entry, name))
+                    .unwrap_or_else(|| "failed to get mtime of data files".to_string());
+                if !opts.quiet {
+                    eprintln!(
+                        "  [!] Failed to get mtime for entry: {:?}: {}",
+                        entry, reason
+                    );
+                }
+                result.failed.push((entry, reason));
                 continue;
             }
         };
 
-        // if last_sync_time is None, this will be false (not skipped)
-        if Some(mtime) < last_sync_time {
+        // if last_sync_time is None, this will be false (not skipped); within the same
+        // interval of the resolution the entry counts as changed
+        let truncate = |time| truncate_time(time, opts.mtime_resolution);
+        if Some(truncate(mtime)) < last_sync_time.map(truncate) {
             // unchanged -> skip
+            result.skipped += 1;
             continue;
         }
 
-        match entry::load_entry_meta(&entry) {
-            Some((meta, params)) => {
-                let sim_id = db::upsert_simulation(&tx, c_uid, &entry_name, &meta, &params)
-                    .expect("db insert sim");
-                println!("  Synced entry: {:?} [{}]", entry, sim_id);
+        // time spent reading this entry, without the waits between retries
+        let mut load_time = Duration::ZERO;
+        let mut loaded = Profile::time(opts.profile, &mut load_time, || {
+            entry::try_load_entry_meta(&entry, &opts.load)
+        });
+        for _ in 0..opts.lock_retries {
+            if !loaded
+                .as_ref()
+                .is_err_and(|reason| entry::is_in_use(reason))
+            {
+                break;
             }
-            None => {
-                println!("  [!] Failed to read entry: {:?}", entry);
+            std::thread::sleep(opts.lock_retry_delay);
+            loaded = Profile::time(opts.profile, &mut load_time, || {
+                entry::try_load_entry_meta(&entry, &opts.load)
+            });
+        }
+        if opts.profile {
+            result.profile.loading += load_time;
+            result.load_times.push((entry.clone(), load_time));
+        }
+        match loaded {
+            Ok((meta, params)) => {
+                let upserted = Profile::time(opts.profile, &mut result.profile.writing, || {
+                    db::upsert_simulation(conn, uid, &entry_name, &meta, &params)
+                })
+                .map_err(|e| format!("failed to write {:?}: {}", entry, e))?;
+                if opts.profile {
+                    db::set_simulation_load_time(conn, upserted.id, load_time)
+                        .map_err(|e| format!("failed to write {:?}: {}", entry, e))?;
+                }
+                if !opts.quiet {
+                    println!("  Synced entry: {:?} [{}]", entry, upserted.id);
+                }
+                let superblock = opts
+                    .load
+                    .data_files
+                    .iter()
+                    .map(|name| entry.join(name))
+                    .find(|path| path.exists())
+                    .and_then(|path| entry::read_superblock_version(&path));
+                db::set_simulation_superblock(conn, upserted.id, superblock)
+                    .map_err(|e| format!("failed to write {:?}: {}", entry, e))?;
+                if !opts.dataset_storage.is_empty() {
+                    match entry::read_dataset_storage(
+                        &entry,
+                        &opts.load.data_files,
+                        &opts.dataset_storage,
+                    ) {
+                        Ok(storage) => db::set_simulation_storage(conn, upserted.id, &storage)
+                            .map_err(|e| format!("failed to write {:?}: {}", entry, e))?,
+                        Err(reason) => {
+                            eprintln!("  [!] Failed to read storage of {:?}: {}", entry, reason)
+                        }
+                    }
+                }
+                if upserted.inserted {
+                    result.added.push(entry_name);
+                } else {
+                    result.updated.push(entry_name);
+                }
+                if let Some(n) = opts.batch_size
+                    && result.synced().is_multiple_of(n)
+                {
+                    // commit the batch and continue in a fresh transaction,
+                    // which the caller closes as usual
+                    conn.execute_batch("COMMIT; BEGIN")
+                        .map_err(|e| format!("failed to commit batch: {}", e))?;
+                }
+            }
+            Err(reason) if entry::is_in_use(&reason) => {
+                if !opts.quiet {
+                    println!(
+                        "  [~] Skipping entry {:?}: {}, will retry next scan",
+                        entry, reason
+                    );
+                }
+                result.in_use.push(entry);
+            }
+            Err(reason) => {
+                if !opts.quiet {
+                    println!("  [!] Failed to read entry: {:?}: {}", entry, reason);
+                }
+                result.failed.push((entry, reason));
             }
         }
     }
-    Ok(())
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_change_in_the_second_of_the_last_sync_is_reread() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let entry_path = entry::create_example_entry(tmp_dir.path()).unwrap();
+        let conn = db::open_or_init(":memory:").unwrap();
+        let opts = SyncOptions {
+            quiet: true,
+            ..Default::default()
+        };
+        sync(&conn, "C", tmp_dir.path(), &opts).unwrap();
+
+        // synced at .700, then rewritten on a file system with one-second mtimes
+        let second = DateTime::parse_from_rfc3339("2024-05-01T12:00:00+00:00").unwrap();
+        conn.execute(
+            "UPDATE simulations SET _last_sync_time = ?1",
+            [(second + TimeDelta::milliseconds(700)).to_rfc3339()],
+        )
+        .unwrap();
+        fs::File::options()
+            .write(true)
+            .open(entry_path.join("data.h5"))
+            .unwrap()
+            .set_modified(second.into())
+            .unwrap();
+
+        let exact = SyncOptions {
+            mtime_resolution: Duration::ZERO,
+            ..opts.clone()
+        };
+        assert_eq!(sync(&conn, "C", tmp_dir.path(), &exact).unwrap().skipped, 1);
+        let result = sync(&conn, "C", tmp_dir.path(), &opts).unwrap();
+        assert_eq!(result.updated, ["example"]);
+    }
+
+    #[test]
+    fn test_fail_fast_stops_at_the_first_unreadable_entry() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        for name in ["a", "b"] {
+            fs::create_dir(tmp_dir.path().join(name)).unwrap();
+            fs::write(tmp_dir.path().join(name).join("data.h5"), "not hdf5").unwrap();
+        }
+        let conn = db::open_or_init(":memory:").unwrap();
+        let opts = SyncOptions {
+            quiet: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            sync(&conn, "C", tmp_dir.path(), &opts)
+                .unwrap()
+                .failed
+                .len(),
+            2
+        );
+
+        let fail_fast = SyncOptions {
+            fail_fast: true,
+            ..opts
+        };
+        let result = sync(&conn, "C", tmp_dir.path(), &fail_fast).unwrap();
+        assert_eq!(result.failed.len(), 1);
+    }
+
+    #[test]
+    fn test_profile_stores_the_read_time_of_each_entry() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        entry::create_example_entry(tmp_dir.path()).unwrap();
+        let conn = db::open_or_init(":memory:").unwrap();
+        let opts = SyncOptions {
+            quiet: true,
+            profile: true,
+            ..Default::default()
+        };
+        let result = sync(&conn, "C", tmp_dir.path(), &opts).unwrap();
+        assert_eq!(result.load_times.len(), 1);
+        let load_seconds: Option<f64> = conn
+            .query_row(
+                "SELECT load_seconds FROM simulations WHERE name = 'example'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(load_seconds.is_some_and(|s| s >= 0.0));
+    }
+
+    #[test]
+    fn test_new_only_skips_known_entries_without_a_sync_time() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        entry::create_example_entry(tmp_dir.path()).unwrap();
+        let conn = db::open_or_init(":memory:").unwrap();
+        let opts = SyncOptions {
+            quiet: true,
+            new_only: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            sync(&conn, "C", tmp_dir.path(), &opts).unwrap().added,
+            ["example"]
+        );
+
+        conn.execute("UPDATE simulations SET _last_sync_time = NULL", [])
+            .unwrap();
+        let result = sync(&conn, "C", tmp_dir.path(), &opts).unwrap();
+        assert_eq!(result.skipped, 1);
+        assert!(result.updated.is_empty());
+    }
+
+    #[test]
+    fn test_sync_entries_reads_only_the_listed_entries() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let entry_path = entry::create_example_entry(tmp_dir.path()).unwrap();
+        let other = tmp_dir.path().join("other");
+        fs::create_dir(&other).unwrap();
+        fs::copy(entry_path.join("data.h5"), other.join("data.h5")).unwrap();
+        let conn = db::open_or_init(":memory:").unwrap();
+        let opts = SyncOptions {
+            quiet: true,
+            ..Default::default()
+        };
+
+        let result = sync_entries(&conn, "C", tmp_dir.path(), vec![other], &opts).unwrap();
+        assert_eq!(result.added, ["other"]);
+        assert!(db::simulation_exists(&conn, "C", "other").unwrap());
+        assert!(!db::simulation_exists(&conn, "C", "example").unwrap());
+    }
 }
-*/
@@ -1,16 +1,14 @@
-use rusqlite::{Connection, OptionalExtension, params};
+use rusqlite::{Connection, OpenFlags, OptionalExtension, params};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::path::{Path, PathBuf};
 
 use crate::core::types::{MetaData, Parameters};
 
-pub fn open_or_init<P: AsRef<Path>>(db_path: P) -> rusqlite::Result<Connection> {
-    let conn = Connection::open(db_path)?;
-    conn.execute_batch(
-        r#"
-        CREATE TABLE IF NOT EXISTS collections (
-            uid TEXT PRIMARY KEY,
-            path TEXT NOT NULL
-        );
+/// Number of scans kept in the `scan_runs` table, older ones are pruned
+pub const SCAN_RUNS_KEEP: usize = 100;
+
+const CREATE_SIMULATIONS: &str = "
         CREATE TABLE IF NOT EXISTS simulations (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             collection_uid TEXT NOT NULL,
@@ -21,10 +19,419 @@ pub fn open_or_init<P: AsRef<Path>>(db_path: P) -> rusqlite::Result<Connection>
             submitted INTEGER,
             parameters_json JSON,
             _last_sync_time TEXT,
+            storage_json JSON,
+            hdf5_superblock INTEGER,
+            load_seconds REAL,
+            params_hash TEXT,
+            status_override TEXT,
             UNIQUE(collection_uid, name)
+        );";
+
+/// Opens the database at `db_path`, creating and migrating its tables as needed. A
+/// directory is resolved for the current directory, see [`find_db_path`]. Fails
+/// without touching the database if its tables cannot be migrated, see
+/// [`schema_mismatch`].
+pub fn open_or_init<P: AsRef<Path>>(db_path: P) -> Result<Connection, String> {
+    let db_path = find_db_path(db_path.as_ref(), Path::new("."));
+    let conn = Connection::open(&db_path).map_err(|e| e.to_string())?;
+    if let Some(mismatch) = schema_mismatch(&conn).map_err(|e| e.to_string())? {
+        return Err(format!("{}: {}", db_path.display(), mismatch));
+    }
+    init_schema(&conn).map_err(|e| e.to_string())?;
+    Ok(conn)
+}
+
+/// Returns the names of the columns of `table`, empty if there is no such table.
+fn table_columns(conn: &Connection, table: &str) -> rusqlite::Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let names = stmt.query_map([], |row| row.get::<_, String>(1))?;
+    names.collect()
+}
+
+/// Describes why the existing tables of a database cannot be migrated to the current
+/// schema, e.g. a database of another application, or a legacy cache that kept its
+/// parameters in a separate `parameters` table. Returns None for fresh databases and
+/// those the migrations of [`open_or_init`] handle.
+pub fn schema_mismatch(conn: &Connection) -> rusqlite::Result<Option<String>> {
+    let legacy = is_legacy_schema(conn)?;
+    let simulations = table_columns(conn, "simulations")?;
+    if legacy
+        && !simulations.iter().any(|c| c == "parameters_json")
+        && !table_columns(conn, "parameters")?.is_empty()
+    {
+        return Ok(Some(
+            "this legacy cache keeps its parameters in a separate `parameters` table, \
+             which cannot be migrated; scan into a new database instead"
+                .to_string(),
+        ));
+    }
+
+    let mut required = vec![("simulations", "collection_uid"), ("collections", "uid")];
+    if !legacy {
+        // the legacy upgrade derives `name` from `path` and leaves parameters empty
+        required.extend([("simulations", "name"), ("simulations", "parameters_json")]);
+    }
+    required.push(("collections", "path"));
+    let mut missing = Vec::new();
+    for (table, column) in required {
+        let columns = table_columns(conn, table)?;
+        if !columns.is_empty() && !columns.iter().any(|c| c == column) {
+            missing.push(format!("{}.{}", table, column));
+        }
+    }
+    if missing.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(format!(
+        "not a simdex cache or written by an incompatible version, missing columns {}; \
+         scan into a new database instead",
+        missing.join(", ")
+    )))
+}
+
+/// Creates the tables of a database and migrates older ones.
+fn init_schema(conn: &Connection) -> rusqlite::Result<()> {
+    let mut needs_hashes = upgrade_legacy_simulations(conn)?.is_some();
+    let tracks_param_keys = !table_columns(conn, "collection_param_keys")?.is_empty();
+    conn.execute_batch(CREATE_SIMULATIONS)?;
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS collections (
+            uid TEXT PRIMARY KEY,
+            path TEXT NOT NULL,
+            description TEXT,
+            parent_uid TEXT
+        );
+        CREATE TABLE IF NOT EXISTS sync_log (
+            simulation_id INTEGER NOT NULL,
+            synced_at TEXT NOT NULL,
+            old_status TEXT,
+            new_status TEXT,
+            params_changed INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE TABLE IF NOT EXISTS collection_param_keys (
+            collection_uid TEXT NOT NULL,
+            key TEXT NOT NULL,
+            first_seen TEXT NOT NULL,
+            last_seen TEXT NOT NULL,
+            PRIMARY KEY (collection_uid, key)
+        );
+        CREATE TABLE IF NOT EXISTS scan_runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            scanned_at TEXT NOT NULL,
+            root TEXT NOT NULL,
+            collections INTEGER NOT NULL,
+            synced INTEGER NOT NULL,
+            failed INTEGER NOT NULL
         );
     "#,
     )?;
+    // databases created before collections had a description
+    if !has_column(conn, "collections", "description")? {
+        conn.execute("ALTER TABLE collections ADD COLUMN description TEXT", [])?;
+    }
+    if !has_column(conn, "collections", "parent_uid")? {
+        conn.execute("ALTER TABLE collections ADD COLUMN parent_uid TEXT", [])?;
+    }
+    if !has_column(conn, "simulations", "storage_json")? {
+        conn.execute("ALTER TABLE simulations ADD COLUMN storage_json JSON", [])?;
+    }
+    if !has_column(conn, "simulations", "hdf5_superblock")? {
+        conn.execute(
+            "ALTER TABLE simulations ADD COLUMN hdf5_superblock INTEGER",
+            [],
+        )?;
+    }
+    if !has_column(conn, "simulations", "load_seconds")? {
+        conn.execute("ALTER TABLE simulations ADD COLUMN load_seconds REAL", [])?;
+    }
+    if !has_column(conn, "simulations", "status_override")? {
+        conn.execute(
+            "ALTER TABLE simulations ADD COLUMN status_override TEXT",
+            [],
+        )?;
+    }
+    if !has_column(conn, "simulations", "params_hash")? {
+        conn.execute("ALTER TABLE simulations ADD COLUMN params_hash TEXT", [])?;
+        needs_hashes = true;
+    }
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS simulations_params_hash ON simulations (collection_uid, params_hash)",
+        [],
+    )?;
+    if needs_hashes {
+        backfill_params_hash(conn)?;
+    }
+    if !tracks_param_keys {
+        backfill_param_keys(conn, None)?;
+    }
+    Ok(())
+}
+
+/// Serializes parameters as compact JSON with the keys of all objects sorted, so that
+/// equal parameters always give the same string.
+pub fn canonical_json(parameters: &Parameters) -> String {
+    fn sorted(value: &Value) -> Value {
+        match value {
+            Value::Object(map) => {
+                let entries: BTreeMap<&String, Value> =
+                    map.iter().map(|(k, v)| (k, sorted(v))).collect();
+                Value::Object(entries.into_iter().map(|(k, v)| (k.clone(), v)).collect())
+            }
+            Value::Array(items) => Value::Array(items.iter().map(sorted).collect()),
+            other => other.clone(),
+        }
+    }
+    let entries: BTreeMap<&String, Value> =
+        parameters.iter().map(|(k, v)| (k, sorted(v))).collect();
+    serde_json::to_string(&entries).unwrap_or("{}".to_string())
+}
+
+/// 64-bit FNV-1a hash, stable across platforms and releases.
+fn fnv1a(s: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in s.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// 64-bit FNV-1a hash of the canonical parameters JSON, as 16 hex digits. Not
+/// cryptographic, only meant to cheaply compare parameter sets.
+pub fn params_hash(canonical_json: &str) -> String {
+    format!("{:016x}", fnv1a(canonical_json))
+}
+
+/// Resolves a `--db` argument. A directory holds one database per root, named after
+/// the root's last path component plus a hash of its canonical path, e.g.
+/// `project-1a2b3c4d.db`; any other path is returned unchanged.
+pub fn resolve_db_path(db_path: &Path, root: &Path) -> PathBuf {
+    if !db_path.is_dir() {
+        return db_path.to_path_buf();
+    }
+    let root = root.canonicalize().unwrap_or(root.to_path_buf());
+    let name: String = root
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or("root".to_string())
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let hash = fnv1a(&root.to_string_lossy()) as u32;
+    db_path.join(format!("{}-{:08x}.db", name, hash))
+}
+
+/// Resolves a `--db` argument of a command that is not given a root, like [`scan`] is,
+/// for the directory `dir`. For a directory, this is the database of the closest
+/// scanned root that contains `dir`, named as by [`resolve_db_path`], so that it is
+/// found from anywhere below the root; if there is none, the one for `dir` itself.
+/// Any other path is returned unchanged.
+///
+/// [`scan`]: crate::api::scan
+pub fn find_db_path(db_path: &Path, dir: &Path) -> PathBuf {
+    if !db_path.is_dir() {
+        return db_path.to_path_buf();
+    }
+    let dir = dir.canonicalize().unwrap_or(dir.to_path_buf());
+    dir.ancestors()
+        .map(|root| resolve_db_path(db_path, root))
+        .find(|path| path.is_file())
+        .unwrap_or_else(|| resolve_db_path(db_path, &dir))
+}
+
+/// Fills `collection_param_keys` from the simulations, with the first and last sync of
+/// the simulations having each key, e.g. for a database that predates it. Keys that are
+/// tracked already are left alone. With `collection_uid`, only that collection is filled.
+fn backfill_param_keys(conn: &Connection, collection_uid: Option<&str>) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO main.collection_param_keys
+            (collection_uid, key, first_seen, last_seen)
+         SELECT s.collection_uid, p.key,
+                COALESCE(MIN(s._last_sync_time), ?1), COALESCE(MAX(s._last_sync_time), ?1)
+         FROM main.simulations s, json_each(s.parameters_json) p
+         WHERE s.collection_uid IS NOT NULL AND json_valid(s.parameters_json)
+               AND (?2 IS NULL OR s.collection_uid = ?2)
+         GROUP BY s.collection_uid, p.key",
+        params![chrono::offset::Local::now().to_rfc3339(), collection_uid],
+    )?;
+    Ok(())
+}
+
+/// Removes the tracked keys of the collection `collection_uid` that none of its
+/// simulations has anymore, all of them or only `key`.
+fn prune_param_keys(
+    conn: &Connection,
+    collection_uid: &str,
+    key: Option<&str>,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "DELETE FROM collection_param_keys
+         WHERE collection_uid = ?1 AND (?2 IS NULL OR key = ?2)
+           AND key NOT IN (
+               SELECT p.key FROM simulations s, json_each(s.parameters_json) p
+               WHERE s.collection_uid = ?1 AND json_valid(s.parameters_json))",
+        params![collection_uid, key],
+    )?;
+    Ok(())
+}
+
+/// Returns the collection of the simulation with the given id, if there is one.
+fn simulation_collection(conn: &Connection, id: i64) -> rusqlite::Result<Option<String>> {
+    conn.query_row(
+        "SELECT collection_uid FROM simulations WHERE id = ?1",
+        [id],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// Fills `params_hash` of rows that have none, rewriting their `parameters_json` in
+/// canonical form. Rows with malformed JSON are left alone.
+fn backfill_params_hash(conn: &Connection) -> rusqlite::Result<()> {
+    let rows: Vec<(i64, Option<String>)> = conn
+        .prepare("SELECT id, parameters_json FROM simulations WHERE params_hash IS NULL")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    for (id, json) in rows {
+        let parameters: Parameters = match json.as_deref().map(serde_json::from_str) {
+            Some(Ok(parameters)) => parameters,
+            Some(Err(_)) => continue,
+            None => Parameters::new(),
+        };
+        let canonical = canonical_json(&parameters);
+        let hash = params_hash(&canonical);
+        conn.execute(
+            "UPDATE simulations SET parameters_json = ?2, params_hash = ?3 WHERE id = ?1",
+            params![id, canonical, hash],
+        )?;
+    }
+    Ok(())
+}
+
+/// Returns whether the database has a `simulations` table from the legacy schema,
+/// which identified simulations by their `path` instead of a `name`.
+pub fn is_legacy_schema(conn: &Connection) -> rusqlite::Result<bool> {
+    Ok(has_column(conn, "simulations", "path")? && !has_column(conn, "simulations", "name")?)
+}
+
+/// Rebuilds a legacy `simulations` table in the current schema, deriving each `name`
+/// from the last component of its `path`. Columns the legacy table lacks are left
+/// empty. Returns the number of migrated rows, or None if there was nothing to do.
+pub fn upgrade_legacy_simulations(conn: &Connection) -> rusqlite::Result<Option<usize>> {
+    use rusqlite::types::Value as SqlValue;
+
+    if !is_legacy_schema(conn)? {
+        return Ok(None);
+    }
+    let columns = [
+        "collection_uid",
+        "created_at",
+        "description",
+        "status",
+        "submitted",
+        "parameters_json",
+        "_last_sync_time",
+    ];
+    let mut select = vec!["path".to_string()];
+    for column in columns {
+        select.push(if has_column(conn, "simulations", column)? {
+            column.to_string()
+        } else {
+            format!("NULL AS {}", column)
+        });
+    }
+
+    conn.execute_batch("SAVEPOINT upgrade; ALTER TABLE simulations RENAME TO simulations_legacy;")?;
+    conn.execute_batch(CREATE_SIMULATIONS)?;
+    let mut migrated = 0;
+    {
+        let mut read = conn.prepare(&format!(
+            "SELECT {} FROM simulations_legacy ORDER BY rowid",
+            select.join(", ")
+        ))?;
+        let mut insert = conn.prepare(&format!(
+            "INSERT OR IGNORE INTO simulations (name, {}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            columns.join(", ")
+        ))?;
+        let mut rows = read.query([])?;
+        while let Some(row) = rows.next()? {
+            let path: String = row.get(0)?;
+            let name = Path::new(&path)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or(path.clone());
+            let mut values: Vec<SqlValue> = (1..=columns.len())
+                .map(|i| row.get(i))
+                .collect::<Result<_, _>>()?;
+            if values[0] == SqlValue::Null {
+                values[0] = SqlValue::Text(String::new());
+            }
+            migrated += insert.execute(rusqlite::params_from_iter(
+                std::iter::once(SqlValue::Text(name)).chain(values),
+            ))?;
+        }
+    }
+    conn.execute_batch("DROP TABLE simulations_legacy; RELEASE upgrade;")?;
+    Ok(Some(migrated))
+}
+
+/// Returns whether `table` has a column named `column`, e.g. to read caches opened
+/// with [`open_existing`] that predate the column.
+pub fn has_column(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+    Ok(table_columns(conn, table)?.iter().any(|c| c == column))
+}
+
+/// SQL for the status of a simulation `s` as shown to users: the one set with
+/// [`set_status_override`] if any, else the one read from its entry.
+pub fn status_sql(conn: &Connection) -> rusqlite::Result<&'static str> {
+    Ok(if has_column(conn, "simulations", "status_override")? {
+        "COALESCE(s.status_override, s.status)"
+    } else {
+        "s.status"
+    })
+}
+
+/// Opens an existing cache database read-only, without creating it or its tables.
+///
+/// # Errors
+///
+/// Fails with a descriptive message if the file does not exist, cannot be opened,
+/// or does not contain the simdex tables (i.e. is not a simdex cache).
+pub fn open_existing<P: AsRef<Path>>(db_path: P) -> Result<Connection, String> {
+    let db_path = &find_db_path(db_path.as_ref(), Path::new("."));
+    if !db_path.is_file() {
+        return Err(format!("no such database: {}", db_path.display()));
+    }
+    let conn = Connection::open_with_flags(
+        db_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|e| format!("cannot open database {}: {}", db_path.display(), e))?;
+
+    let n_tables: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master
+             WHERE type = 'table' AND name IN ('collections', 'simulations')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("{} is not a simdex database: {}", db_path.display(), e))?;
+    if n_tables != 2 {
+        return Err(format!("{} is not a simdex database", db_path.display()));
+    }
+    if is_legacy_schema(&conn).map_err(|e| e.to_string())? {
+        return Err(format!(
+            "{} uses the legacy schema; run `simdex upgrade-db` first",
+            db_path.display()
+        ));
+    }
     Ok(conn)
 }
 
@@ -40,6 +447,63 @@ pub fn get_collection_path(conn: &Connection, uid: &str) -> Option<PathBuf> {
     path.map(PathBuf::from)
 }
 
+/// Copies the collection `uid`, its simulations and their sync history from the database
+/// attached as `src` into the main database of `conn`, keeping the simulation ids.
+/// Columns missing on either side are skipped. Returns the number of copied simulations.
+pub fn copy_attached_collection(conn: &Connection, uid: &str) -> rusqlite::Result<usize> {
+    let shared_columns = |table: &str| -> rusqlite::Result<String> {
+        let mut stmt = conn.prepare(
+            "SELECT name FROM pragma_table_info(?1, 'main')
+             WHERE name IN (SELECT name FROM pragma_table_info(?1, 'src'))",
+        )?;
+        let names = stmt.query_map(params![table], |row| row.get::<_, String>(0))?;
+        Ok(names.collect::<rusqlite::Result<Vec<_>>>()?.join(", "))
+    };
+
+    let columns = shared_columns("collections")?;
+    conn.execute(
+        &format!(
+            "INSERT INTO main.collections ({0}) SELECT {0} FROM src.collections WHERE uid = ?1",
+            columns
+        ),
+        params![uid],
+    )?;
+    let columns = shared_columns("simulations")?;
+    let copied = conn.execute(
+        &format!(
+            "INSERT INTO main.simulations ({0})
+             SELECT {0} FROM src.simulations WHERE collection_uid = ?1",
+            columns
+        ),
+        params![uid],
+    )?;
+    // keep when each key was first seen, and track any the source lacks
+    let columns = shared_columns("collection_param_keys")?;
+    if !columns.is_empty() {
+        conn.execute(
+            &format!(
+                "INSERT OR IGNORE INTO main.collection_param_keys ({0})
+                 SELECT {0} FROM src.collection_param_keys WHERE collection_uid = ?1",
+                columns
+            ),
+            params![uid],
+        )?;
+    }
+    backfill_param_keys(conn, Some(uid))?;
+    let columns = shared_columns("sync_log")?;
+    if !columns.is_empty() {
+        conn.execute(
+            &format!(
+                "INSERT INTO main.sync_log ({0}) SELECT {0} FROM src.sync_log
+                 WHERE simulation_id IN (SELECT id FROM main.simulations)",
+                columns
+            ),
+            [],
+        )?;
+    }
+    Ok(copied)
+}
+
 /// Returns the collection uid for the given path, or None if not found
 pub fn get_collection_uid(conn: &Connection, path: &Path) -> Option<String> {
     let mut stmt = conn
@@ -52,6 +516,543 @@ pub fn get_collection_uid(conn: &Connection, path: &Path) -> Option<String> {
     uid
 }
 
+/// Parses the stored `parameters_json` of simulation `id`. Malformed JSON is an error
+/// if `strict` is set; otherwise a warning with the offending snippet is printed and
+/// the simulation is treated as having no parameters.
+pub fn parse_parameters_json(id: i64, json: &str, strict: bool) -> Result<Parameters, String> {
+    match serde_json::from_str(json) {
+        Ok(parameters) => Ok(parameters),
+        Err(e) => {
+            let snippet: String = json.chars().take(60).collect();
+            let ellipsis = if snippet.len() < json.len() {
+                "..."
+            } else {
+                ""
+            };
+            let msg = format!(
+                "simulation {} has invalid parameters_json ({}): {}{}",
+                id, e, snippet, ellipsis
+            );
+            if strict {
+                return Err(msg);
+            }
+            eprintln!("[!] {}", msg);
+            Ok(Parameters::new())
+        }
+    }
+}
+
+/// Returns the parsed parameters of every simulation in the given collection. Those
+/// without stored parameters, e.g. cleared by `repair-json`, have none.
+pub fn get_parameters(
+    conn: &Connection,
+    collection_uid: &str,
+) -> rusqlite::Result<Vec<Parameters>> {
+    let mut stmt =
+        conn.prepare("SELECT id, parameters_json FROM simulations WHERE collection_uid = ?1")?;
+    let rows = stmt.query_map(params![collection_uid], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?))
+    })?;
+    let mut out = Vec::new();
+    for row in rows {
+        let (id, json) = row?;
+        out.push(
+            json.map(|json| parse_parameters_json(id, &json, false).unwrap_or_default())
+                .unwrap_or_default(),
+        );
+    }
+    Ok(out)
+}
+
+/// A simulation whose stored `parameters_json` is not valid JSON, see `simdex repair-json`
+#[derive(Debug)]
+pub struct InvalidParameters {
+    pub id: i64,
+    pub collection_uid: String,
+    pub name: String,
+    /// Why the JSON does not parse
+    pub error: String,
+}
+
+/// Returns the simulations whose `parameters_json` does not parse as a JSON object, in
+/// all collections or only in `collection`. Missing values are not reported.
+pub fn find_invalid_parameters(
+    conn: &Connection,
+    collection: Option<&str>,
+) -> rusqlite::Result<Vec<InvalidParameters>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, collection_uid, name, parameters_json FROM simulations
+         WHERE parameters_json IS NOT NULL AND (?1 IS NULL OR collection_uid = ?1)
+         ORDER BY id",
+    )?;
+    let rows = stmt.query_map([collection], |row| {
+        Ok((
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+            row.get::<_, String>(3)?,
+        ))
+    })?;
+    let mut invalid = Vec::new();
+    for row in rows {
+        let (id, collection_uid, name, json) = row?;
+        if let Err(e) = serde_json::from_str::<Parameters>(&json) {
+            invalid.push(InvalidParameters {
+                id,
+                collection_uid,
+                name,
+                error: e.to_string(),
+            });
+        }
+    }
+    Ok(invalid)
+}
+
+/// Sets the `parameters_json` of a simulation to NULL, so it shows no parameters
+/// without a warning.
+pub fn clear_parameters_json(conn: &Connection, id: i64) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE simulations SET parameters_json = NULL, params_hash = NULL WHERE id = ?1",
+        [id],
+    )?;
+    if let Some(collection_uid) = simulation_collection(conn, id)? {
+        prune_param_keys(conn, &collection_uid, None)?;
+    }
+    Ok(())
+}
+
+/// A simulation as stored in the cache
+#[derive(Debug)]
+pub struct Simulation {
+    pub id: i64,
+    pub name: String,
+    pub created_at: Option<String>,
+    pub description: Option<String>,
+    pub status: Option<String>,
+    pub submitted: bool,
+    pub parameters: Parameters,
+}
+
+fn simulation_from_row(row: &rusqlite::Row) -> rusqlite::Result<Simulation> {
+    let id = row.get(0)?;
+    let parameters_json: Option<String> = row.get(6)?;
+    Ok(Simulation {
+        id,
+        name: row.get(1)?,
+        created_at: row.get(2)?,
+        description: row.get(3)?,
+        status: row.get(4)?,
+        submitted: row.get::<_, Option<bool>>(5)?.unwrap_or(false),
+        parameters: parameters_json
+            .map(|json| parse_parameters_json(id, &json, false).unwrap_or_default())
+            .unwrap_or_default(),
+    })
+}
+
+/// Returns all simulations of a collection, ordered by id. The status is the one set
+/// with [`set_status_override`] if any.
+pub fn get_simulations(
+    conn: &Connection,
+    collection_uid: &str,
+) -> rusqlite::Result<Vec<Simulation>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, name, created_at, description, {}, submitted, parameters_json
+         FROM simulations s WHERE collection_uid = ?1 ORDER BY id",
+        status_sql(conn)?
+    ))?;
+    let rows = stmt.query_map(params![collection_uid], simulation_from_row)?;
+    rows.collect()
+}
+
+/// Returns the status read from the entry and the one set with [`set_status_override`]
+/// of each simulation of a collection that has the latter, by name.
+pub fn get_status_overrides(
+    conn: &Connection,
+    collection_uid: &str,
+) -> rusqlite::Result<HashMap<String, (Option<String>, String)>> {
+    if !has_column(conn, "simulations", "status_override")? {
+        return Ok(HashMap::new());
+    }
+    let mut stmt = conn.prepare(
+        "SELECT name, status, status_override FROM simulations
+         WHERE collection_uid = ?1 AND status_override IS NOT NULL",
+    )?;
+    let rows = stmt.query_map(params![collection_uid], |row| {
+        Ok((row.get(0)?, (row.get(1)?, row.get(2)?)))
+    })?;
+    rows.collect()
+}
+
+/// Returns the names of the simulations of a collection, ordered by id.
+pub fn simulation_names(conn: &Connection, collection_uid: &str) -> rusqlite::Result<Vec<String>> {
+    let mut stmt =
+        conn.prepare("SELECT name FROM simulations WHERE collection_uid = ?1 ORDER BY id")?;
+    let names = stmt.query_map(params![collection_uid], |row| row.get(0))?;
+    names.collect()
+}
+
+/// Returns up to `limit` simulations of a collection with an id above `after_id`,
+/// ordered by id. Passing the last id of one page to get the next reads a large
+/// collection in bounded memory. The status is the one set with [`set_status_override`]
+/// if any.
+pub fn get_simulations_page(
+    conn: &Connection,
+    collection_uid: &str,
+    after_id: i64,
+    limit: usize,
+) -> rusqlite::Result<Vec<Simulation>> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT id, name, created_at, description, {}, submitted, parameters_json
+         FROM simulations s WHERE collection_uid = ?1 AND id > ?2 ORDER BY id LIMIT ?3",
+        status_sql(conn)?
+    ))?;
+    let rows = stmt.query_map(
+        params![collection_uid, after_id, limit as i64],
+        simulation_from_row,
+    )?;
+    rows.collect()
+}
+
+/// Summary of one parameter key across the simulations of a collection
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamSummary {
+    /// One of the values
+    pub example: Value,
+    /// Number of simulations that have this parameter
+    pub count: usize,
+    /// Number of distinct values of this parameter
+    pub distinct_count: usize,
+    /// Whether every value of this parameter is a number
+    pub is_numeric: bool,
+    /// Smallest numeric value, None if no value is numeric
+    pub min: Option<f64>,
+    /// Largest numeric value, None if no value is numeric
+    pub max: Option<f64>,
+    /// JSON types of the values: `int`, `float`, `bool`, `string`, `array`, `object`, `null`
+    pub types: BTreeSet<String>,
+}
+
+/// Maps an SQLite `json_each` type to the name used in [`ParamSummary::types`].
+fn json_type_name(sqlite_type: &str) -> &str {
+    match sqlite_type {
+        "integer" => "int",
+        "real" => "float",
+        "true" | "false" => "bool",
+        "text" => "string",
+        other => other,
+    }
+}
+
+/// Aggregates the parameters of a collection per key, in a single grouped query over
+/// `parameters_json`. Rows whose `parameters_json` is not valid JSON are ignored.
+/// The result is keyed by parameter name, so iterating it yields the keys in a stable,
+/// alphabetical order. With `status`, only simulations with that status, or that were
+/// set to it with [`set_status_override`], are considered.
+pub fn aggregate_parameters(
+    conn: &Connection,
+    collection_uid: &str,
+    status: Option<&str>,
+) -> rusqlite::Result<BTreeMap<String, ParamSummary>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT p.key,
+                COUNT(*),
+                COUNT(DISTINCT json_quote(p.value)),
+                SUM(p.type IN ('integer', 'real')),
+                MIN(CASE WHEN p.type IN ('integer', 'real') THEN p.value END),
+                MAX(CASE WHEN p.type IN ('integer', 'real') THEN p.value END),
+                MIN(json_quote(p.value)),
+                group_concat(DISTINCT p.type)
+         FROM simulations s, json_each(s.parameters_json) p
+         WHERE s.collection_uid = ?1 AND json_valid(s.parameters_json)
+               AND (?2 IS NULL OR {} = ?2)
+         GROUP BY p.key",
+        status_sql(conn)?
+    ))?;
+    let rows = stmt.query_map(params![collection_uid, status], |row| {
+        let count = row.get::<_, i64>(1)?;
+        let example: String = row.get(6)?;
+        let types: String = row.get(7)?;
+        Ok((
+            row.get::<_, String>(0)?,
+            ParamSummary {
+                example: serde_json::from_str(&example).unwrap_or(Value::String(example)),
+                count: count as usize,
+                distinct_count: row.get::<_, i64>(2)? as usize,
+                is_numeric: row.get::<_, i64>(3)? == count,
+                min: row.get(4)?,
+                max: row.get(5)?,
+                types: types
+                    .split(',')
+                    .map(|t| json_type_name(t).to_string())
+                    .collect(),
+            },
+        ))
+    })?;
+    rows.collect()
+}
+
+/// Returns the distinct values of each parameter of a collection that has at most
+/// `max_distinct` of them, sorted by their JSON text. Keys with more values are omitted.
+pub fn low_cardinality_values(
+    conn: &Connection,
+    collection_uid: &str,
+    max_distinct: usize,
+) -> rusqlite::Result<BTreeMap<String, Vec<Value>>> {
+    let mut stmt = conn.prepare(
+        "WITH vals AS (
+             SELECT DISTINCT p.key AS key, json_quote(p.value) AS value
+             FROM simulations s, json_each(s.parameters_json) p
+             WHERE s.collection_uid = ?1 AND json_valid(s.parameters_json)
+         )
+         SELECT key, value FROM vals
+         WHERE key IN (SELECT key FROM vals GROUP BY key HAVING COUNT(*) <= ?2)
+         ORDER BY key, value",
+    )?;
+    let rows = stmt.query_map(params![collection_uid, max_distinct as i64], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+    let mut values: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    for row in rows {
+        let (key, value) = row?;
+        let value = serde_json::from_str(&value).unwrap_or(Value::String(value));
+        values.entry(key).or_default().push(value);
+    }
+    Ok(values)
+}
+
+/// Returns the sorted union of the parameter keys of all simulations in a collection.
+/// Simulations with malformed `parameters_json` are ignored, as are those without the
+/// given `status`, read from the entry or set with [`set_status_override`]. Without
+/// `status`, the keys are read from those tracked by [`upsert_simulation`] if there are
+/// any, see [`tracked_param_keys`].
+pub fn collection_param_keys(
+    conn: &Connection,
+    collection_uid: &str,
+    status: Option<&str>,
+) -> rusqlite::Result<BTreeSet<String>> {
+    if status.is_none() {
+        let tracked: BTreeSet<String> = tracked_param_keys(conn, collection_uid)?
+            .into_iter()
+            .map(|k| k.key)
+            .collect();
+        if !tracked.is_empty() {
+            return Ok(tracked);
+        }
+    }
+    let mut stmt = conn.prepare(&format!(
+        "SELECT DISTINCT p.key
+         FROM simulations s, json_each(s.parameters_json) p
+         WHERE s.collection_uid = ?1 AND json_valid(s.parameters_json)
+               AND (?2 IS NULL OR {} = ?2)",
+        status_sql(conn)?
+    ))?;
+    let keys = stmt.query_map(params![collection_uid, status], |row| row.get(0))?;
+    keys.collect()
+}
+
+/// A parameter key of a collection as tracked by [`upsert_simulation`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackedParamKey {
+    pub key: String,
+    /// When a simulation with this key was first synced, RFC 3339
+    pub first_seen: String,
+    /// When a simulation with this key was last synced, RFC 3339
+    pub last_seen: String,
+}
+
+/// Returns the parameter keys of a collection in the order they first appeared. Empty
+/// for caches that predate key tracking and are opened read-only.
+pub fn tracked_param_keys(
+    conn: &Connection,
+    collection_uid: &str,
+) -> rusqlite::Result<Vec<TrackedParamKey>> {
+    if table_columns(conn, "collection_param_keys")?.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn.prepare_cached(
+        "SELECT key, first_seen, last_seen FROM collection_param_keys
+         WHERE collection_uid = ?1 ORDER BY first_seen, key",
+    )?;
+    let keys = stmt.query_map([collection_uid], |row| {
+        Ok(TrackedParamKey {
+            key: row.get(0)?,
+            first_seen: row.get(1)?,
+            last_seen: row.get(2)?,
+        })
+    })?;
+    keys.collect()
+}
+
+/// How often a parameter key has values of one JSON type in a collection
+#[derive(Debug)]
+pub struct ParamTypeUsage {
+    pub key: String,
+    /// Type name as in [`ParamSummary::types`]
+    pub json_type: String,
+    /// Number of simulations with a value of this type
+    pub count: usize,
+    /// Id of one of these simulations
+    pub example_id: i64,
+}
+
+/// Returns, per parameter key and JSON type, how many simulations of a collection have
+/// a value of that type. Ordered by key, then type.
+pub fn param_type_usage(
+    conn: &Connection,
+    collection_uid: &str,
+) -> rusqlite::Result<Vec<ParamTypeUsage>> {
+    let mut stmt = conn.prepare(
+        "SELECT p.key,
+                CASE WHEN p.type IN ('true', 'false') THEN 'bool' ELSE p.type END AS t,
+                COUNT(*),
+                MIN(s.id)
+         FROM simulations s, json_each(s.parameters_json) p
+         WHERE s.collection_uid = ?1 AND json_valid(s.parameters_json)
+         GROUP BY p.key, t
+         ORDER BY p.key, t",
+    )?;
+    let rows = stmt.query_map(params![collection_uid], |row| {
+        Ok(ParamTypeUsage {
+            key: row.get(0)?,
+            json_type: json_type_name(&row.get::<_, String>(1)?).to_string(),
+            count: row.get::<_, i64>(2)? as usize,
+            example_id: row.get(3)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Returns the number of simulations of a collection whose parameters can be aggregated,
+/// i.e. whose `parameters_json` is valid JSON.
+pub fn count_simulations(conn: &Connection, collection_uid: &str) -> rusqlite::Result<usize> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM simulations WHERE collection_uid = ?1 AND json_valid(parameters_json)",
+        params![collection_uid],
+        |row| row.get::<_, i64>(0).map(|n| n as usize),
+    )
+}
+
+/// Returns the names of simulations sharing the same parameters, one group per
+/// parameter set with more than one simulation, in order of the first name.
+pub fn get_duplicate_params(
+    conn: &Connection,
+    collection_uid: &str,
+) -> rusqlite::Result<Vec<Vec<String>>> {
+    // caches opened read-only are not migrated; without hashes, compare the stored
+    // JSON, which misses duplicates written with a different key order
+    let column = if has_column(conn, "simulations", "params_hash")? {
+        "params_hash"
+    } else {
+        "parameters_json"
+    };
+    let mut stmt = conn.prepare(&format!(
+        "SELECT group_concat(name, char(31)) FROM simulations
+        WHERE collection_uid = ?1 AND {0} IS NOT NULL
+        GROUP BY {0}
+        HAVING COUNT(*) > 1",
+        column
+    ))?;
+    let mut groups = stmt
+        .query_map(params![collection_uid], |row| {
+            let names: String = row.get(0)?;
+            let mut names: Vec<String> = names.split('\u{1f}').map(str::to_string).collect();
+            names.sort();
+            Ok(names)
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    groups.sort();
+    Ok(groups)
+}
+
+/// A simulation row identified across collections
+#[derive(Debug)]
+pub struct SimulationRef {
+    pub id: i64,
+    pub collection_uid: String,
+    pub name: String,
+    pub created_at: String,
+}
+
+/// Returns all simulations with the given status, or set to it with
+/// [`set_status_override`], across all collections
+pub fn get_simulations_by_status(
+    conn: &Connection,
+    status: &str,
+) -> rusqlite::Result<Vec<SimulationRef>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, collection_uid, name, created_at FROM simulations s WHERE {} = ?1
+         ORDER BY collection_uid, name",
+        status_sql(conn)?
+    ))?;
+    let rows = stmt.query_map(params![status], |row| {
+        Ok(SimulationRef {
+            id: row.get(0)?,
+            collection_uid: row.get(1)?,
+            name: row.get(2)?,
+            created_at: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+        })
+    })?;
+    rows.collect()
+}
+
+/// A simulation with the timestamps used to find the most recent runs
+#[derive(Debug)]
+pub struct RecentSimulation {
+    pub id: i64,
+    pub collection_uid: String,
+    pub name: String,
+    pub status: Option<String>,
+    pub created_at: Option<String>,
+    pub synced_at: Option<String>,
+}
+
+/// Returns the `limit` most recent simulations across all collections, by creation
+/// time or, if `by_sync` is set, by the time they were last synced. The status is the
+/// one set with [`set_status_override`] if any.
+pub fn get_recent_simulations(
+    conn: &Connection,
+    by_sync: bool,
+    limit: usize,
+) -> rusqlite::Result<Vec<RecentSimulation>> {
+    let order_column = if by_sync {
+        "_last_sync_time"
+    } else {
+        "created_at"
+    };
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, collection_uid, name, {1}, created_at, _last_sync_time
+         FROM simulations s WHERE {0} IS NOT NULL ORDER BY {0} DESC LIMIT ?1",
+        order_column,
+        status_sql(conn)?
+    ))?;
+    let rows = stmt.query_map(params![limit as i64], |row| {
+        Ok(RecentSimulation {
+            id: row.get(0)?,
+            collection_uid: row.get(1)?,
+            name: row.get(2)?,
+            status: row.get(3)?,
+            created_at: row.get(4)?,
+            synced_at: row.get(5)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Deletes the simulations with the given ids, returns the number of deleted rows
+pub fn delete_simulations(conn: &Connection, ids: &[i64]) -> rusqlite::Result<usize> {
+    let mut stmt = conn.prepare("DELETE FROM simulations WHERE id = ?1")?;
+    let mut deleted = 0;
+    let mut collections = BTreeSet::new();
+    for id in ids {
+        collections.extend(simulation_collection(conn, *id)?);
+        deleted += stmt.execute(params![id])?;
+    }
+    for collection_uid in &collections {
+        prune_param_keys(conn, collection_uid, None)?;
+    }
+    Ok(deleted)
+}
+
 pub fn get_sim_sync_time(
     conn: &Connection,
     collection_uid: &str,
@@ -70,33 +1071,146 @@ pub fn get_sim_sync_time(
         .map(|dt| dt.with_timezone(&chrono::Local))
 }
 
+/// Returns whether the collection has a simulation with this name
+pub fn simulation_exists(
+    conn: &Connection,
+    collection_uid: &str,
+    name: &str,
+) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM simulations WHERE collection_uid = ?1 AND name = ?2)",
+        params![collection_uid, name],
+        |row| row.get(0),
+    )
+}
+
+/// Clears the last sync time of a simulation so that the next scan re-reads it.
+/// Returns the number of affected rows (0 if the simulation is unknown).
+pub fn reset_sync_time(
+    conn: &Connection,
+    collection_uid: &str,
+    name: &str,
+) -> rusqlite::Result<usize> {
+    conn.execute(
+        "UPDATE simulations SET _last_sync_time = NULL WHERE collection_uid = ?1 AND name = ?2",
+        params![collection_uid, name],
+    )
+}
+
 /// Returns simulation id (rowid)
 pub fn upsert_collection(conn: &Connection, uid: &str, path: &str) -> rusqlite::Result<()> {
+    // keeps the description of a known collection
     conn.execute(
-        "INSERT OR REPLACE INTO collections (uid, path) VALUES (?1, ?2)",
+        "INSERT INTO collections (uid, path) VALUES (?1, ?2)
+        ON CONFLICT(uid) DO UPDATE SET path = excluded.path",
         params![uid, path],
     )?;
     Ok(())
 }
 
+/// Records the collection `uid` is nested in, see `scan --nested`
+pub fn set_collection_parent(
+    conn: &Connection,
+    uid: &str,
+    parent_uid: Option<&str>,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE collections SET parent_uid = ?2 WHERE uid = ?1",
+        params![uid, parent_uid],
+    )?;
+    Ok(())
+}
+
+/// Sets the description of a collection, returns false if the collection is unknown
+pub fn set_collection_description(
+    conn: &Connection,
+    uid: &str,
+    description: &str,
+) -> rusqlite::Result<bool> {
+    let updated = conn.execute(
+        "UPDATE collections SET description = ?2 WHERE uid = ?1",
+        params![uid, description],
+    )?;
+    Ok(updated > 0)
+}
+
+/// A collection as stored in the `collections` table
+#[derive(Debug)]
+pub struct CollectionRow {
+    pub uid: String,
+    pub path: String,
+    pub description: Option<String>,
+    /// Collection this one is nested in, only recorded by `scan --nested`
+    pub parent_uid: Option<String>,
+}
+
+/// Returns all collections ordered by uid. Works on databases that predate the
+/// `description` or `parent_uid` columns, which then read as None.
+pub fn get_collections(conn: &Connection) -> rusqlite::Result<Vec<CollectionRow>> {
+    let column = |name: &'static str| -> rusqlite::Result<&'static str> {
+        Ok(if has_column(conn, "collections", name)? {
+            name
+        } else {
+            "NULL"
+        })
+    };
+    let query = format!(
+        "SELECT uid, path, {}, {} FROM collections ORDER BY uid",
+        column("description")?,
+        column("parent_uid")?
+    );
+    let mut stmt = conn.prepare(&query)?;
+    let rows = stmt.query_map([], |row| {
+        Ok(CollectionRow {
+            uid: row.get(0)?,
+            path: row.get(1)?,
+            description: row.get(2)?,
+            parent_uid: row.get(3)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Result of [`upsert_simulation`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Upserted {
+    /// Row id of the simulation
+    pub id: i64,
+    /// Whether the simulation was new, as opposed to an update of an existing row
+    pub inserted: bool,
+}
+
 pub fn upsert_simulation(
     conn: &Connection,
     collection_uid: &str,
     name: &str,
     meta: &MetaData,
     parameters: &Parameters,
-) -> rusqlite::Result<i64> {
-    let parameters_json = serde_json::to_string(parameters).unwrap_or("{}".to_string());
+) -> rusqlite::Result<Upserted> {
+    let parameters_json = canonical_json(parameters);
+    let hash = params_hash(&parameters_json);
+
+    // previous state, to record what this sync changed
+    let previous: Option<(Option<String>, Option<String>, Option<String>)> = conn
+        .query_row(
+            "SELECT status, params_hash, parameters_json FROM simulations
+             WHERE collection_uid = ?1 AND name = ?2",
+            params![collection_uid, name],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?;
+    let synced_at = chrono::offset::Local::now().to_rfc3339();
 
     conn.execute(
-        "INSERT INTO simulations (collection_uid, name, created_at, description, status, submitted, parameters_json, _last_sync_time)
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+        "INSERT INTO simulations (collection_uid, name, created_at, description, status, submitted, parameters_json, params_hash, _last_sync_time)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
         ON CONFLICT(collection_uid, name) DO UPDATE SET
             created_at = excluded.created_at,
             description = excluded.description,
             status = excluded.status,
             submitted = excluded.submitted,
             parameters_json = excluded.parameters_json,
+            params_hash = excluded.params_hash,
             _last_sync_time = excluded._last_sync_time
         ",
         params![
@@ -107,13 +1221,684 @@ pub fn upsert_simulation(
             meta.status.as_str(),
             meta.submitted as i32,
             parameters_json,
-            chrono::offset::Local::now().to_rfc3339(),
+            hash,
+            synced_at,
         ],
     )?;
 
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO collection_param_keys (collection_uid, key, first_seen, last_seen)
+         VALUES (?1, ?2, ?3, ?3)
+         ON CONFLICT(collection_uid, key) DO UPDATE SET last_seen = excluded.last_seen",
+    )?;
+    for key in parameters.keys() {
+        stmt.execute(params![collection_uid, key, synced_at])?;
+    }
+
     // get simulation row id
     let mut stmt =
         conn.prepare("SELECT id FROM simulations WHERE collection_uid = ?1 AND name = ?2")?;
     let id: i64 = stmt.query_row(params![collection_uid, name], |row| row.get(0))?;
-    Ok(id)
+    let inserted = previous.is_none();
+
+    let (old_status, params_changed) = match previous {
+        Some((old_status, old_hash, old_json)) => {
+            let params_changed = old_hash.as_deref() != Some(hash.as_str());
+            // keys this entry no longer has may have been the last of their collection
+            if params_changed
+                && let Some(old) =
+                    old_json.and_then(|j| serde_json::from_str::<Parameters>(&j).ok())
+            {
+                for key in old.keys().filter(|key| !parameters.contains_key(*key)) {
+                    prune_param_keys(conn, collection_uid, Some(key))?;
+                }
+            }
+            (old_status, params_changed)
+        }
+        None => (None, true),
+    };
+    if old_status.as_deref() != Some(meta.status.as_str()) || params_changed {
+        conn.execute(
+            "INSERT INTO sync_log (simulation_id, synced_at, old_status, new_status, params_changed)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                id,
+                chrono::offset::Local::now().to_rfc3339(),
+                old_status,
+                meta.status.as_str(),
+                params_changed,
+            ],
+        )?;
+    }
+    Ok(Upserted { id, inserted })
+}
+
+/// Stores the dataset storage summary of a simulation, see `scan --dataset-storage`
+pub fn set_simulation_storage(conn: &Connection, id: i64, storage: &Value) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE simulations SET storage_json = ?2 WHERE id = ?1",
+        params![id, storage.to_string()],
+    )?;
+    Ok(())
+}
+
+/// Stores the superblock version of a simulation's first data file, see
+/// [`crate::core::entry::read_superblock_version`]
+pub fn set_simulation_superblock(
+    conn: &Connection,
+    id: i64,
+    version: Option<u8>,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE simulations SET hdf5_superblock = ?2 WHERE id = ?1",
+        params![id, version],
+    )?;
+    Ok(())
+}
+
+/// Sets or, with `None`, clears the manual status of the simulation `name` of a
+/// collection. Syncs only update `status`, so the override survives re-scans. Returns
+/// false if there is no such simulation.
+pub fn set_status_override(
+    conn: &Connection,
+    uid: &str,
+    name: &str,
+    status: Option<&str>,
+) -> rusqlite::Result<bool> {
+    let updated = conn.execute(
+        "UPDATE simulations SET status_override = ?3 WHERE collection_uid = ?1 AND name = ?2",
+        params![uid, name, status],
+    )?;
+    Ok(updated > 0)
+}
+
+/// Stores how long the last sync took to read a simulation, see `scan --profile`
+pub fn set_simulation_load_time(
+    conn: &Connection,
+    id: i64,
+    load_time: std::time::Duration,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE simulations SET load_seconds = ?2 WHERE id = ?1",
+        params![id, load_time.as_secs_f64()],
+    )?;
+    Ok(())
+}
+
+/// One recorded change of a simulation, see the `sync_log` table
+#[derive(Debug)]
+pub struct SyncLogEntry {
+    pub synced_at: String,
+    pub old_status: Option<String>,
+    pub new_status: Option<String>,
+    pub params_changed: bool,
+}
+
+/// Returns the sync history of a simulation, oldest first
+pub fn get_sync_log(
+    conn: &Connection,
+    collection_uid: &str,
+    name: &str,
+) -> rusqlite::Result<Vec<SyncLogEntry>> {
+    // caches opened read-only are not migrated, older ones have no log
+    if table_columns(conn, "sync_log")?.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn.prepare(
+        "SELECT l.synced_at, l.old_status, l.new_status, l.params_changed
+         FROM sync_log l JOIN simulations s ON s.id = l.simulation_id
+         WHERE s.collection_uid = ?1 AND s.name = ?2
+         ORDER BY l.rowid",
+    )?;
+    let rows = stmt.query_map(params![collection_uid, name], |row| {
+        Ok(SyncLogEntry {
+            synced_at: row.get(0)?,
+            old_status: row.get(1)?,
+            new_status: row.get(2)?,
+            params_changed: row.get(3)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// One recorded scan, see the `scan_runs` table
+#[derive(Debug)]
+pub struct ScanRun {
+    pub scanned_at: String,
+    pub root: String,
+    pub collections: usize,
+    pub synced: usize,
+    pub failed: usize,
+}
+
+/// Appends a scan to the `scan_runs` table and prunes all but the last [`SCAN_RUNS_KEEP`].
+pub fn record_scan_run(conn: &Connection, run: &ScanRun) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO scan_runs (scanned_at, root, collections, synced, failed)
+        VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            run.scanned_at,
+            run.root,
+            run.collections as i64,
+            run.synced as i64,
+            run.failed as i64,
+        ],
+    )?;
+    conn.execute(
+        "DELETE FROM scan_runs WHERE id NOT IN
+         (SELECT id FROM scan_runs ORDER BY id DESC LIMIT ?1)",
+        params![SCAN_RUNS_KEEP as i64],
+    )?;
+    Ok(())
+}
+
+/// Returns the recorded scans, most recent first
+pub fn get_scan_runs(conn: &Connection) -> rusqlite::Result<Vec<ScanRun>> {
+    // caches opened read-only are not migrated, older ones have no scans recorded
+    if table_columns(conn, "scan_runs")?.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn.prepare(
+        "SELECT scanned_at, root, collections, synced, failed FROM scan_runs ORDER BY id DESC",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(ScanRun {
+            scanned_at: row.get(0)?,
+            root: row.get(1)?,
+            collections: row.get::<_, i64>(2)? as usize,
+            synced: row.get::<_, i64>(3)? as usize,
+            failed: row.get::<_, i64>(4)? as usize,
+        })
+    })?;
+    rows.collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(conn: &Connection, name: &str, parameters_json: &str) {
+        conn.execute(
+            "INSERT INTO simulations (collection_uid, name, parameters_json) VALUES ('c', ?1, ?2)",
+            params![name, parameters_json],
+        )
+        .unwrap();
+    }
+
+    /// Creates a cache with the tables of the first simdex release, with one simulation.
+    fn create_baseline_db(path: &Path) {
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE collections (uid TEXT PRIMARY KEY, path TEXT NOT NULL);
+             CREATE TABLE simulations (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                collection_uid TEXT NOT NULL,
+                name TEXT NOT NULL,
+                created_at TEXT,
+                description TEXT,
+                status TEXT,
+                submitted INTEGER,
+                parameters_json JSON,
+                _last_sync_time TEXT,
+                UNIQUE(collection_uid, name)
+             );
+             INSERT INTO collections VALUES ('c', '/data/c');
+             INSERT INTO simulations (collection_uid, name, parameters_json)
+                VALUES ('c', 'a', '{\"dt\": 0.1}');",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_read_commands_work_on_unmigrated_caches() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let db_path = tmp_dir.path().join("old.db");
+        create_baseline_db(&db_path);
+        let conn = open_existing(&db_path).unwrap();
+
+        assert!(get_scan_runs(&conn).unwrap().is_empty());
+        assert!(get_sync_log(&conn, "c", "a").unwrap().is_empty());
+        assert!(get_duplicate_params(&conn, "c").unwrap().is_empty());
+        assert_eq!(get_simulations_page(&conn, "c", 0, 10).unwrap().len(), 1);
+        assert!(get_simulations_by_status(&conn, "failed").is_ok());
+    }
+
+    #[test]
+    fn test_find_invalid_parameters() {
+        let conn = open_or_init(":memory:").unwrap();
+        insert(&conn, "ok", r#"{"dt": 0.1}"#);
+        insert(&conn, "truncated", r#"{"dt": 0."#);
+        insert(&conn, "not_an_object", "[1, 2]");
+        conn.execute(
+            "INSERT INTO simulations (collection_uid, name) VALUES ('c', 'null')",
+            [],
+        )
+        .unwrap();
+
+        let invalid = find_invalid_parameters(&conn, Some("c")).unwrap();
+        let names: Vec<&str> = invalid.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["truncated", "not_an_object"]);
+        assert!(
+            find_invalid_parameters(&conn, Some("other"))
+                .unwrap()
+                .is_empty()
+        );
+
+        clear_parameters_json(&conn, invalid[0].id).unwrap();
+        assert_eq!(find_invalid_parameters(&conn, None).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_simulation_pages_cover_the_collection() {
+        let conn = open_or_init(":memory:").unwrap();
+        for name in ["a", "b", "c", "d", "e"] {
+            insert(&conn, name, "{}");
+        }
+        let mut names = Vec::new();
+        let mut last_id = 0;
+        loop {
+            let page = get_simulations_page(&conn, "c", last_id, 2).unwrap();
+            let Some(last) = page.last() else { break };
+            assert!(page.len() <= 2);
+            last_id = last.id;
+            names.extend(page.into_iter().map(|sim| sim.name));
+        }
+        assert_eq!(names, ["a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn test_aggregate_parameters_summarizes_per_key() {
+        let conn = open_or_init(":memory:").unwrap();
+        insert(&conn, "a", r#"{"dt": 0.1, "method": "cg", "n": 4}"#);
+        insert(&conn, "b", r#"{"dt": 0.01, "method": "cg"}"#);
+        insert(&conn, "c", r#"{"dt": 0.1, "method": "gmres", "n": "auto"}"#);
+        insert(&conn, "d", "not json");
+
+        let summary = aggregate_parameters(&conn, "c", None).unwrap();
+        let keys: Vec<&str> = summary.keys().map(String::as_str).collect();
+        assert_eq!(keys, ["dt", "method", "n"]);
+        assert!(
+            collection_param_keys(&conn, "c", None)
+                .unwrap()
+                .iter()
+                .eq(keys)
+        );
+
+        let dt = &summary["dt"];
+        assert_eq!((dt.count, dt.distinct_count), (3, 2));
+        assert!(dt.is_numeric);
+        assert_eq!((dt.min, dt.max), (Some(0.01), Some(0.1)));
+
+        let method = &summary["method"];
+        assert_eq!(method.distinct_count, 2);
+        assert!(!method.is_numeric);
+        assert_eq!((method.min, method.max), (None, None));
+        assert_eq!(method.example, Value::from("cg"));
+
+        let n = &summary["n"];
+        assert_eq!(n.count, 2);
+        assert!(!n.is_numeric);
+        assert_eq!((n.min, n.max), (Some(4.0), Some(4.0)));
+        assert_eq!(
+            n.types,
+            BTreeSet::from(["int".to_string(), "string".to_string()])
+        );
+        assert_eq!(dt.types, BTreeSet::from(["float".to_string()]));
+
+        assert!(low_cardinality_values(&conn, "c", 1).unwrap().is_empty());
+        let values = low_cardinality_values(&conn, "c", 2).unwrap();
+        assert_eq!(values["method"], [Value::from("cg"), Value::from("gmres")]);
+        assert_eq!(values["n"], [Value::from("auto"), Value::from(4)]);
+
+        conn.execute(
+            "UPDATE simulations SET status = 'finished' WHERE name = 'b'",
+            [],
+        )
+        .unwrap();
+        let finished = aggregate_parameters(&conn, "c", Some("finished")).unwrap();
+        assert_eq!(finished["dt"].count, 1);
+        assert!(!finished.contains_key("n"));
+        let keys = collection_param_keys(&conn, "c", Some("finished")).unwrap();
+        assert!(keys.iter().eq(["dt", "method"]));
+    }
+
+    #[test]
+    fn test_resolve_db_path_names_per_root() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let (a, b) = (tmp_dir.path().join("my proj"), tmp_dir.path().join("other"));
+        std::fs::create_dir_all(&a).unwrap();
+        std::fs::create_dir_all(&b).unwrap();
+        let dbs = tmp_dir.path().join("dbs");
+        std::fs::create_dir(&dbs).unwrap();
+
+        let db_a = resolve_db_path(&dbs, &a);
+        assert_eq!(db_a.parent(), Some(dbs.as_path()));
+        assert!(
+            db_a.file_name()
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .starts_with("my_proj-")
+        );
+        assert_eq!(resolve_db_path(&dbs, &a), db_a);
+        assert_ne!(resolve_db_path(&dbs, &b), db_a);
+
+        let file = tmp_dir.path().join("simdex.db");
+        assert_eq!(resolve_db_path(&file, &a), file);
+
+        // readers find the database of a scanned root from below it
+        drop(open_or_init(&db_a).unwrap());
+        let below = a.join("sub/dir");
+        std::fs::create_dir_all(&below).unwrap();
+        assert_eq!(find_db_path(&dbs, &below), db_a);
+        assert_eq!(find_db_path(&dbs, &a), db_a);
+        assert_eq!(find_db_path(&dbs, &b), resolve_db_path(&dbs, &b));
+        assert_eq!(find_db_path(&file, &below), file);
+    }
+
+    #[test]
+    fn test_params_hash_ignores_key_order() {
+        let conn = open_or_init(":memory:").unwrap();
+        let meta = MetaData {
+            created_at: chrono::Utc::now(),
+            description: String::new(),
+            status: Default::default(),
+            submitted: false,
+        };
+        let a: Parameters =
+            serde_json::from_str(r#"{"n": 4, "solver": {"tol": 1e-6, "kind": "cg"}}"#).unwrap();
+        let b: Parameters =
+            serde_json::from_str(r#"{"solver": {"kind": "cg", "tol": 1e-6}, "n": 4}"#).unwrap();
+        let c: Parameters = serde_json::from_str(r#"{"n": 8}"#).unwrap();
+        assert_eq!(
+            canonical_json(&a),
+            r#"{"n":4,"solver":{"kind":"cg","tol":1e-6}}"#
+        );
+
+        upsert_simulation(&conn, "c", "run_b", &meta, &b).unwrap();
+        upsert_simulation(&conn, "c", "run_a", &meta, &a).unwrap();
+        upsert_simulation(&conn, "c", "run_c", &meta, &c).unwrap();
+        assert_eq!(
+            get_duplicate_params(&conn, "c").unwrap(),
+            [["run_a", "run_b"]]
+        );
+    }
+
+    #[test]
+    fn test_upsert_simulation_logs_changes_only() {
+        let conn = open_or_init(":memory:").unwrap();
+        let mut meta = MetaData {
+            created_at: chrono::Utc::now(),
+            description: String::new(),
+            status: "running".to_string(),
+            submitted: true,
+        };
+        let mut parameters = Parameters::new();
+        parameters.insert("dt".to_string(), serde_json::json!(0.1));
+
+        assert!(
+            upsert_simulation(&conn, "c", "a", &meta, &parameters)
+                .unwrap()
+                .inserted
+        );
+        assert!(
+            !upsert_simulation(&conn, "c", "a", &meta, &parameters)
+                .unwrap()
+                .inserted
+        );
+        meta.status = "finished".to_string();
+        upsert_simulation(&conn, "c", "a", &meta, &parameters).unwrap();
+
+        let log = get_sync_log(&conn, "c", "a").unwrap();
+        assert_eq!(log.len(), 2);
+        assert_eq!(log[0].old_status, None);
+        assert_eq!(log[1].old_status.as_deref(), Some("running"));
+        assert_eq!(log[1].new_status.as_deref(), Some("finished"));
+        assert!(!log[1].params_changed);
+    }
+
+    #[test]
+    fn test_status_override_survives_upsert() {
+        let conn = open_or_init(":memory:").unwrap();
+        let mut meta = MetaData {
+            created_at: chrono::Utc::now(),
+            description: String::new(),
+            status: "running".to_string(),
+            submitted: true,
+        };
+        upsert_simulation(&conn, "c", "a", &meta, &Parameters::new()).unwrap();
+        assert!(set_status_override(&conn, "c", "a", Some("failed")).unwrap());
+        assert!(!set_status_override(&conn, "c", "missing", Some("failed")).unwrap());
+
+        meta.status = "finished".to_string();
+        let params: Parameters = serde_json::from_str(r#"{"dt": 0.1}"#).unwrap();
+        upsert_simulation(&conn, "c", "a", &meta, &params).unwrap();
+        let (status, status_override): (String, Option<String>) = conn
+            .query_row(
+                "SELECT status, status_override FROM simulations WHERE name = 'a'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(status, "finished");
+        assert_eq!(status_override.as_deref(), Some("failed"));
+
+        // readers filtering or reporting by status see the override
+        assert_eq!(get_simulations_by_status(&conn, "failed").unwrap().len(), 1);
+        assert!(
+            get_simulations_by_status(&conn, "finished")
+                .unwrap()
+                .is_empty()
+        );
+        let page = get_simulations_page(&conn, "c", 0, 10).unwrap();
+        assert_eq!(page[0].status.as_deref(), Some("failed"));
+        let sims = get_simulations(&conn, "c").unwrap();
+        assert_eq!(sims[0].status.as_deref(), Some("failed"));
+        let recent = get_recent_simulations(&conn, false, 10).unwrap();
+        assert_eq!(recent[0].status.as_deref(), Some("failed"));
+        let overrides = get_status_overrides(&conn, "c").unwrap();
+        assert_eq!(
+            overrides["a"],
+            (Some("finished".to_string()), "failed".to_string())
+        );
+        assert!(
+            aggregate_parameters(&conn, "c", Some("failed"))
+                .unwrap()
+                .contains_key("dt")
+        );
+        assert!(
+            aggregate_parameters(&conn, "c", Some("finished"))
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            collection_param_keys(&conn, "c", Some("failed"))
+                .unwrap()
+                .contains("dt")
+        );
+        assert!(
+            collection_param_keys(&conn, "c", Some("finished"))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_param_keys_are_tracked_on_upsert() {
+        let conn = open_or_init(":memory:").unwrap();
+        let meta = MetaData {
+            created_at: chrono::Utc::now(),
+            description: String::new(),
+            status: "finished".to_string(),
+            submitted: true,
+        };
+        let parameters = |json: &str| serde_json::from_str::<Parameters>(json).unwrap();
+        upsert_simulation(&conn, "c", "a", &meta, &parameters(r#"{"dt": 0.1}"#)).unwrap();
+        let b = upsert_simulation(
+            &conn,
+            "c",
+            "b",
+            &meta,
+            &parameters(r#"{"dt": 0.1, "mesh": 8}"#),
+        )
+        .unwrap();
+        let keys = |conn: &Connection| -> Vec<String> {
+            tracked_param_keys(conn, "c")
+                .unwrap()
+                .into_iter()
+                .map(|k| k.key)
+                .collect()
+        };
+        assert_eq!(keys(&conn), ["dt", "mesh"]);
+        assert!(
+            collection_param_keys(&conn, "c", None)
+                .unwrap()
+                .iter()
+                .eq(["dt", "mesh"])
+        );
+
+        // a key dropped by the last simulation that had it is no longer tracked
+        upsert_simulation(&conn, "c", "b", &meta, &parameters(r#"{"dt": 0.1}"#)).unwrap();
+        assert_eq!(keys(&conn), ["dt"]);
+        upsert_simulation(
+            &conn,
+            "c",
+            "b",
+            &meta,
+            &parameters(r#"{"dt": 0.1, "mesh": 8}"#),
+        )
+        .unwrap();
+        delete_simulations(&conn, &[b.id]).unwrap();
+        assert_eq!(keys(&conn), ["dt"]);
+    }
+
+    #[test]
+    fn test_copied_collections_track_all_param_keys() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let src_path = tmp_dir.path().join("src.db");
+        let src = open_or_init(&src_path).unwrap();
+        upsert_collection(&src, "c", "/data/c").unwrap();
+        let meta = MetaData {
+            created_at: chrono::Utc::now(),
+            description: String::new(),
+            status: "finished".to_string(),
+            submitted: true,
+        };
+        let parameters: Parameters = serde_json::from_str(r#"{"dt": 0.1}"#).unwrap();
+        upsert_simulation(&src, "c", "a", &meta, &parameters).unwrap();
+        // written without upsert_simulation, so not tracked in the source
+        src.execute(
+            "INSERT INTO simulations (collection_uid, name, parameters_json)
+             VALUES ('c', 'b', '{\"mesh\": 8}')",
+            [],
+        )
+        .unwrap();
+        drop(src);
+
+        let conn = open_or_init(tmp_dir.path().join("dest.db")).unwrap();
+        conn.execute(
+            "ATTACH DATABASE ?1 AS src",
+            [src_path.to_string_lossy().as_ref()],
+        )
+        .unwrap();
+        assert_eq!(copy_attached_collection(&conn, "c").unwrap(), 2);
+        let keys = collection_param_keys(&conn, "c", None).unwrap();
+        assert!(keys.iter().eq(["dt", "mesh"]));
+    }
+
+    #[test]
+    fn test_scan_runs_are_capped() {
+        let conn = open_or_init(":memory:").unwrap();
+        for i in 0..SCAN_RUNS_KEEP + 5 {
+            let run = ScanRun {
+                scanned_at: format!("t{}", i),
+                root: "/data".to_string(),
+                collections: 1,
+                synced: i,
+                failed: 0,
+            };
+            record_scan_run(&conn, &run).unwrap();
+        }
+
+        let runs = get_scan_runs(&conn).unwrap();
+        assert_eq!(runs.len(), SCAN_RUNS_KEEP);
+        assert_eq!(runs[0].synced, SCAN_RUNS_KEEP + 4);
+        assert_eq!(runs.last().unwrap().synced, 5);
+    }
+
+    #[test]
+    fn test_rescan_keeps_collection_description() {
+        let conn = open_or_init(":memory:").unwrap();
+        upsert_collection(&conn, "c", "/old").unwrap();
+        assert!(set_collection_description(&conn, "c", "mesh study").unwrap());
+        assert!(!set_collection_description(&conn, "x", "unknown").unwrap());
+        upsert_collection(&conn, "c", "/new").unwrap();
+
+        let collections = get_collections(&conn).unwrap();
+        assert_eq!(collections.len(), 1);
+        assert_eq!(collections[0].path, "/new");
+        assert_eq!(collections[0].description.as_deref(), Some("mesh study"));
+    }
+
+    #[test]
+    fn test_parse_parameters_json_strict() {
+        let truncated = r#"{"dt": 0.1, "meth"#;
+        assert!(
+            parse_parameters_json(1, truncated, false)
+                .unwrap()
+                .is_empty()
+        );
+        let err = parse_parameters_json(1, truncated, true).unwrap_err();
+        assert!(err.contains("simulation 1") && err.contains(truncated));
+    }
+
+    #[test]
+    fn test_upgrade_legacy_simulations_derives_names() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE simulations (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                collection_uid TEXT NOT NULL,
+                path TEXT NOT NULL,
+                status TEXT,
+                parameters_json JSON
+            );
+            INSERT INTO simulations (collection_uid, path, status, parameters_json)
+            VALUES ('c', '/data/c/run_a', 'finished', '{\"dt\": 0.1}');",
+        )
+        .unwrap();
+        assert!(is_legacy_schema(&conn).unwrap());
+
+        assert_eq!(upgrade_legacy_simulations(&conn).unwrap(), Some(1));
+        assert!(!is_legacy_schema(&conn).unwrap());
+        assert_eq!(upgrade_legacy_simulations(&conn).unwrap(), None);
+
+        let sims = get_simulations(&conn, "c").unwrap();
+        assert_eq!(sims[0].name, "run_a");
+        assert_eq!(sims[0].status.as_deref(), Some("finished"));
+        assert_eq!(sims[0].parameters["dt"], serde_json::json!(0.1));
+    }
+
+    #[test]
+    fn test_schema_mismatch_rejects_unmigratable_tables() {
+        let conn = Connection::open_in_memory().unwrap();
+        assert_eq!(schema_mismatch(&conn).unwrap(), None);
+        conn.execute_batch(
+            "CREATE TABLE simulations (collection_uid TEXT, path TEXT);
+             CREATE TABLE parameters (simulation_id INTEGER, key TEXT, value TEXT);",
+        )
+        .unwrap();
+        assert!(
+            schema_mismatch(&conn)
+                .unwrap()
+                .unwrap()
+                .contains("`parameters` table")
+        );
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE collections (id INTEGER, name TEXT)")
+            .unwrap();
+        let mismatch = schema_mismatch(&conn).unwrap().unwrap();
+        assert!(mismatch.contains("collections.uid, collections.path"));
+    }
 }
@@ -1,12 +1,24 @@
 use crate::config;
 use crate::core::db;
 use crate::core::types::{Author, MetaFile};
+use regex::Regex;
+use serde::Deserialize;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process::Command;
+use std::sync::LazyLock;
 use std::{fs, io};
 use walkdir::WalkDir;
 
+static EMAIL_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[^@\s]+@[^@\s]+\.[^@\s]+$").expect("valid email regex"));
+
+/// Trims `raw` and returns it if it looks like an email address (`local@domain.tld`).
+fn normalize_email(raw: &str) -> Option<String> {
+    let email = raw.trim();
+    EMAIL_RE.is_match(email).then(|| email.to_string())
+}
+
 fn get_author() -> Option<Author> {
     fn _git_user() -> Option<Author> {
         let name = Command::new("git")
@@ -14,14 +26,16 @@ fn get_author() -> Option<Author> {
             .output()
             .ok()
             .and_then(|o| String::from_utf8(o.stdout).ok())
-            .map(|s| s.trim().to_string());
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty());
 
+        // an author without a usable email is left out of the marker entirely
         let email = Command::new("git")
             .args(["config", "--get", "user.email"])
             .output()
             .ok()
             .and_then(|o| String::from_utf8(o.stdout).ok())
-            .map(|s| s.trim().to_string());
+            .and_then(|s| normalize_email(&s));
 
         match (name, email) {
             (Some(name), Some(email)) => Some(Author { name, email }),
@@ -57,7 +71,10 @@ fn create_identifier(path: &Path, uid: &str) -> std::io::Result<()> {
     Ok(())
 }
 
-pub fn new_collection(path: impl Into<PathBuf>, uid: &str) -> std::io::Result<()> {
+/// Creates a collection with the given uid in `path`, which must not exist or be an
+/// empty directory. Missing parent directories are only created with `parents`, so
+/// that a mistyped path fails instead of leaving a stray directory tree.
+pub fn new_collection(path: impl Into<PathBuf>, uid: &str, parents: bool) -> std::io::Result<()> {
     let path: PathBuf = path.into();
     let _uid: String = uid.into();
 
@@ -82,8 +99,19 @@ pub fn new_collection(path: impl Into<PathBuf>, uid: &str) -> std::io::Result<()
             ));
         }
         // Directory exists and is empty
-    } else {
+    } else if parents {
         fs::create_dir_all(&path)?;
+    } else {
+        fs::create_dir(&path).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "Parent of '{}' does not exist, use --parents to create it",
+                    path.display()
+                ),
+            ),
+            _ => e,
+        })?;
     }
 
     // Create the identifier file
@@ -112,6 +140,12 @@ pub fn new_collection(path: impl Into<PathBuf>, uid: &str) -> std::io::Result<()
 /// Any errors encountered while reading directories or entries are printed to stderr,
 /// and those entries are skipped.
 pub fn find_all(root: &Path) -> Vec<(PathBuf, String)> {
+    iter_all(root).collect()
+}
+
+/// Like [`find_all`], but yields the collections lazily while walking `root`, so that
+/// callers can start working on the first collection before the walk is complete.
+pub fn iter_all(root: &Path) -> impl Iterator<Item = (PathBuf, String)> {
     WalkDir::new(root)
         .min_depth(1)
         .max_depth(5) // Change as needed
@@ -136,39 +170,43 @@ pub fn find_all(root: &Path) -> Vec<(PathBuf, String)> {
 
             Some((parent.to_path_buf(), uid.to_string()))
         })
+}
+
+/// Returns the index of the collection directory each of `paths` is nested in, i.e. the
+/// closest other one that contains it, or None for top-level ones. The result is in the
+/// same order as `paths`.
+pub fn find_parents(paths: &[PathBuf]) -> Vec<Option<usize>> {
+    paths
+        .iter()
+        .map(|path| {
+            (0..paths.len())
+                .filter(|&j| paths[j] != *path && path.starts_with(&paths[j]))
+                .max_by_key(|&j| paths[j].components().count())
+        })
         .collect()
 }
 
-fn find_one(uid: &str, root: Option<&Path>) -> io::Result<PathBuf> {
+/// Finds the directory of the collection with the given uid below `root`
+/// (the current directory if None), by its marker file.
+pub fn find_one(uid: &str, root: Option<&Path>) -> io::Result<PathBuf> {
     let root = root.unwrap_or_else(|| Path::new("."));
-    let patterns = [
-        format!("{}{}", config::META_FILE_PREFIX, uid),
-        format!("{}{}.yml", config::META_FILE_PREFIX, uid),
-    ];
-
-    for entry in WalkDir::new(root)
-        .min_depth(1)
-        .max_depth(5)
-        .into_iter()
-        .flatten()
-    {
-        if entry.file_type().is_file() {
-            let file_name = entry.file_name().to_string_lossy();
-            if patterns.iter().any(|p| p == &file_name) {
-                return Ok(entry.path().parent().map(|p| p.to_path_buf()).unwrap());
-            }
-        }
-    }
-    Err(io::Error::new(
-        io::ErrorKind::NotFound,
-        format!("Collection with UID '{}' not found", uid),
-    ))
+    iter_all(root)
+        .find(|(_, found)| found == uid)
+        .map(|(path, _)| path)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Collection with UID '{}' not found", uid),
+            )
+        })
 }
 
 pub fn get_path(uid: &str) -> io::Result<PathBuf> {
-    let conn = db::open_or_init(config::DEFAULT_DB_PATH).expect("Failed to open DB");
+    let cached = db::open_existing(config::DEFAULT_DB_PATH)
+        .ok()
+        .and_then(|conn| db::get_collection_path(&conn, uid));
 
-    match db::get_collection_path(&conn, uid).filter(|p| p.exists()) {
+    match cached.filter(|p| p.exists()) {
         Some(path) => Ok(path),
         None => find_one(uid, None),
     }
@@ -200,10 +238,10 @@ fn read_uid_from_meta_file(path: &Path) -> Result<String, String> {
             Some(s) => s,
             None => continue,
         };
-        if let Some(caps) = re.captures(file_name_str) {
-            if let Some(uid) = caps.name("uid") {
-                return Ok(uid.as_str().to_string());
-            }
+        if let Some(caps) = re.captures(file_name_str)
+            && let Some(uid) = caps.name("uid")
+        {
+            return Ok(uid.as_str().to_string());
         }
     }
 
@@ -214,6 +252,70 @@ fn read_uid_from_meta_file(path: &Path) -> Result<String, String> {
     ))
 }
 
+/// Contents of a collection marker file. `created` and `author` are None if the marker
+/// lacks them or cannot be parsed.
+#[derive(Debug, Default, Deserialize)]
+pub struct Marker {
+    #[serde(default)]
+    pub uid: String,
+    pub created: Option<String>,
+    pub author: Option<Author>,
+}
+
+/// Reads the marker of the collection `uid` in `collection_path`. The uid is the one
+/// from the file name, or with `body_uid` the one written in the marker, so that a
+/// renamed marker keeps its collection; a warning is printed if they differ. If the
+/// marker cannot be read, is not valid YAML or has no uid, the one from the file name is
+/// used. If it cannot be read or is not valid YAML, a warning is printed, unless `strict`
+/// is set, which makes it an error.
+pub fn read_marker(
+    collection_path: &Path,
+    uid: &str,
+    strict: bool,
+    body_uid: bool,
+) -> Result<Marker, String> {
+    let name = format!("{}{}", config::META_FILE_PREFIX, uid);
+    let path = [format!("{}.yml", name), name]
+        .into_iter()
+        .map(|name| collection_path.join(name))
+        .find(|path| path.is_file())
+        .ok_or_else(|| format!("no marker for {} in {}", uid, collection_path.display()))?;
+
+    let parsed = match fs::read_to_string(&path) {
+        Ok(contents) if contents.trim().is_empty() => Ok(Marker::default()),
+        Ok(contents) => serde_yaml::from_str::<Marker>(&contents).map_err(|e| e.to_string()),
+        Err(e) => Err(format!("cannot be read: {}", e)),
+    };
+    let marker = match parsed {
+        Ok(marker) => marker,
+        Err(e) if strict => return Err(format!("invalid marker {}: {}", path.display(), e)),
+        Err(e) => {
+            eprintln!(
+                "Warning: invalid marker {}: {}; using the uid from its name",
+                path.display(),
+                e
+            );
+            Marker::default()
+        }
+    };
+    let uid = match marker.uid.trim() {
+        "" => uid,
+        written if written != uid => {
+            let used = if body_uid { written } else { uid };
+            eprintln!(
+                "Warning: marker {} was written for collection {}; using {}",
+                path.display(),
+                written,
+                used
+            );
+            used
+        }
+        _ => uid,
+    }
+    .to_string();
+    Ok(Marker { uid, ..marker })
+}
+
 pub fn get_uid(path: &Path) -> Result<String, String> {
     if !path.exists() || !path.is_dir() {
         return Err(format!("Path '{}' is not a directory", path.display()));
@@ -227,6 +329,75 @@ mod tests {
     use serde_yaml::Value;
     use std::fs;
 
+    #[test]
+    fn test_read_marker_falls_back_on_invalid_yaml() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let marker = tmp_dir
+            .path()
+            .join(format!("{}ABC.yml", config::META_FILE_PREFIX));
+        fs::write(&marker, "uid: ABC\ncreated: [unclosed\n").unwrap();
+
+        let read = read_marker(tmp_dir.path(), "ABC", false, true).unwrap();
+        assert_eq!(read.uid, "ABC");
+        assert!(read.created.is_none());
+        assert!(read_marker(tmp_dir.path(), "ABC", true, false).is_err());
+
+        fs::write(&marker, "uid: ABC\ncreated: '2024-05-01'\n").unwrap();
+        let read = read_marker(tmp_dir.path(), "ABC", true, false).unwrap();
+        assert_eq!(read.created.as_deref(), Some("2024-05-01"));
+
+        // not UTF-8, e.g. written by a broken tool
+        fs::write(&marker, b"uid: \xff\xfe\n").unwrap();
+        assert_eq!(
+            read_marker(tmp_dir.path(), "ABC", false, false)
+                .unwrap()
+                .uid,
+            "ABC"
+        );
+        assert!(read_marker(tmp_dir.path(), "ABC", true, false).is_err());
+    }
+
+    #[test]
+    fn test_read_marker_prefers_written_uid_if_asked() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let marker = tmp_dir
+            .path()
+            .join(format!("{}RENAMED.yml", config::META_FILE_PREFIX));
+        fs::write(&marker, "uid: ABC\n").unwrap();
+        assert_eq!(
+            read_marker(tmp_dir.path(), "RENAMED", false, false)
+                .unwrap()
+                .uid,
+            "RENAMED"
+        );
+        assert_eq!(
+            read_marker(tmp_dir.path(), "RENAMED", false, true)
+                .unwrap()
+                .uid,
+            "ABC"
+        );
+
+        fs::write(&marker, "created: '2024-05-01'\n").unwrap();
+        assert_eq!(
+            read_marker(tmp_dir.path(), "RENAMED", false, true)
+                .unwrap()
+                .uid,
+            "RENAMED"
+        );
+    }
+
+    #[test]
+    fn test_find_parents_picks_closest_ancestor() {
+        let paths = [
+            "data/sweep",
+            "data/sweep/re100/mesh",
+            "data/sweep/re100",
+            "data/sweep2",
+        ]
+        .map(PathBuf::from);
+        assert_eq!(find_parents(&paths), [None, Some(2), Some(0), None]);
+    }
+
     #[test]
     fn test_create_identifier_creates_yaml_with_timestamp() {
         let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
@@ -256,4 +427,15 @@ mod tests {
             "'created' field is not a string"
         );
     }
+
+    #[test]
+    fn test_normalize_email() {
+        assert_eq!(
+            normalize_email("  jane@example.org\n").as_deref(),
+            Some("jane@example.org")
+        );
+        assert_eq!(normalize_email(""), None);
+        assert_eq!(normalize_email("jane"), None);
+        assert_eq!(normalize_email("jane doe@example.org"), None);
+    }
 }
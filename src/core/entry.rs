@@ -1,9 +1,12 @@
 use chrono::{DateTime, Utc};
 use hdf5::File;
+use regex::Regex;
 use serde::Deserialize;
 use serde_json::Value;
-use std::{fs, path::Path};
+use std::{collections::BTreeMap, fs, path::Path};
 
+use crate::core::filter::GlobFilter;
+use crate::core::time::UNKNOWN_CREATED_AT;
 use crate::core::types::{MetaData, Parameters};
 
 #[derive(Deserialize)]
@@ -14,68 +17,601 @@ struct TypeWrapper {
     value: String,
 }
 
-/// Returns the modification time of `data.h5` in RFC3339 format, or None if unavailable.
-/// If the file does not exist or cannot be accessed, it returns None.
+/// Options controlling where `load_entry_meta` looks for metadata in an entry's data files.
+#[derive(Clone, Debug)]
+pub struct LoadOptions {
+    /// Names of the entry's HDF5 files, in order; metadata and parameters of later files
+    /// override those of earlier ones. An entry needs at least one of them
+    pub data_files: Vec<String>,
+    /// Group holding the `created_at`, `description`, `status` and `submitted` attributes.
+    /// Data files without this group are read from the root group instead
+    pub meta_group: String,
+    /// Group whose attributes are the simulation parameters
+    pub params_group: String,
+    /// Which attributes of the parameters group are stored; others are not read at all
+    pub params_filter: GlobFilter,
+    /// Regex whose named groups are extracted from the entry's directory name as parameters
+    pub name_params: Option<Regex>,
+    /// Store string parameters that hold a JSON array or object as the parsed value
+    pub parse_json_strings: bool,
+    /// Index entries without metadata attributes instead of failing: `created_at` falls
+    /// back to the newest mtime of the data files and `status` to `unknown`
+    pub allow_missing_meta: bool,
+    /// Fail on entries without a parameters group instead of indexing them with no
+    /// parameters; unresolvable links to the group fail either way
+    pub require_params_group: bool,
+    /// Prefixes prepended to parameter keys per source before the sources are merged,
+    /// keyed by data file name, or `name` for the parameters of [`Self::name_params`].
+    /// Data files without a prefix keep their keys and override earlier ones on collision;
+    /// parameters from the entry name never override those read from the data files
+    pub params_prefixes: BTreeMap<String, String>,
+    /// Lowercase parameter keys, merging variants like `Reynolds` and `reynolds`
+    pub case_fold: bool,
+    /// For data files that are symlinks, also count the modification time of the link
+    /// itself, so re-pointing it to another file triggers a re-sync
+    pub symlink_mtime: bool,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self {
+            data_files: vec!["data.h5".to_string()],
+            meta_group: "/".to_string(),
+            params_group: "/.parameters".to_string(),
+            params_filter: GlobFilter::default(),
+            name_params: None,
+            parse_json_strings: false,
+            allow_missing_meta: false,
+            require_params_group: false,
+            params_prefixes: BTreeMap::new(),
+            case_fold: false,
+            symlink_mtime: false,
+        }
+    }
+}
+
+/// Converts a textual value to the narrowest JSON value: integer, float, boolean or string.
+pub fn coerce_value(s: &str) -> Value {
+    if let Ok(i) = s.parse::<i64>() {
+        Value::from(i)
+    } else if let Ok(f) = s.parse::<f64>() {
+        Value::from(f)
+    } else if let Ok(b) = s.parse::<bool>() {
+        Value::from(b)
+    } else {
+        Value::from(s)
+    }
+}
+
+/// Parses `s` as JSON if it holds an array or object, e.g. written by Python's
+/// `json.dumps`. Any other string, including ones holding a JSON scalar, is kept as is.
+pub fn parse_json_string(s: &str) -> Value {
+    match serde_json::from_str::<Value>(s) {
+        Ok(v @ (Value::Array(_) | Value::Object(_))) => v,
+        _ => Value::from(s),
+    }
+}
+
+/// Adds the named capture groups of `re` matched against `name` to `parameters`.
+/// Parameters already present (e.g. read from the HDF5 file) are not overwritten.
+pub fn apply_name_params(re: &Regex, name: &str, parameters: &mut Parameters) {
+    let Some(caps) = re.captures(name) else {
+        return;
+    };
+    for group in re.capture_names().flatten() {
+        if let Some(m) = caps.name(group) {
+            parameters
+                .entry(group.to_string())
+                .or_insert_with(|| coerce_value(m.as_str()));
+        }
+    }
+}
+
+/// Prepends `prefix` to every key of `parameters`, if there is a prefix.
+fn prefix_keys(parameters: Parameters, prefix: Option<&String>) -> Parameters {
+    match prefix {
+        Some(prefix) => parameters
+            .into_iter()
+            .map(|(key, value)| (format!("{}{}", prefix, key), value))
+            .collect(),
+        None => parameters,
+    }
+}
+
+/// Lowercases the parameter keys, merging keys that differ only in case. If the variants
+/// of a key hold different values, the one whose original key sorts first is kept and
+/// the folded key is returned as a conflict.
+pub fn fold_parameter_keys(parameters: Parameters) -> (Parameters, Vec<String>) {
+    let mut entries: Vec<(String, Value)> = parameters.into_iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut folded = Parameters::new();
+    let mut conflicts = Vec::new();
+    for (key, value) in entries {
+        let key = key.to_lowercase();
+        match folded.get(&key) {
+            Some(kept) => {
+                if *kept != value && !conflicts.contains(&key) {
+                    conflicts.push(key);
+                }
+            }
+            None => {
+                folded.insert(key, value);
+            }
+        }
+    }
+    conflicts.sort();
+    (folded, conflicts)
+}
+
+/// Returns the newest modification time of the entry's data files, or None if none of
+/// them exists or can be accessed. Symlinks are followed; with
+/// [`LoadOptions::symlink_mtime`], the mtime of the link itself counts as well.
 ///
 /// # Arguments
-/// * `path` - The path to the collection directory containing `data.h5`.
-pub fn get_data_h5_mtime(path: &Path) -> Option<chrono::DateTime<chrono::Local>> {
-    let h5_path = path.join("data.h5");
-    let meta = fs::metadata(h5_path).ok()?;
-    let mtime = meta.modified().ok()?;
-    let dt: chrono::DateTime<chrono::Local> = mtime.into();
-    Some(dt)
+/// * `path` - The path to the entry directory.
+/// * `opts` - Which data files to consider, see [`LoadOptions::data_files`].
+pub fn get_data_mtime(path: &Path, opts: &LoadOptions) -> Option<chrono::DateTime<chrono::Local>> {
+    opts.data_files
+        .iter()
+        .flat_map(|name| {
+            let file = path.join(name);
+            let target = fs::metadata(&file).and_then(|m| m.modified()).ok();
+            let link = match opts.symlink_mtime {
+                true => fs::symlink_metadata(&file).and_then(|m| m.modified()).ok(),
+                false => None,
+            };
+            [target, link]
+        })
+        .flatten()
+        .max()
+        .map(chrono::DateTime::<chrono::Local>::from)
+}
+
+/// Returns a description like `data.h5 is a broken symlink to ../shared.h5` if the data
+/// file `name` of an entry is a symlink whose target does not exist.
+pub fn broken_symlink(entry_path: &Path, name: &str) -> Option<String> {
+    let file = entry_path.join(name);
+    if file.exists() || !file.is_symlink() {
+        return None;
+    }
+    let target = fs::read_link(&file).ok()?;
+    Some(format!(
+        "{} is a broken symlink to {}",
+        name,
+        target.display()
+    ))
+}
+
+/// Suffix of the load error for a data file another process holds open, see [`is_in_use`].
+const IN_USE: &str = "is in use by another process";
+
+/// Returns true if HDF5 refused to open a file because it is locked, e.g. by a
+/// simulation still writing it.
+fn is_lock_error(message: &str) -> bool {
+    message.contains("unable to lock file") || message.contains("file is already open for")
+}
+
+/// Returns true if a load error is due to a data file that is still being written,
+/// so that the entry can be retried later instead of being reported as broken.
+pub fn is_in_use(reason: &str) -> bool {
+    reason.ends_with(IN_USE)
+}
+
+/// Opens the data files of an entry that exist, in order, along with their names. Fails
+/// if there are none or one of them is a broken symlink.
+fn open_data_files<'a>(
+    entry_path: &Path,
+    data_files: &'a [String],
+) -> Result<Vec<(&'a String, File)>, String> {
+    let mut files = Vec::new();
+    for name in data_files {
+        let h5_path = entry_path.join(name);
+        if h5_path.exists() {
+            let file = File::open(&h5_path).map_err(|e| match e.to_string() {
+                e if is_lock_error(&e) => format!("{} {}", name, IN_USE),
+                e => format!("cannot open {}: {}", name, e),
+            })?;
+            files.push((name, file));
+        } else if let Some(reason) = broken_symlink(entry_path, name) {
+            return Err(reason);
+        }
+    }
+    if files.is_empty() {
+        return Err(format!(
+            "cannot open {}: no such file",
+            data_files.join(", ")
+        ));
+    }
+    Ok(files)
 }
 
 fn parse_datetime_field(val: &str) -> Option<DateTime<Utc>> {
-    if let Ok(wrapped) = serde_json::from_str::<TypeWrapper>(val) {
-        if wrapped._type == "datetime" {
-            if let Ok(dt) = DateTime::parse_from_rfc3339(&wrapped.value) {
-                return Some(dt.with_timezone(&Utc));
-            } else if let Ok(dt) = DateTime::parse_from_rfc3339(&format!("{}Z", wrapped.value)) {
-                return Some(dt.with_timezone(&Utc));
-            }
+    if let Ok(wrapped) = serde_json::from_str::<TypeWrapper>(val)
+        && wrapped._type == "datetime"
+    {
+        if let Ok(dt) = DateTime::parse_from_rfc3339(&wrapped.value) {
+            return Some(dt.with_timezone(&Utc));
+        } else if let Ok(dt) = DateTime::parse_from_rfc3339(&format!("{}Z", wrapped.value)) {
+            return Some(dt.with_timezone(&Utc));
+        }
+    }
+    None
+}
+
+/// Nanoseconds per unit of a Unix timestamp, detected by its magnitude: below 1e11 it is
+/// in seconds, below 1e14 in milliseconds, below 1e17 in microseconds, else nanoseconds.
+fn epoch_unit_nanos(magnitude: f64) -> i64 {
+    match magnitude {
+        m if m < 1e11 => 1_000_000_000,
+        m if m < 1e14 => 1_000_000,
+        m if m < 1e17 => 1_000,
+        _ => 1,
+    }
+}
+
+/// Converts an integer Unix timestamp of any unit, see [`epoch_unit_nanos`].
+fn epoch_to_datetime(value: i64) -> Option<DateTime<Utc>> {
+    let nanos = value.checked_mul(epoch_unit_nanos(value.unsigned_abs() as f64))?;
+    Some(DateTime::from_timestamp_nanos(nanos))
+}
+
+/// Converts a fractional Unix timestamp of any unit, see [`epoch_unit_nanos`].
+fn epoch_float_to_datetime(value: f64) -> Option<DateTime<Utc>> {
+    let nanos = value * epoch_unit_nanos(value.abs()) as f64;
+    if !nanos.is_finite() || nanos.abs() >= i64::MAX as f64 {
+        return None;
+    }
+    Some(DateTime::from_timestamp_nanos(nanos.round() as i64))
+}
+
+/// Reads a timestamp attribute stored as a number since the Unix epoch from the last of
+/// `groups` that has it. Returns None if it is absent or not numeric.
+fn read_epoch_attr(groups: &[(&String, hdf5::Group)], name: &str) -> Option<DateTime<Utc>> {
+    let attr = groups
+        .iter()
+        .rev()
+        .find_map(|(_, group)| group.attr(name).ok())?;
+    if let Ok(value) = attr.read_scalar::<i64>() {
+        epoch_to_datetime(value)
+    } else {
+        epoch_float_to_datetime(attr.read_scalar::<f64>().ok()?)
+    }
+}
+
+/// Opens the group `name` of `file`. External links are followed by HDF5 itself (the
+/// target is looked up relative to the directory of `data.h5`); if the link exists but
+/// its target cannot be opened, the error says so instead of reporting a missing group.
+fn open_group(file: &File, name: &str) -> Result<hdf5::Group, String> {
+    file.group(name).map_err(|e| {
+        if name != "/" && file.link_exists(name) {
+            format!(
+                "group '{}' is a link that cannot be resolved (missing external file?): {}",
+                name, e
+            )
+        } else {
+            format!("missing group '{}': {}", name, e)
+        }
+    })
+}
+
+/// Longest fixed-length string read from an attribute; longer ones are truncated.
+const MAX_FIXED_STRING_LEN: usize = 1024;
+
+/// Reads a scalar string attribute, stored either as variable-length or as fixed-length
+/// (as written by C and Fortran codes) ASCII or UTF-8 string. Padding of fixed-length
+/// strings is removed. Returns None if the attribute is not a string.
+fn read_string_scalar(attr: &hdf5::Attribute) -> Option<String> {
+    use hdf5::types::{FixedAscii, FixedUnicode, TypeDescriptor, VarLenAscii, VarLenUnicode};
+
+    match attr.dtype().ok()?.to_descriptor().ok()? {
+        TypeDescriptor::VarLenUnicode => attr
+            .read_scalar::<VarLenUnicode>()
+            .ok()
+            .map(|s| s.to_string()),
+        TypeDescriptor::VarLenAscii => attr
+            .read_scalar::<VarLenAscii>()
+            .ok()
+            .map(|s| s.to_string()),
+        TypeDescriptor::FixedAscii(_) => attr
+            .read_scalar::<FixedAscii<MAX_FIXED_STRING_LEN>>()
+            .ok()
+            .map(|s| s.as_str().trim_end_matches(['\0', ' ']).to_string()),
+        TypeDescriptor::FixedUnicode(_) => attr
+            .read_scalar::<FixedUnicode<MAX_FIXED_STRING_LEN>>()
+            .ok()
+            .map(|s| s.as_str().trim_end_matches(['\0', ' ']).to_string()),
+        _ => None,
+    }
+}
+
+/// Reads a string attribute of `group`, see [`read_string_scalar`].
+fn read_string_attr(group: &hdf5::Group, name: &str) -> Result<String, String> {
+    let attr = group
+        .attr(name)
+        .map_err(|e| format!("missing attribute '{}': {}", name, e))?;
+    read_string_scalar(&attr).ok_or_else(|| {
+        let dtype = attr
+            .dtype()
+            .and_then(|t| t.to_descriptor())
+            .map(|t| format!("{:?}", t))
+            .unwrap_or_else(|e| format!("unknown ({})", e));
+        format!(
+            "attribute '{}' has unexpected type {}, expected a string",
+            name, dtype
+        )
+    })
+}
+
+/// Reads a metadata string attribute from the last of `groups` that has it, each given
+/// with the name of its data file for error messages. If `allow_missing` is set, an
+/// attribute absent from all groups yields None instead of an error; attributes of the
+/// wrong type still fail.
+fn read_meta_attr(
+    groups: &[(&String, hdf5::Group)],
+    name: &str,
+    allow_missing: bool,
+) -> Result<Option<String>, String> {
+    let read = |(file, group): &(&String, hdf5::Group)| {
+        read_string_attr(group, name).map_err(|e| format!("{}: {}", file, e))
+    };
+    match groups
+        .iter()
+        .rev()
+        .find(|(_, group)| group.attr(name).is_ok())
+    {
+        Some(group) => read(group).map(Some),
+        None => match groups.last() {
+            Some(group) if !allow_missing => read(group).map(Some),
+            _ => Ok(None),
+        },
+    }
+}
+
+/// Reads the `submitted` flag. Besides booleans, integers (non-zero is true) and the
+/// strings `"true"`/`"false"` are accepted, since writers differ in how they store it.
+/// Defaults to false if the attribute is absent or unreadable.
+fn read_submitted(group: &hdf5::Group) -> bool {
+    let Ok(attr) = group.attr("submitted") else {
+        return false;
+    };
+    if let Ok(b) = attr.read_scalar::<bool>() {
+        b
+    } else if let Ok(i) = attr.read_scalar::<i64>() {
+        i != 0
+    } else if let Ok(i) = attr.read_scalar::<i32>() {
+        i != 0
+    } else if let Ok(u) = attr.read_scalar::<u8>() {
+        u != 0
+    } else if let Some(s) = read_string_scalar(&attr) {
+        s.trim().eq_ignore_ascii_case("true")
+    } else {
+        false
+    }
+}
+
+/// Signature at the start of the HDF5 superblock
+const HDF5_SIGNATURE: &[u8; 8] = b"\x89HDF\r\n\x1a\n";
+
+/// Reads the superblock version of an HDF5 file, which tells which library versions
+/// can read it: 0 and 1 are written by default by all versions, 2 with library
+/// version bounds of 1.8 or later and 3 by 1.10 or later, e.g. for SWMR. The bindings
+/// do not expose it, so the superblock is located by its signature, which is at offset
+/// 0 or, after a user block, at 512 or a larger power of two. Returns None if the
+/// file cannot be read or is not HDF5.
+pub fn read_superblock_version(path: &Path) -> Option<u8> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut file = fs::File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    let mut offset = 0;
+    while offset + 9 <= len {
+        let mut buf = [0u8; 9];
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        file.read_exact(&mut buf).ok()?;
+        if &buf[..8] == HDF5_SIGNATURE {
+            return Some(buf[8]);
         }
+        offset = if offset == 0 { 512 } else { offset * 2 };
     }
     None
 }
 
-pub fn load_entry_meta(entry_path: &Path) -> Option<(MetaData, Parameters)> {
-    let h5_path = entry_path.join("data.h5");
-    let file = File::open(&h5_path).ok()?;
-    let root = file.group("/").ok()?;
+/// Summarizes how each of `datasets` is stored in the entry's data files: layout,
+/// chunk shape, filter pipeline and stored vs. uncompressed size. Each dataset is
+/// looked up in the last data file containing it. Only metadata is read, never the
+/// data itself. Datasets that cannot be opened get an `error` field.
+pub fn read_dataset_storage(
+    entry_path: &Path,
+    data_files: &[String],
+    datasets: &[String],
+) -> Result<Value, String> {
+    let files = open_data_files(entry_path, data_files)?;
+
+    let mut summary = serde_json::Map::new();
+    for name in datasets {
+        let file = files
+            .iter()
+            .map(|(_, file)| file)
+            .rev()
+            .find(|file| file.link_exists(name))
+            .unwrap_or(&files[files.len() - 1].1);
+        let info = match file.dataset(name) {
+            Ok(ds) => {
+                let item_size = ds.dtype().map(|t| t.size()).unwrap_or(0);
+                let filters: Vec<String> = ds
+                    .filters()
+                    .iter()
+                    .map(|f| format!("{:?}", f).to_lowercase())
+                    .collect();
+                serde_json::json!({
+                    "layout": format!("{:?}", ds.layout()).to_lowercase(),
+                    "chunk": ds.chunk(),
+                    "filters": filters,
+                    "storage_size": ds.storage_size(),
+                    "data_size": ds.size() * item_size,
+                })
+            }
+            Err(e) => serde_json::json!({ "error": e.to_string() }),
+        };
+        summary.insert(name.clone(), info);
+    }
+    Ok(Value::Object(summary))
+}
+
+/// Reads the attributes of the parameters group. Attributes of unsupported types are skipped.
+fn read_parameters(params_group: &hdf5::Group, opts: &LoadOptions) -> Result<Parameters, String> {
+    let mut parameters = Parameters::new();
+
+    let attr_names = params_group
+        .attr_names()
+        .map_err(|e| format!("cannot list parameters: {}", e))?;
+    for attr_name in attr_names {
+        if !opts.params_filter.matches(&attr_name) {
+            continue;
+        }
+        let attr = params_group
+            .attr(&attr_name)
+            .map_err(|e| format!("cannot open parameter '{}': {}", attr_name, e))?;
+        let value = if let Ok(scalar) = attr.read_scalar::<i64>() {
+            Value::from(scalar)
+        } else if let Ok(scalar) = attr.read_scalar::<f64>() {
+            Value::from(scalar)
+        } else if let Some(scalar) = read_string_scalar(&attr) {
+            if opts.parse_json_strings {
+                parse_json_string(&scalar)
+            } else {
+                Value::from(scalar)
+            }
+        } else {
+            continue; // Skip unsupported types
+        };
+        parameters.insert(attr_name, value);
+    }
+    Ok(parameters)
+}
+
+/// Writes a minimal valid entry named `example` into a collection, with all metadata
+/// attributes and a few parameters, so that `scan` and `display` have something to show.
+pub fn create_example_entry(collection_path: &Path) -> Result<std::path::PathBuf, String> {
+    use hdf5::types::VarLenUnicode;
+    use std::str::FromStr;
+
+    let entry_path = collection_path.join("example");
+    fs::create_dir_all(&entry_path)
+        .map_err(|e| format!("cannot create {}: {}", entry_path.display(), e))?;
+
+    let write = || -> hdf5::Result<()> {
+        let file = File::create(entry_path.join("data.h5"))?;
+        let created_at = format!(
+            r#"{{"__type__": "datetime", "__value__": "{}"}}"#,
+            Utc::now().to_rfc3339()
+        );
+        for (name, value) in [
+            ("created_at", created_at.as_str()),
+            ("description", "example entry created by simdex"),
+            ("status", "finished"),
+        ] {
+            let value = VarLenUnicode::from_str(value).expect("no null bytes");
+            file.new_attr::<VarLenUnicode>()
+                .shape(())
+                .create(name)?
+                .write_scalar(&value)?;
+        }
+        file.new_attr::<bool>()
+            .shape(())
+            .create("submitted")?
+            .write_scalar(&false)?;
+
+        let params = file.create_group(".parameters")?;
+        params
+            .new_attr::<f64>()
+            .shape(())
+            .create("dt")?
+            .write_scalar(&0.1)?;
+        params
+            .new_attr::<i64>()
+            .shape(())
+            .create("n_steps")?
+            .write_scalar(&100_i64)?;
+        params
+            .new_attr::<VarLenUnicode>()
+            .shape(())
+            .create("method")?
+            .write_scalar(&VarLenUnicode::from_str("euler").expect("no null bytes"))?;
+        Ok(())
+    };
+    write().map_err(|e| format!("cannot write example data.h5: {}", e))?;
+    Ok(entry_path)
+}
+
+pub fn load_entry_meta(entry_path: &Path, opts: &LoadOptions) -> Option<(MetaData, Parameters)> {
+    try_load_entry_meta(entry_path, opts).ok()
+}
+
+/// Like [`load_entry_meta`], but returns the reason if the entry cannot be read.
+pub fn try_load_entry_meta(
+    entry_path: &Path,
+    opts: &LoadOptions,
+) -> Result<(MetaData, Parameters), String> {
+    let files = open_data_files(entry_path, &opts.data_files)?;
+    let allow_missing = opts.allow_missing_meta;
+
+    // metadata groups of all files that have one, later files taking precedence
+    let mut roots = Vec::new();
+    let mut root_error = None;
+    for (name, file) in &files {
+        match open_group(file, &opts.meta_group) {
+            Ok(group) => roots.push((*name, group)),
+            Err(_) if opts.meta_group != "/" && !file.link_exists(&opts.meta_group) => {
+                eprintln!(
+                    "  [!] {:?}: {} has no group '{}', reading metadata from '/'",
+                    entry_path, name, opts.meta_group
+                );
+                match open_group(file, "/") {
+                    Ok(group) => roots.push((*name, group)),
+                    Err(e) => root_error = Some(format!("{}: {}", name, e)),
+                }
+            }
+            Err(e) => root_error = Some(format!("{}: {}", name, e)),
+        }
+    }
+    if let Some(e) = root_error
+        && roots.is_empty()
+        && !allow_missing
+    {
+        return Err(e);
+    }
 
     // Extract metadata attributes
-    let created_at_str: String = root
-        .attr("created_at")
-        .ok()?
-        .read_scalar::<hdf5::types::VarLenUnicode>()
-        .ok()?
-        .to_string();
-    let created_at = match parse_datetime_field(&created_at_str) {
-        Some(dt) => dt,
-        None => {
-            eprintln!("Failed to parse created_at: {}", created_at_str);
-            DateTime::from_timestamp_nanos(0)
+    let created_at = if let Some(dt) = read_epoch_attr(&roots, "created_at") {
+        dt
+    } else {
+        match read_meta_attr(&roots, "created_at", allow_missing)? {
+            Some(created_at_str) => match parse_datetime_field(&created_at_str) {
+                Some(dt) => dt,
+                None => {
+                    eprintln!(
+                        "  [!] {:?}: cannot parse created_at '{}', it will show as unknown",
+                        entry_path, created_at_str
+                    );
+                    UNKNOWN_CREATED_AT
+                }
+            },
+            None => get_data_mtime(entry_path, opts)
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or(UNKNOWN_CREATED_AT),
         }
     };
 
-    let description: String = root
-        .attr("description")
-        .ok()?
-        .read_scalar::<hdf5::types::VarLenUnicode>()
-        .ok()?
-        .to_string();
-    let status: String = root
-        .attr("status")
-        .ok()?
-        .read_scalar::<hdf5::types::VarLenUnicode>()
-        .ok()?
-        .to_string();
-    let submitted: bool = root
-        .attr("submitted")
-        .and_then(|attr| attr.read_scalar::<bool>())
-        .unwrap_or(false);
+    let description = read_meta_attr(&roots, "description", allow_missing)?.unwrap_or_default();
+    let status =
+        read_meta_attr(&roots, "status", allow_missing)?.unwrap_or_else(|| "unknown".to_string());
+    let submitted = roots
+        .iter()
+        .rev()
+        .find(|(_, group)| group.attr("submitted").is_ok())
+        .is_some_and(|(_, group)| read_submitted(group));
 
     let metadata = MetaData {
         created_at,
@@ -85,22 +621,412 @@ pub fn load_entry_meta(entry_path: &Path) -> Option<(MetaData, Parameters)> {
     };
 
     // Extract parameters
-    let params_group = root.group(".parameters").ok()?;
     let mut parameters = Parameters::new();
+    let mut params_error = None;
+    let mut found_params = false;
+    for (name, file) in &files {
+        match open_group(file, &opts.params_group) {
+            Ok(group) => {
+                let read = read_parameters(&group, opts).map_err(|e| format!("{}: {}", name, e))?;
+                parameters.extend(prefix_keys(read, opts.params_prefixes.get(*name)));
+                found_params = true;
+            }
+            // a group that is missing altogether means no parameters
+            Err(_) if !opts.require_params_group && !file.link_exists(&opts.params_group) => {}
+            Err(e) => params_error = Some(format!("{}: {}", name, e)),
+        }
+    }
+    if let Some(e) = params_error
+        && !found_params
+        && !allow_missing
+    {
+        return Err(e);
+    }
 
-    for attr_name in params_group.attr_names().ok()? {
-        let attr = params_group.attr(&attr_name).ok()?;
-        let value = if let Ok(scalar) = attr.read_scalar::<i64>() {
-            Value::from(scalar)
-        } else if let Ok(scalar) = attr.read_scalar::<f64>() {
-            Value::from(scalar)
-        } else if let Ok(scalar) = attr.read_scalar::<hdf5::types::VarLenUnicode>() {
-            Value::from(scalar.to_string())
-        } else {
-            continue; // Skip unsupported types
+    if let Some(re) = &opts.name_params
+        && let Some(name) = entry_path.file_name()
+    {
+        let mut from_name = Parameters::new();
+        apply_name_params(re, &name.to_string_lossy(), &mut from_name);
+        for (key, value) in prefix_keys(from_name, opts.params_prefixes.get("name")) {
+            parameters.entry(key).or_insert(value);
+        }
+    }
+
+    if opts.case_fold {
+        let (folded, conflicts) = fold_parameter_keys(parameters);
+        for key in conflicts {
+            eprintln!(
+                "  [!] {:?}: parameter '{}' has different values in different casings",
+                entry_path, key
+            );
+        }
+        parameters = folded;
+    }
+
+    Ok((metadata, parameters))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hdf5::types::VarLenUnicode;
+    use std::str::FromStr;
+
+    fn write_str_attr(group: &hdf5::Group, name: &str, value: &str) {
+        group
+            .new_attr::<VarLenUnicode>()
+            .shape(())
+            .create(name)
+            .unwrap()
+            .write_scalar(&VarLenUnicode::from_str(value).unwrap())
+            .unwrap();
+    }
+
+    /// Writes an entry's `data.h5` with all metadata attributes except `submitted`
+    /// and an empty `.parameters` group, and returns the open file.
+    fn create_test_entry(entry_path: &Path) -> File {
+        create_test_entry_without(entry_path, &[])
+    }
+
+    /// Like [`create_test_entry`], but leaves out the attributes or group in `skip`,
+    /// e.g. to write them with another type.
+    fn create_test_entry_without(entry_path: &Path, skip: &[&str]) -> File {
+        let file = File::create(entry_path.join("data.h5")).unwrap();
+        for (name, value) in [
+            (
+                "created_at",
+                r#"{"__type__": "datetime", "__value__": "2024-05-01T12:00:00"}"#,
+            ),
+            ("description", "test entry"),
+            ("status", "finished"),
+        ] {
+            if !skip.contains(&name) {
+                write_str_attr(&file, name, value);
+            }
+        }
+        if !skip.contains(&".parameters") {
+            file.create_group(".parameters").unwrap();
+        }
+        file
+    }
+
+    #[test]
+    fn test_int_backed_submitted_is_read_as_bool() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let file = create_test_entry(tmp_dir.path());
+        file.new_attr::<i64>()
+            .shape(())
+            .create("submitted")
+            .unwrap()
+            .write_scalar(&1_i64)
+            .unwrap();
+        drop(file);
+
+        let (meta, _) = try_load_entry_meta(tmp_dir.path(), &LoadOptions::default()).unwrap();
+        assert!(meta.submitted);
+        assert_eq!(meta.status, "finished");
+    }
+
+    #[test]
+    fn test_fixed_length_strings_are_read() {
+        use hdf5::types::{FixedAscii, FixedUnicode};
+
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let file = create_test_entry_without(tmp_dir.path(), &["description", "status"]);
+        for (name, value) in [("description", "fortran run  "), ("status", "finished")] {
+            file.new_attr::<FixedAscii<16>>()
+                .shape(())
+                .create(name)
+                .unwrap()
+                .write_scalar(&FixedAscii::<16>::from_ascii(value).unwrap())
+                .unwrap();
+        }
+        file.group(".parameters")
+            .unwrap()
+            .new_attr::<FixedUnicode<8>>()
+            .shape(())
+            .create("method")
+            .unwrap()
+            .write_scalar(&FixedUnicode::<8>::from_str("cg").unwrap())
+            .unwrap();
+        drop(file);
+
+        let (meta, params) = try_load_entry_meta(tmp_dir.path(), &LoadOptions::default()).unwrap();
+        assert_eq!(meta.description, "fortran run");
+        assert_eq!(meta.status, "finished");
+        assert_eq!(params["method"], Value::from("cg"));
+    }
+
+    #[test]
+    fn test_apply_name_params_coerces_captures() {
+        let re = Regex::new(r"run_re(?P<reynolds>\d+)_mesh(?P<mesh>\d+)_(?P<solver>\w+)").unwrap();
+        let mut parameters = Parameters::new();
+        parameters.insert("mesh".to_string(), Value::from(32));
+
+        apply_name_params(&re, "run_re1000_mesh64_cg", &mut parameters);
+
+        assert_eq!(parameters["reynolds"], Value::from(1000));
+        assert_eq!(parameters["solver"], Value::from("cg"));
+        // values read from the file take precedence
+        assert_eq!(parameters["mesh"], Value::from(32));
+    }
+
+    #[test]
+    fn test_fold_parameter_keys_reports_conflicts() {
+        let parameters: Parameters = serde_json::from_value(serde_json::json!({
+            "Reynolds": 100, "reynolds": 100, "MESH": 32, "mesh": 64, "dt": 0.1
+        }))
+        .unwrap();
+
+        let (folded, conflicts) = fold_parameter_keys(parameters);
+        assert_eq!(folded.len(), 3);
+        assert_eq!(folded["reynolds"], Value::from(100));
+        assert_eq!(folded["mesh"], Value::from(32));
+        assert_eq!(conflicts, ["mesh"]);
+    }
+
+    #[test]
+    fn test_parse_json_string_only_unwraps_containers() {
+        assert_eq!(parse_json_string("[1, 2, 3]"), serde_json::json!([1, 2, 3]));
+        assert_eq!(
+            parse_json_string(r#"{"a": 1}"#),
+            serde_json::json!({"a": 1})
+        );
+        assert_eq!(parse_json_string("42"), Value::from("42"));
+        assert_eq!(parse_json_string("cg"), Value::from("cg"));
+    }
+
+    #[test]
+    fn test_entry_without_parameters_group_has_no_parameters() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        drop(create_test_entry_without(tmp_dir.path(), &[".parameters"]));
+
+        let (meta, params) = try_load_entry_meta(tmp_dir.path(), &LoadOptions::default()).unwrap();
+        assert_eq!(meta.description, "test entry");
+        assert!(params.is_empty());
+
+        let opts = LoadOptions {
+            require_params_group: true,
+            ..Default::default()
         };
-        parameters.insert(attr_name, value);
+        let err = try_load_entry_meta(tmp_dir.path(), &opts).unwrap_err();
+        assert!(err.starts_with("data.h5: missing group '/.parameters'"));
+    }
+
+    #[test]
+    fn test_allow_missing_meta_falls_back_to_defaults() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        drop(File::create(tmp_dir.path().join("data.h5")).unwrap());
+
+        assert!(try_load_entry_meta(tmp_dir.path(), &LoadOptions::default()).is_err());
+
+        let opts = LoadOptions {
+            allow_missing_meta: true,
+            ..Default::default()
+        };
+        let (meta, params) = try_load_entry_meta(tmp_dir.path(), &opts).unwrap();
+        assert_eq!(meta.status, "unknown");
+        assert_eq!(meta.description, "");
+        assert!(!meta.submitted);
+        assert!(meta.created_at.timestamp() > 0);
+        assert!(params.is_empty());
+    }
+
+    #[test]
+    fn test_superblock_version_after_user_block() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = tmp_dir.path().join("data.h5");
+        let mut bytes = vec![0u8; 512];
+        bytes.extend_from_slice(HDF5_SIGNATURE);
+        bytes.extend_from_slice(&[2, 0, 0, 0]);
+        fs::write(&path, &bytes).unwrap();
+        assert_eq!(read_superblock_version(&path), Some(2));
+
+        fs::write(&path, "not hdf5").unwrap();
+        assert_eq!(read_superblock_version(&path), None);
+    }
+
+    #[test]
+    fn test_lock_errors_are_reported_as_in_use() {
+        assert!(is_lock_error(
+            "unable to lock file, errno = 11, error message = 'Resource temporarily unavailable'"
+        ));
+        assert!(is_lock_error("file is already open for write"));
+        assert!(!is_lock_error("file signature not found"));
+        assert!(is_in_use(&format!("data.h5 {}", IN_USE)));
+        assert!(!is_in_use("cannot open data.h5: file signature not found"));
+    }
+
+    #[test]
+    fn test_epoch_unit_is_detected_by_magnitude() {
+        let expected = DateTime::parse_from_rfc3339("2024-05-01T12:00:00Z").unwrap();
+        assert_eq!(epoch_to_datetime(1_714_564_800).unwrap(), expected);
+        assert_eq!(epoch_to_datetime(1_714_564_800_000).unwrap(), expected);
+        assert_eq!(epoch_to_datetime(1_714_564_800_000_000).unwrap(), expected);
+        assert_eq!(
+            epoch_to_datetime(1_714_564_800_000_000_000).unwrap(),
+            expected
+        );
+        assert_eq!(
+            epoch_float_to_datetime(1_714_564_800.5).unwrap(),
+            expected + chrono::Duration::milliseconds(500)
+        );
+    }
+
+    #[test]
+    fn test_numeric_created_at_is_read_as_epoch() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let file = create_test_entry_without(tmp_dir.path(), &["created_at"]);
+        file.new_attr::<i64>()
+            .shape(())
+            .create("created_at")
+            .unwrap()
+            .write_scalar(&1_714_564_800_000_i64)
+            .unwrap();
+        drop(file);
+
+        let (meta, _) = try_load_entry_meta(tmp_dir.path(), &LoadOptions::default()).unwrap();
+        assert_eq!(meta.created_at.to_rfc3339(), "2024-05-01T12:00:00+00:00");
+    }
+
+    #[test]
+    fn test_read_errors_name_file_and_attribute() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let file = create_test_entry_without(tmp_dir.path(), &["status"]);
+        file.new_attr::<i64>()
+            .shape(())
+            .create("status")
+            .unwrap()
+            .write_scalar(&3_i64)
+            .unwrap();
+        drop(file);
+
+        let err = try_load_entry_meta(tmp_dir.path(), &LoadOptions::default()).unwrap_err();
+        assert!(
+            err.starts_with("data.h5: attribute 'status' has unexpected type"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_missing_meta_group_falls_back_to_root() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        drop(create_test_entry(tmp_dir.path()));
+
+        let opts = LoadOptions {
+            meta_group: "/meta".to_string(),
+            ..Default::default()
+        };
+        let (meta, _) = try_load_entry_meta(tmp_dir.path(), &opts).unwrap();
+        assert_eq!(meta.status, "finished");
+        assert_eq!(meta.description, "test entry");
+    }
+
+    #[test]
+    fn test_params_filter_selects_the_stored_parameters() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let file = create_test_entry(tmp_dir.path());
+        let params = file.group(".parameters").unwrap();
+        for name in ["solver.method", "solver.tol", "mesh", "debug_level"] {
+            write_str_attr(&params, name, "x");
+        }
+        drop(file);
+
+        let glob = |p: &str| globset::Glob::new(p).unwrap();
+        let opts = LoadOptions {
+            params_filter: GlobFilter::new(&[glob("solver.*"), glob("debug*")], &[glob("debug*")])
+                .unwrap(),
+            ..Default::default()
+        };
+        let (_, params) = try_load_entry_meta(tmp_dir.path(), &opts).unwrap();
+        let keys: std::collections::BTreeSet<&str> = params.keys().map(String::as_str).collect();
+        assert_eq!(
+            keys,
+            std::collections::BTreeSet::from(["solver.method", "solver.tol"])
+        );
     }
 
-    Some((metadata, parameters))
+    #[test]
+    fn test_later_data_files_override_earlier() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let file = create_test_entry(tmp_dir.path());
+        write_str_attr(&file.group(".parameters").unwrap(), "method", "cg");
+        drop(file);
+        std::fs::rename(
+            tmp_dir.path().join("data.h5"),
+            tmp_dir.path().join("config.h5"),
+        )
+        .unwrap();
+        let results = File::create(tmp_dir.path().join("results.h5")).unwrap();
+        write_str_attr(&results, "status", "failed");
+        let params = results.create_group(".parameters").unwrap();
+        write_str_attr(&params, "method", "gmres");
+        write_str_attr(&params, "residual", "nan");
+        drop(results);
+
+        let opts = LoadOptions {
+            data_files: vec!["config.h5".to_string(), "results.h5".to_string()],
+            ..Default::default()
+        };
+        let (meta, params) = try_load_entry_meta(tmp_dir.path(), &opts).unwrap();
+        assert_eq!(meta.status, "failed");
+        assert_eq!(meta.description, "test entry");
+        assert_eq!(params["method"], Value::from("gmres"));
+        assert_eq!(params["residual"], Value::from("nan"));
+
+        let opts = LoadOptions {
+            params_prefixes: BTreeMap::from([
+                ("config.h5".to_string(), "cfg.".to_string()),
+                ("name".to_string(), "dir.".to_string()),
+            ]),
+            name_params: Some(Regex::new(r"(?P<method>\w+)").unwrap()),
+            ..opts
+        };
+        let (_, params) = try_load_entry_meta(tmp_dir.path(), &opts).unwrap();
+        assert_eq!(params["cfg.method"], Value::from("cg"));
+        assert_eq!(params["method"], Value::from("gmres"));
+        assert!(params.contains_key("dir.method"));
+        assert_eq!(params.len(), 4);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_data_file() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let shared = tmp_dir.path().join("shared.h5");
+        drop(create_test_entry(tmp_dir.path()));
+        std::fs::rename(tmp_dir.path().join("data.h5"), &shared).unwrap();
+        let old = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+        std::fs::File::options()
+            .write(true)
+            .open(&shared)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        std::os::unix::fs::symlink("shared.h5", tmp_dir.path().join("data.h5")).unwrap();
+
+        let mut opts = LoadOptions::default();
+        let mtime = get_data_mtime(tmp_dir.path(), &opts).unwrap();
+        assert_eq!(std::time::SystemTime::from(mtime), old);
+        opts.symlink_mtime = true;
+        assert!(std::time::SystemTime::from(get_data_mtime(tmp_dir.path(), &opts).unwrap()) > old);
+        assert_eq!(broken_symlink(tmp_dir.path(), "data.h5"), None);
+
+        std::fs::remove_file(&shared).unwrap();
+        let reason = try_load_entry_meta(tmp_dir.path(), &opts).unwrap_err();
+        assert_eq!(reason, "data.h5 is a broken symlink to shared.h5");
+    }
+
+    #[test]
+    fn test_example_entry_is_loadable() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let entry_path = create_example_entry(tmp_dir.path()).unwrap();
+
+        let (meta, params) = try_load_entry_meta(&entry_path, &LoadOptions::default()).unwrap();
+        assert_eq!(meta.status, "finished");
+        assert_eq!(params["n_steps"], Value::from(100));
+        assert_eq!(params["method"], Value::from("euler"));
+    }
 }
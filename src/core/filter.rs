@@ -0,0 +1,216 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use regex::Regex;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use crate::core::grid::value_matches;
+
+/// Comparison operator of a filter clause.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// Membership in an array (or substring of a string)
+    Contains,
+}
+
+/// A single filter clause like `dt<0.1`, `box_size[0]>10` or `materials contains steel`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Filter {
+    pub key: String,
+    /// Array indices applied to the value of `key`, in order
+    pub indices: Vec<usize>,
+    pub op: Op,
+    pub value: String,
+}
+
+static CLAUSE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^\s*(?P<key>[A-Za-z_][\w.\-]*)(?P<idx>(?:\[\d+\])*)\s*(?P<op>==|!=|<=|>=|=|<|>|\s+contains\s+)\s*(?P<value>.*?)\s*$",
+    )
+    .unwrap()
+});
+
+impl Filter {
+    /// Parses a clause of the form `<key>[<index>]... <op> <value>`, where `op` is one of
+    /// `=`, `==`, `!=`, `<`, `<=`, `>`, `>=` or `contains`. The value may be quoted.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let caps = CLAUSE_RE
+            .captures(s)
+            .ok_or_else(|| format!("Invalid filter '{}': expected e.g. 'dt<0.1'", s))?;
+        let indices = caps["idx"]
+            .trim_matches(['[', ']'])
+            .split("][")
+            .filter(|i| !i.is_empty())
+            .map(|i| {
+                i.parse()
+                    .map_err(|_| format!("Invalid index in filter '{}'", s))
+            })
+            .collect::<Result<Vec<usize>, String>>()?;
+        let op = match caps["op"].trim() {
+            "=" | "==" => Op::Eq,
+            "!=" => Op::Ne,
+            "<" => Op::Lt,
+            "<=" => Op::Le,
+            ">" => Op::Gt,
+            ">=" => Op::Ge,
+            _ => Op::Contains,
+        };
+        let value = &caps["value"];
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+            .unwrap_or(value);
+        if value.is_empty() {
+            return Err(format!("Invalid filter '{}': missing value", s));
+        }
+        Ok(Filter {
+            key: caps["key"].to_string(),
+            indices,
+            op,
+            value: value.to_string(),
+        })
+    }
+
+    /// Evaluates the clause against the value of its key (None if the key is absent).
+    /// A missing key or an out-of-range index never matches, not even for `!=`.
+    pub fn matches(&self, value: Option<&Value>) -> bool {
+        let mut value = match value {
+            Some(v) => v,
+            None => return false,
+        };
+        for &i in &self.indices {
+            value = match value.as_array().and_then(|a| a.get(i)) {
+                Some(v) => v,
+                None => return false,
+            };
+        }
+
+        match self.op {
+            Op::Eq => value_matches(value, &self.value),
+            Op::Ne => !value_matches(value, &self.value),
+            Op::Contains => match value {
+                Value::Array(items) => items.iter().any(|v| value_matches(v, &self.value)),
+                Value::String(s) => s.contains(&self.value),
+                _ => false,
+            },
+            Op::Lt | Op::Le | Op::Gt | Op::Ge => {
+                let ordering = match (value, self.value.parse::<f64>()) {
+                    (Value::Number(n), Ok(rhs)) => n.as_f64().and_then(|lhs| lhs.partial_cmp(&rhs)),
+                    (Value::String(s), _) => Some(s.as_str().cmp(self.value.as_str())),
+                    _ => None,
+                };
+                match ordering {
+                    Some(ord) => match self.op {
+                        Op::Lt => ord == Ordering::Less,
+                        Op::Le => ord != Ordering::Greater,
+                        Op::Gt => ord == Ordering::Greater,
+                        _ => ord != Ordering::Less,
+                    },
+                    None => false,
+                }
+            }
+        }
+    }
+}
+
+/// Include/exclude glob filter, used on collection paths (`scan --include`/`--exclude`)
+/// and parameter keys (`scan --param-include`/`--param-exclude`). A path matches if it
+/// matches any include pattern (or there are none) and no exclude pattern.
+#[derive(Clone, Debug, Default)]
+pub struct GlobFilter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+impl GlobFilter {
+    pub fn new(include: &[Glob], exclude: &[Glob]) -> Result<Self, globset::Error> {
+        let build = |globs: &[Glob]| {
+            let mut builder = GlobSetBuilder::new();
+            for glob in globs {
+                builder.add(glob.clone());
+            }
+            builder.build()
+        };
+        Ok(Self {
+            include: if include.is_empty() {
+                None
+            } else {
+                Some(build(include)?)
+            },
+            exclude: build(exclude)?,
+        })
+    }
+
+    /// Whether a path, e.g. a collection path relative to the scanned root, passes the filter.
+    pub fn matches(&self, path: impl AsRef<Path>) -> bool {
+        let path = path.as_ref();
+        self.include.as_ref().is_none_or(|set| set.is_match(path)) && !self.exclude.is_match(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn eval(clause: &str, value: Value) -> bool {
+        Filter::parse(clause).unwrap().matches(Some(&value))
+    }
+
+    #[test]
+    fn test_comparisons() {
+        assert!(eval("dt<0.1", json!(0.01)));
+        assert!(eval("dt >= 0.1", json!(0.1)));
+        assert!(!eval("dt>0.1", json!(0.1)));
+        assert!(eval("method=cg", json!("cg")));
+        assert!(eval("method != 'cg'", json!("gmres")));
+        assert!(!Filter::parse("dt!=1").unwrap().matches(None));
+    }
+
+    #[test]
+    fn test_array_index_and_membership() {
+        let box_size = json!([12, 3, 4]);
+        assert!(eval("box_size[0]>10", box_size.clone()));
+        assert!(!eval("box_size[1]>10", box_size.clone()));
+        // out of range: no match for any operator
+        assert!(!eval("box_size[5]>10", box_size.clone()));
+        assert!(!eval("box_size[5]!=10", box_size));
+        assert!(eval("grid[1][0]=2", json!([[0, 1], [2, 3]])));
+
+        let materials = json!(["steel", "copper"]);
+        assert!(eval("materials contains steel", materials.clone()));
+        assert!(!eval("materials contains wood", materials));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Filter::parse("dt").is_err());
+        assert!(Filter::parse("dt<").is_err());
+        assert!(Filter::parse("[0]>1").is_err());
+    }
+
+    #[test]
+    fn test_glob_filter_excludes_take_precedence() {
+        let glob = |p: &str| Glob::new(p).unwrap();
+        let filter = GlobFilter::new(
+            &[glob("**/production/**"), glob("legacy/*")],
+            &[glob("**/broken*")],
+        )
+        .unwrap();
+        assert!(filter.matches(Path::new("2024/production/sweep")));
+        assert!(filter.matches(Path::new("legacy/run1")));
+        assert!(!filter.matches(Path::new("2024/staging/sweep")));
+        assert!(!filter.matches(Path::new("2024/production/broken_sweep")));
+
+        let everything = GlobFilter::default();
+        assert!(everything.matches(Path::new("any/where")));
+    }
+}
@@ -0,0 +1,138 @@
+use serde_json::Value;
+
+use crate::core::types::Parameters;
+
+/// A single axis of a parameter grid: a parameter key and the values it should take.
+pub type Axis = (String, Vec<String>);
+
+/// Parses a grid specification into its axes.
+///
+/// The specification is a whitespace separated list of `key=value1,value2,...` terms,
+/// e.g. `dt=0.1,0.01 method=cg,gmres`. Axes keep the order in which they are given.
+pub fn parse_spec(spec: &str) -> Result<Vec<Axis>, String> {
+    let mut axes: Vec<Axis> = Vec::new();
+    for term in spec.split_whitespace() {
+        let (key, values) = term
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid grid term '{}': expected key=v1,v2,...", term))?;
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(format!(
+                "Invalid grid term '{}': empty parameter name",
+                term
+            ));
+        }
+        if axes.iter().any(|(k, _)| k == key) {
+            return Err(format!(
+                "Parameter '{}' appears more than once in the grid",
+                key
+            ));
+        }
+        let values: Vec<String> = values
+            .split(',')
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect();
+        if values.is_empty() {
+            return Err(format!("Parameter '{}' has no values in the grid", key));
+        }
+        axes.push((key.to_string(), values));
+    }
+    if axes.is_empty() {
+        return Err("Empty grid specification".to_string());
+    }
+    Ok(axes)
+}
+
+/// Returns the Cartesian product of all axes, one combination per inner vector,
+/// with values in the same order as the axes.
+pub fn product(axes: &[Axis]) -> Vec<Vec<String>> {
+    axes.iter().fold(vec![Vec::new()], |acc, (_, values)| {
+        acc.iter()
+            .flat_map(|combo| {
+                values.iter().map(move |v| {
+                    let mut next = combo.clone();
+                    next.push(v.clone());
+                    next
+                })
+            })
+            .collect()
+    })
+}
+
+/// Checks whether a stored parameter value equals a value written in a grid specification.
+///
+/// Numbers are compared numerically (so `0.10` matches `0.1`), booleans and strings by value.
+/// Arrays and objects never match.
+pub fn value_matches(stored: &Value, spec: &str) -> bool {
+    match stored {
+        Value::Number(n) => match (n.as_f64(), spec.parse::<f64>()) {
+            (Some(a), Ok(b)) => a == b,
+            _ => false,
+        },
+        Value::Bool(b) => spec.parse::<bool>() == Ok(*b),
+        Value::String(s) => s == spec,
+        Value::Null => spec == "null",
+        _ => false,
+    }
+}
+
+/// Returns the combinations of the grid that no simulation in `existing` covers.
+///
+/// A simulation covers a combination if it has every grid key with a matching value;
+/// parameters outside the grid are ignored.
+pub fn missing(axes: &[Axis], existing: &[Parameters]) -> Vec<Vec<String>> {
+    product(axes)
+        .into_iter()
+        .filter(|combo| {
+            !existing.iter().any(|params| {
+                axes.iter().zip(combo).all(|((key, _), value)| {
+                    params.get(key).is_some_and(|v| value_matches(v, value))
+                })
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_missing_subtracts_existing_combinations() {
+        let axes = parse_spec("dt=0.1,0.01 method=cg,gmres").unwrap();
+        assert_eq!(product(&axes).len(), 4);
+
+        let existing: Vec<Parameters> = vec![
+            [
+                ("dt".to_string(), json!(0.1)),
+                ("method".to_string(), json!("cg")),
+            ]
+            .into(),
+            [
+                ("dt".to_string(), json!(0.01)),
+                ("method".to_string(), json!("gmres")),
+                ("seed".to_string(), json!(1)),
+            ]
+            .into(),
+        ];
+
+        let missing = missing(&axes, &existing);
+        assert_eq!(
+            missing,
+            vec![
+                vec!["0.1".to_string(), "gmres".to_string()],
+                vec!["0.01".to_string(), "cg".to_string()]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_spec_rejects_malformed_terms() {
+        assert!(parse_spec("dt").is_err());
+        assert!(parse_spec("dt=").is_err());
+        assert!(parse_spec("dt=1 dt=2").is_err());
+        assert!(parse_spec("").is_err());
+    }
+}
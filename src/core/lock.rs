@@ -0,0 +1,78 @@
+use fs2::FileExt;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const LOCK_FILE_EXTENSION: &str = "lock";
+
+/// Returns the path of the lock file guarding writes to the given database, the
+/// database's file name with [`LOCK_FILE_EXTENSION`] appended, so that databases in the
+/// same directory are locked independently.
+pub fn lock_path(db_path: &Path) -> PathBuf {
+    let mut name = db_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(LOCK_FILE_EXTENSION);
+    db_path.with_file_name(name)
+}
+
+/// Holds an exclusive advisory lock on a database; released when dropped.
+pub struct DbLock {
+    file: File,
+}
+
+impl Drop for DbLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+/// Acquires the exclusive lock for the given database without blocking.
+///
+/// # Errors
+///
+/// Returns `WouldBlock` if another process holds the lock, or any error
+/// encountered while creating the lock file.
+pub fn try_lock(db_path: &Path) -> io::Result<DbLock> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(lock_path(db_path))?;
+    file.try_lock_exclusive().map_err(|e| {
+        if e.kind() == fs2::lock_contended_error().kind() {
+            io::Error::new(io::ErrorKind::WouldBlock, "another scan is in progress")
+        } else {
+            e
+        }
+    })?;
+    Ok(DbLock { file })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_lock_fails_until_released() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let db_path = tmp_dir.path().join("simdex.db");
+
+        let lock = try_lock(&db_path).expect("first lock");
+        let err = try_lock(&db_path).err().expect("second lock must fail");
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+
+        drop(lock);
+        assert!(try_lock(&db_path).is_ok());
+    }
+
+    #[test]
+    fn test_databases_in_one_directory_lock_independently() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let (a, b) = (tmp_dir.path().join("a.db"), tmp_dir.path().join("b.db"));
+        assert_eq!(lock_path(&a), tmp_dir.path().join("a.db.lock"));
+
+        let _a = try_lock(&a).expect("lock a");
+        let _b = try_lock(&b).expect("lock b while a is locked");
+        assert!(try_lock(&a).is_err());
+    }
+}
@@ -2,4 +2,9 @@ pub mod collection;
 pub mod db;
 pub mod discovery;
 pub mod entry;
+pub mod filter;
+pub mod grid;
+pub mod lock;
+pub mod sidecar;
+pub mod time;
 pub mod types;
@@ -42,6 +42,12 @@ pub fn display_polars(uid: &str) {
         .map(|r| r.unwrap())
         .collect();
 
+    // `df!` followed by `with_column` on empty vectors fails on the length check
+    if rows.is_empty() {
+        println!("collection {} has no simulations", uid);
+        return;
+    }
+
     // Flatten the parameters field into separate columns
     let (all_keys, columns) = flatten_hashmap_field(&rows, |r| &r.parameters);
 
@@ -72,8 +78,12 @@ pub fn display_polars(uid: &str) {
 
     for key in &all_keys {
         let col_name = format!("parameters_{}", key);
-        let col_values: Vec<Option<String>> = columns.get(key).unwrap().clone();
-        let s = Series::new(col_name.into(), col_values);
+        let s = match columns.get(key).unwrap().clone() {
+            Column::Int(values) => Series::new(col_name.into(), values),
+            Column::Float(values) => Series::new(col_name.into(), values),
+            Column::Bool(values) => Series::new(col_name.into(), values),
+            Column::Str(values) => Series::new(col_name.into(), values),
+        };
         df_builder.with_column(s).unwrap();
     }
 
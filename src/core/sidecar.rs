@@ -0,0 +1,118 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use crate::core::types::MetaFileContent;
+
+/// Compression applied to a sidecar metadata file, derived from its extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+}
+
+/// Writes a sidecar metadata file, compressing it if the path ends in `.gz` or `.zst`.
+pub fn write(path: &Path, contents: &str) -> io::Result<()> {
+    match Compression::from_path(path) {
+        Compression::None => fs::write(path, contents),
+        Compression::Gzip => {
+            let file = fs::File::create(path)?;
+            let mut encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+            encoder.write_all(contents.as_bytes())?;
+            encoder.finish()?;
+            Ok(())
+        }
+        Compression::Zstd => {
+            let file = fs::File::create(path)?;
+            let mut encoder = zstd::stream::write::Encoder::new(file, 0)?;
+            encoder.write_all(contents.as_bytes())?;
+            encoder.finish()?;
+            Ok(())
+        }
+    }
+}
+
+/// Reads a sidecar metadata file, decompressing it if the path ends in `.gz` or `.zst`.
+pub fn read_to_string(path: &Path) -> io::Result<String> {
+    let mut contents = String::new();
+    match Compression::from_path(path) {
+        Compression::None => return fs::read_to_string(path),
+        Compression::Gzip => {
+            flate2::read::GzDecoder::new(fs::File::open(path)?).read_to_string(&mut contents)?;
+        }
+        Compression::Zstd => {
+            zstd::stream::read::Decoder::new(fs::File::open(path)?)?
+                .read_to_string(&mut contents)?;
+        }
+    }
+    Ok(contents)
+}
+
+/// Reads the metadata and parameters of a sidecar file written by `migrate`.
+pub fn load(path: &Path) -> Result<MetaFileContent, String> {
+    let contents =
+        read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    serde_yaml::from_str(&contents).map_err(|e| format!("invalid {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compressed_round_trip() {
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let contents = "metadata:\n  status: finished\nparameters:\n  dt: 0.1\n";
+
+        for name in ["meta.yml", "meta.yml.gz", "meta.yml.zst"] {
+            let path = tmp_dir.path().join(name);
+            write(&path, contents).expect("Failed to write sidecar");
+            assert_eq!(
+                read_to_string(&path).expect("Failed to read sidecar"),
+                contents
+            );
+        }
+
+        // compressed files must not be stored as plain text
+        let raw = fs::read(tmp_dir.path().join("meta.yml.gz")).unwrap();
+        assert_ne!(raw, contents.as_bytes());
+    }
+
+    #[test]
+    fn test_load_round_trips_migrated_file() {
+        use crate::core::types::MetaData;
+
+        let tmp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = tmp_dir.path().join("meta.yml");
+        let content = MetaFileContent {
+            metadata: MetaData {
+                created_at: chrono::DateTime::parse_from_rfc3339("2024-05-01T12:00:00+00:00")
+                    .unwrap()
+                    .to_utc(),
+                description: "two\nlines".to_string(),
+                status: "finished".to_string(),
+                submitted: true,
+            },
+            parameters: serde_json::from_str(r#"{"dt": 0.1, "method": "cg"}"#).unwrap(),
+        };
+        let yaml = serde_yaml::to_string(&content).unwrap();
+        assert!(yaml.contains("created_at: 2024-05-01T12:00:00+00:00"));
+        write(&path, &yaml).unwrap();
+
+        let loaded = load(&path).unwrap();
+        assert_eq!(loaded.metadata.created_at, content.metadata.created_at);
+        assert_eq!(loaded.metadata.description, "two\nlines");
+        assert_eq!(loaded.parameters, content.parameters);
+    }
+}
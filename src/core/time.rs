@@ -0,0 +1,38 @@
+use chrono::{DateTime, Duration, Utc};
+
+/// `created_at` stored for entries whose timestamp could not be read or parsed.
+pub const UNKNOWN_CREATED_AT: DateTime<Utc> = DateTime::<Utc>::UNIX_EPOCH;
+
+/// How [`UNKNOWN_CREATED_AT`] is shown by `display` and `export`.
+pub const UNKNOWN_LABEL: &str = "<unknown>";
+
+/// Returns true if a stored `created_at` is the [`UNKNOWN_CREATED_AT`] sentinel rather
+/// than a real time.
+pub fn is_unknown_created_at(created_at: &str) -> bool {
+    DateTime::parse_from_rfc3339(created_at).is_ok_and(|dt| dt == UNKNOWN_CREATED_AT)
+}
+
+/// Parses an age such as `30d`, `12h`, `90m`, `45s` or `2w` into a duration.
+/// A bare number is interpreted as days.
+pub fn parse_age(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let n: i64 = number
+        .parse()
+        .map_err(|_| format!("Invalid age '{}': expected e.g. 30d, 12h or 2w", s))?;
+    let duration = match unit {
+        "s" => Duration::try_seconds(n),
+        "m" => Duration::try_minutes(n),
+        "h" => Duration::try_hours(n),
+        "" | "d" => Duration::try_days(n),
+        "w" => Duration::try_weeks(n),
+        _ => {
+            return Err(format!(
+                "Invalid age unit '{}' in '{}': use s, m, h, d or w",
+                unit, s
+            ));
+        }
+    };
+    duration.ok_or_else(|| format!("Age '{}' is out of range", s))
+}
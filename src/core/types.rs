@@ -1,10 +1,11 @@
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct MetaData {
+    #[serde(with = "rfc3339")]
     pub created_at: DateTime<Utc>,
     pub description: String,
     pub status: String,
@@ -13,7 +14,45 @@ pub struct MetaData {
 
 pub type Parameters = HashMap<String, Value>;
 
-#[derive(Serialize)]
+/// Contents of a sidecar metadata file written by `migrate`. Parameters are written
+/// sorted by key.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetaFileContent {
+    pub metadata: MetaData,
+    #[serde(serialize_with = "serialize_sorted")]
+    pub parameters: Parameters,
+}
+
+fn serialize_sorted<S: serde::Serializer>(
+    parameters: &Parameters,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    parameters
+        .iter()
+        .collect::<BTreeMap<_, _>>()
+        .serialize(serializer)
+}
+
+/// `created_at` as an RFC 3339 string with a `+00:00` offset, as stored in the database.
+mod rfc3339 {
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(dt: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&dt.to_rfc3339())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<Utc>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        DateTime::parse_from_rfc3339(&s)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Author {
     pub name: String,
     pub email: String,
@@ -23,5 +62,6 @@ pub struct Author {
 pub struct MetaFile<'a> {
     pub uid: &'a str,
     pub created: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub author: Option<Author>,
 }
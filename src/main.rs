@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -9,19 +9,387 @@ pub struct Cli {
     pub command: Commands,
 }
 
+/// Where to find metadata inside an entry's data files
+#[derive(Args)]
+pub struct EntryArgs {
+    /// Name of an HDF5 file of each entry; may be repeated, later files override the
+    /// metadata and parameters of earlier ones (e.g. `config.h5` then `results.h5`)
+    #[arg(long = "data-file", value_name = "FILE", default_value = "data.h5")]
+    data_files: Vec<String>,
+    /// HDF5 group holding the metadata attributes (created_at, status, ...); defaults to
+    /// `entry.meta_group` in simdex.toml, else `/`. Entries lacking it are read from `/`
+    #[arg(long)]
+    meta_group: Option<String>,
+    /// HDF5 group holding the parameters as attributes
+    #[arg(long, default_value = "/.parameters")]
+    params_group: String,
+    /// Regex with named groups extracting parameters from entry directory names,
+    /// e.g. `run_re(?P<reynolds>\d+)_mesh(?P<mesh>\d+)`
+    #[arg(long, value_parser = regex::Regex::new)]
+    name_params: Option<regex::Regex>,
+    /// Parse string parameters holding JSON arrays or objects into structured values
+    #[arg(long)]
+    parse_json_strings: bool,
+    /// Index entries lacking metadata attributes with defaults (mtime as created_at,
+    /// status `unknown`) instead of reporting them as failed
+    #[arg(long)]
+    allow_missing_meta: bool,
+    /// Report entries without a parameters group as failed instead of indexing them
+    /// with no parameters
+    #[arg(long)]
+    require_params_group: bool,
+    /// Prefix the keys of the parameters from one source, e.g. `data.h5=h5.` or
+    /// `name=dir.` for those of --name-params, so data files don't override each other
+    /// (those from the name never override the data files); may be repeated
+    #[arg(long, value_name = "SOURCE=PREFIX", value_parser = parse_prefix)]
+    parameters_prefix: Vec<(String, String)>,
+    /// Lowercase parameter keys, merging variants like `Reynolds` and `reynolds`;
+    /// differing values are reported and the first key in sort order wins
+    #[arg(long)]
+    parameters_case_fold: bool,
+    /// Also use the mtime of data files that are symlinks themselves for change
+    /// detection, so re-pointing a link triggers a re-sync
+    #[arg(long)]
+    symlink_mtime: bool,
+}
+
+/// Parses a non-negative number of seconds, e.g. `1` or `0.5`.
+fn parse_seconds(s: &str) -> Result<std::time::Duration, String> {
+    s.parse::<f64>()
+        .ok()
+        .and_then(|secs| std::time::Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| format!("expected a non-negative number of seconds, got '{}'", s))
+}
+
+/// Parses a probability between 0 and 1, e.g. `0.05`.
+fn parse_rate(s: &str) -> Result<f64, String> {
+    s.parse::<f64>()
+        .ok()
+        .filter(|rate| (0.0..=1.0).contains(rate))
+        .ok_or_else(|| format!("expected a number between 0 and 1, got '{}'", s))
+}
+
+/// Parses a `SOURCE=PREFIX` pair of `--parameters-prefix`.
+fn parse_prefix(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((source, prefix)) if !source.is_empty() => {
+            Ok((source.to_string(), prefix.to_string()))
+        }
+        _ => Err(format!("expected SOURCE=PREFIX, got '{}'", s)),
+    }
+}
+
+impl EntryArgs {
+    fn load_options(&self) -> simdex::core::entry::LoadOptions {
+        simdex::core::entry::LoadOptions {
+            data_files: self.data_files.clone(),
+            meta_group: self
+                .meta_group
+                .clone()
+                .or_else(|| simdex::config::Config::load().entry.meta_group)
+                .unwrap_or_else(|| "/".to_string()),
+            params_group: self.params_group.clone(),
+            params_filter: Default::default(),
+            name_params: self.name_params.clone(),
+            parse_json_strings: self.parse_json_strings,
+            allow_missing_meta: self.allow_missing_meta,
+            require_params_group: self.require_params_group,
+            params_prefixes: self.parameters_prefix.iter().cloned().collect(),
+            case_fold: self.parameters_case_fold,
+            symlink_mtime: self.symlink_mtime,
+        }
+    }
+}
+
 #[derive(Subcommand)]
 pub enum Commands {
     /// Scan & sync simulation data into the cache database
     Scan {
         #[arg(default_value = ".")]
         root: PathBuf,
+        /// Database file, or a directory holding a separate database per scanned root
         #[arg(short, long, default_value = "simdex.db")]
         db: PathBuf,
+        #[command(flatten)]
+        entry: EntryArgs,
+        /// Do not take the lock that prevents concurrent scans of the same database
+        #[arg(long)]
+        no_lock: bool,
+        /// Only index entries that are not in the database yet, ignoring updates to known ones
+        #[arg(long)]
+        new_only: bool,
+        /// Commit every N synced entries instead of once at the end; an interrupted
+        /// scan then keeps the batches committed so far
+        #[arg(long, value_name = "N")]
+        batch_size: Option<std::num::NonZeroUsize>,
+        /// Instead of per-entry progress, list the added, updated and failed entries at the end
+        #[arg(long)]
+        only_changed: bool,
+        /// Like --only-changed, but print the list as JSON
+        #[arg(long)]
+        json: bool,
+        /// Record layout, chunking, filters and sizes of this dataset (e.g. `/results/u`)
+        /// in `storage_json`; may be repeated. Opens every synced file once more
+        #[arg(long, value_name = "DATASET")]
+        dataset_storage: Vec<String>,
+        /// Record for each collection the collection it is nested in, shown by `tree`
+        #[arg(long, conflicts_with = "stream")]
+        nested: bool,
+        /// Sync and commit each collection as soon as it is found instead of walking the
+        /// whole tree first; an interrupted scan keeps the collections synced so far
+        #[arg(long)]
+        stream: bool,
+        /// Only sync collections whose path relative to ROOT matches this glob, e.g.
+        /// `**/production/**`; may be repeated
+        #[arg(long, value_name = "GLOB", value_parser = globset::Glob::new)]
+        include: Vec<globset::Glob>,
+        /// Skip collections whose path relative to ROOT matches this glob; takes
+        /// precedence over --include and may be repeated
+        #[arg(long, value_name = "GLOB", value_parser = globset::Glob::new)]
+        exclude: Vec<globset::Glob>,
+        /// Only store parameters whose key matches this glob, e.g. `solver_*`; may be
+        /// repeated
+        #[arg(long, value_name = "GLOB", value_parser = globset::Glob::new)]
+        param_include: Vec<globset::Glob>,
+        /// Do not store parameters whose key matches this glob; takes precedence over
+        /// --param-include and may be repeated
+        #[arg(long, value_name = "GLOB", value_parser = globset::Glob::new)]
+        param_exclude: Vec<globset::Glob>,
+        /// Abort on a collection marker that is not valid YAML instead of warning and
+        /// using the uid from its file name
+        #[arg(long)]
+        strict_markers: bool,
+        /// Abort when two collection directories resolve to the same uid, e.g. a copied
+        /// collection, instead of skipping the second one
+        #[arg(long)]
+        strict: bool,
+        /// Index each collection under the uid written in its marker rather than the
+        /// one in the marker's file name, e.g. after renaming the marker
+        #[arg(long)]
+        marker_uid: bool,
+        /// Print the time spent in discovery, entry listing, HDF5 reads and database
+        /// writes and the slowest entries at the end; stores each entry's read time
+        #[arg(long)]
+        profile: bool,
+        /// Number of the slowest entries printed with --profile
+        #[arg(long, value_name = "N", default_value_t = 10, requires = "profile")]
+        slowest: usize,
+        /// Resolution in seconds to which file mtimes and last sync times are truncated
+        /// before comparing them; entries changed in the same interval as their last
+        /// sync are re-read. Use 0 to compare exactly
+        #[arg(long, value_name = "SECONDS", default_value = "1", value_parser = parse_seconds)]
+        mtime_resolution: std::time::Duration,
+        /// Stop at the first entry that cannot be read, e.g. to catch a bad producer in CI
+        #[arg(long, conflicts_with = "keep_going")]
+        fail_fast: bool,
+        /// Record entries that cannot be read and continue with the next one (default)
+        #[arg(long)]
+        keep_going: bool,
+        /// Retry an entry whose data file is still being written this many times before
+        /// skipping it until the next scan
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        lock_retries: usize,
+        /// Seconds to wait between these retries
+        #[arg(long, value_name = "SECONDS", default_value = "1", value_parser = parse_seconds)]
+        lock_retry_delay: std::time::Duration,
+        /// Sync only the entry directories listed on stdin, one per line, instead of
+        /// walking ROOT; e.g. the changed entries reported by a job scheduler
+        #[arg(long, conflicts_with_all = ["nested", "stream", "include", "exclude", "paths_file"])]
+        from_stdin: bool,
+        /// Like --from-stdin, but read the list of entry directories from this file
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["nested", "stream", "include", "exclude"])]
+        paths_file: Option<PathBuf>,
     },
 
     Ls {
         #[arg(short, long, default_value = "simdex.db")]
         db: PathBuf,
+        /// Show collection paths relative to this directory where possible
+        #[arg(long)]
+        relative_to: Option<PathBuf>,
+    },
+
+    /// Browse the cached collections and simulations interactively
+    Tui {
+        #[arg(short, long, default_value = "simdex.db")]
+        db: PathBuf,
+    },
+
+    /// Show the cached collections nested in each other, see `scan --nested`
+    Tree {
+        #[arg(short, long, default_value = "simdex.db")]
+        db: PathBuf,
+        /// Show collection paths relative to this directory where possible
+        #[arg(long)]
+        relative_to: Option<PathBuf>,
+    },
+
+    /// Show the most recently created or synced simulations across all collections
+    Latest {
+        #[arg(short, long, default_value = "simdex.db")]
+        db: PathBuf,
+        /// Number of simulations to show
+        #[arg(short, long, default_value_t = 10)]
+        n: usize,
+        #[arg(long, value_enum, default_value_t)]
+        by: simdex::api::LatestBy,
+    },
+
+    /// Run a read-only SQL query (SELECT or WITH) against the cache database
+    #[command(alias = "query")]
+    Sql {
+        #[arg(short, long, default_value = "simdex.db")]
+        db: PathBuf,
+        #[arg()]
+        query: String,
+        #[arg(long, value_enum, default_value_t)]
+        format: simdex::api::Format,
+        /// Print the number of resulting rows instead of the rows
+        #[arg(long, conflicts_with = "count_by")]
+        count: bool,
+        /// Print the number of resulting rows per value of this column, or of this
+        /// parameter if the result has a `parameters_json` column
+        #[arg(long, value_name = "KEY")]
+        count_by: Option<String>,
+    },
+
+    /// Infer the type of each parameter of a collection
+    Schema {
+        #[arg(short, long, default_value = "simdex.db")]
+        db: PathBuf,
+        #[arg()]
+        collection: String,
+        #[arg(long, value_enum, default_value_t)]
+        format: simdex::api::SchemaFormat,
+        /// With `--format json`, restrict parameters with at most this many distinct
+        /// values to an `enum` of them
+        #[arg(long, default_value_t = 10)]
+        max_enum: usize,
+    },
+
+    /// Report parameters whose value types differ across the simulations of a collection
+    Lint {
+        #[arg(short, long, default_value = "simdex.db")]
+        db: PathBuf,
+        #[arg()]
+        collection: String,
+    },
+
+    /// List simulations whose cached parameters are not valid JSON and optionally
+    /// re-read them from their entries
+    RepairJson {
+        #[arg(short, long, default_value = "simdex.db")]
+        db: PathBuf,
+        /// Only check this collection
+        #[arg()]
+        collection: Option<String>,
+        /// Re-read the affected simulations from their data files
+        #[arg(long)]
+        fix: bool,
+        /// With --fix, clear the parameters of simulations whose entry cannot be read
+        #[arg(long, requires = "fix")]
+        clear_unreadable: bool,
+        #[command(flatten)]
+        entry: EntryArgs,
+    },
+
+    /// Serve the cache read-only as JSON over HTTP: `/collections`,
+    /// `/collections/{uid}` and `/collections/{uid}/params`
+    Serve {
+        #[arg(short, long, default_value = "simdex.db")]
+        db: PathBuf,
+        #[arg(short, long, default_value_t = 8080)]
+        port: u16,
+        /// Address to listen on; the default only accepts connections from this machine
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: std::net::IpAddr,
+    },
+
+    /// Copy all collections and simulations of one database into another
+    Merge {
+        #[arg()]
+        src: PathBuf,
+        #[arg()]
+        dest: PathBuf,
+        /// Which side wins for collections and simulations present in both
+        #[arg(long, value_enum, default_value_t)]
+        prefer: simdex::api::merge::Prefer,
+    },
+
+    /// Write a standalone database with only one collection, to share it; compressed if
+    /// FILE ends in `.gz` or `.zst`
+    Archive {
+        #[arg(short, long, default_value = "simdex.db")]
+        db: PathBuf,
+        #[arg()]
+        collection: String,
+        #[arg(short, long, value_name = "FILE")]
+        out: PathBuf,
+    },
+    /// Write the size, mtime and SHA-256 of the data files of a collection to a JSON
+    /// manifest, to verify later that they were not altered
+    Manifest {
+        #[arg(short, long, default_value = "simdex.db")]
+        db: PathBuf,
+        #[arg()]
+        collection: String,
+        /// Where to write the manifest; defaults to manifest.json in the collection
+        /// directory, where verify-manifest looks for the files by default
+        #[arg(short, long, value_name = "FILE")]
+        out: Option<PathBuf>,
+        /// Name of a data file of each entry to checksum; may be repeated
+        #[arg(long = "data-file", value_name = "FILE", default_value = "data.h5")]
+        data_files: Vec<String>,
+        /// Number of files hashed in parallel, defaults to the number of CPUs
+        #[arg(short, long)]
+        jobs: Option<std::num::NonZeroUsize>,
+    },
+    /// Re-hash the files of a manifest and report those that are missing or changed
+    VerifyManifest {
+        #[arg()]
+        manifest: PathBuf,
+        /// Directory of the collection, which the paths of the manifest are relative to;
+        /// defaults to the directory of the manifest
+        #[arg(long)]
+        root: Option<PathBuf>,
+        /// Number of files hashed in parallel, defaults to the number of CPUs
+        #[arg(short, long)]
+        jobs: Option<std::num::NonZeroUsize>,
+    },
+
+    /// Migrate a database built with the legacy path-keyed schema
+    UpgradeDb {
+        #[arg(short, long, default_value = "simdex.db")]
+        db: PathBuf,
+    },
+
+    /// Set the status of a simulation by hand; it is kept across scans until cleared
+    SetStatus {
+        #[arg(short, long, default_value = "simdex.db")]
+        db: PathBuf,
+        #[arg()]
+        collection: String,
+        /// Name of the simulation, or else a glob such as `failed_*` or `{a,b}` for several
+        #[arg()]
+        name: String,
+        #[arg(required_unless_present = "clear")]
+        status: Option<String>,
+        /// Remove the override and show the status read from the entry again
+        #[arg(long, conflicts_with = "status")]
+        clear: bool,
+        /// Only list the simulations that would be changed
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Set the description of a collection shown by `ls`
+    Describe {
+        #[arg(short, long, default_value = "simdex.db")]
+        db: PathBuf,
+        #[arg()]
+        uid: String,
+        #[arg()]
+        description: String,
     },
 
     LsParams {
@@ -29,11 +397,45 @@ pub enum Commands {
         db: PathBuf,
         #[arg()]
         collection: String,
+        /// Only print the parameter keys, one per line
+        #[arg(long)]
+        keys_only: bool,
+        /// Only consider simulations with this status, e.g. `finished`
+        #[arg(long)]
+        status: Option<String>,
+    },
+    /// Count simulations by status or another column, optionally per parameter value
+    Stats {
+        #[arg(short, long, default_value = "simdex.db")]
+        db: PathBuf,
+        #[arg()]
+        collection: String,
+        /// Make a row per value of this parameter or column; simulations without it
+        /// are counted under `<none>`
+        #[arg(long)]
+        group_by: Option<String>,
+        /// Parameter or column whose values are counted
+        #[arg(long, default_value = "status")]
+        count_by: String,
+        #[arg(long, value_enum, default_value_t = simdex::api::Format::Table)]
+        format: simdex::api::Format,
+    },
+    /// List simulations of a collection that share the same parameters
+    Dupes {
+        #[arg(short, long, default_value = "simdex.db")]
+        db: PathBuf,
+        #[arg()]
+        collection: String,
     },
 
     Migrate {
         #[arg(default_value = ".")]
         root: PathBuf,
+        /// Name of the sidecar file; a `.gz` or `.zst` suffix compresses it
+        #[arg(short, long, default_value = "meta.yml")]
+        output: String,
+        #[command(flatten)]
+        entry: EntryArgs,
     },
 
     Display {
@@ -41,6 +443,155 @@ pub enum Commands {
         db_path: PathBuf,
         #[arg()]
         collection: String,
+        /// Show all parameters in a single `key=val, ...` column
+        #[arg(long)]
+        params_inline: bool,
+        /// Truncate cells wider than this many characters
+        #[arg(long)]
+        max_col_width: Option<usize>,
+        /// Table border style, overrides `display.style` in simdex.toml
+        #[arg(long, value_enum)]
+        style: Option<simdex::config::TableStyle>,
+        /// Only show rows matching a condition, e.g. `dt<0.1`, `box_size[0]>10` or
+        /// `materials contains steel`; may be repeated
+        #[arg(long = "where", value_name = "CONDITION", value_parser = simdex::core::filter::Filter::parse)]
+        filters: Vec<simdex::core::filter::Filter>,
+        #[arg(long, value_enum, default_value_t)]
+        format: simdex::api::Format,
+        /// Fail on simulations with malformed stored parameters instead of warning
+        #[arg(long)]
+        strict: bool,
+        /// Read the collection's entries from disk instead of the cache
+        #[arg(long)]
+        live: bool,
+        /// Directory to search for collections that are not cached
+        #[arg(long)]
+        root: Option<PathBuf>,
+        /// Lowercase parameter keys, merging columns like `Reynolds` and `reynolds`;
+        /// simulations with differing values for the variants are reported
+        #[arg(long)]
+        parameters_case_fold: bool,
+        /// Write the output to this file instead of stdout, without colors
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+        /// Only show these columns, in this order, e.g. `name,status,dt`
+        #[arg(long, value_delimiter = ',', conflicts_with = "columns_file")]
+        columns: Option<Vec<String>>,
+        /// Read the columns to show from a file, one per line; lines starting with `#`
+        /// are comments
+        #[arg(long, value_name = "FILE")]
+        columns_file: Option<PathBuf>,
+        /// Add a column with the HDF5 superblock version of each simulation's data file
+        #[arg(long)]
+        hdf5_version: bool,
+        /// Sort by this parameter or column and show a subtable per value; rows without
+        /// it come last, under `<ungrouped>`
+        #[arg(long, value_name = "KEY")]
+        group_by: Option<String>,
+        /// Leave out parameters with the same value in every shown row, listing them
+        /// above the table instead. Parameters named in --columns are kept
+        #[arg(long, conflicts_with = "params_inline")]
+        hide_constant: bool,
+        /// Redraw the table whenever it changes until interrupted with Ctrl-C, checking
+        /// every --interval seconds; ignored if stdout is not a terminal
+        #[arg(long, alias = "watch", conflicts_with = "output")]
+        follow: bool,
+        /// Seconds between checks with --follow
+        #[arg(long, value_name = "SECONDS", default_value = "2", value_parser = parse_seconds, requires = "follow")]
+        interval: std::time::Duration,
+    },
+    /// List combinations of a parameter grid that have not been run yet
+    Missing {
+        #[arg(short, long, default_value = "simdex.db")]
+        db: PathBuf,
+        #[arg()]
+        collection: String,
+        /// Grid specification, e.g. `dt=0.1,0.01 method=cg,gmres`
+        #[arg(long, num_args = 1.., required = true)]
+        grid: Vec<String>,
+        #[arg(long, value_enum, default_value_t = simdex::api::Format::Table)]
+        format: simdex::api::Format,
+    },
+    /// Check the cache database and prune stale failed simulations
+    Doctor {
+        #[arg(short, long, default_value = "simdex.db")]
+        db: PathBuf,
+        /// Delete simulations with status `failed`
+        #[arg(long)]
+        prune_failed: bool,
+        /// Only prune simulations older than this, e.g. `30d`, `12h`, `2w`
+        #[arg(long, requires = "prune_failed")]
+        older_than: Option<String>,
+        /// Do not ask for confirmation before deleting
+        #[arg(short, long)]
+        yes: bool,
+        /// Only show what would be deleted
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Export the simulations of a collection as CSV, JSON or YAML
+    Export {
+        #[arg(short, long, default_value = "simdex.db")]
+        db: PathBuf,
+        #[arg()]
+        collection: String,
+        #[arg(long, value_enum, default_value_t = simdex::api::export::ExportFormat::Csv)]
+        format: simdex::api::export::ExportFormat,
+        /// Representation of `created_at`
+        #[arg(long, value_enum, default_value_t)]
+        time_format: simdex::api::export::TimeFormat,
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Only export parameters whose values are all numbers, and `id` (and
+        /// `created_at` with an epoch time format), e.g. for plotting
+        #[arg(long)]
+        numeric_only: bool,
+        /// Core columns to export with --numeric-only anyway, e.g. `name,status`
+        #[arg(long, value_delimiter = ',', requires = "numeric_only")]
+        keep: Vec<String>,
+        /// Export at most this many simulations
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+        /// Export each simulation with this probability, e.g. `0.01` for a 1% sample
+        #[arg(long, value_name = "RATE", value_parser = parse_rate)]
+        sample: Option<f64>,
+        /// Seed of --sample; the same seed gives the same sample
+        #[arg(long, default_value_t = 0, requires = "sample")]
+        seed: u64,
+    },
+    /// Show the sync history of a simulation, or the recent scans if none is given
+    History {
+        #[arg(short, long, default_value = "simdex.db")]
+        db: PathBuf,
+        #[arg(requires = "name")]
+        collection: Option<String>,
+        #[arg()]
+        name: Option<String>,
+        /// Show scan roots relative to this directory where possible
+        #[arg(long)]
+        relative_to: Option<PathBuf>,
+    },
+    /// Check that all entries below root are readable, without using a database
+    Check {
+        #[arg(default_value = ".")]
+        root: PathBuf,
+        #[command(flatten)]
+        entry: EntryArgs,
+    },
+    /// Force a re-sync of one entry on the next scan
+    Touch {
+        #[arg(short, long, default_value = "simdex.db")]
+        db: PathBuf,
+        #[arg()]
+        collection: String,
+        #[arg()]
+        name: String,
+        /// Re-read the entry immediately instead of on the next scan
+        #[arg(long)]
+        now: bool,
+        #[command(flatten)]
+        entry: EntryArgs,
     },
     // Ds {
     //     #[arg()]
@@ -51,27 +602,549 @@ pub enum Commands {
         path: PathBuf,
         #[arg()]
         uid: String,
+        /// Also write an example entry that `scan` and `display` can show right away
+        #[arg(long)]
+        example: bool,
+        /// Create missing parent directories of PATH as well
+        #[arg(short, long)]
+        parents: bool,
+    },
+
+    /// Print the cached collection uids starting with PREFIX, one per line, for shell
+    /// completion functions, e.g. `COMPREPLY=($(simdex __complete-uids "$cur"))` in bash
+    #[command(name = "__complete-uids", hide = true)]
+    CompleteUids {
+        #[arg(short, long, default_value = "simdex.db")]
+        db: PathBuf,
+        #[arg(default_value = "")]
+        prefix: String,
     },
 }
 fn main() {
     let cli = Cli::parse();
 
     match &cli.command {
-        Commands::Scan { root, db } => simdex::api::scan(root, db),
-        Commands::Ls { db } => simdex::api::ls_collections(db),
-        Commands::LsParams { db, collection } => simdex::api::ls_params(db, collection),
-        Commands::Migrate { root } => simdex::api::migrate(root),
+        Commands::Scan {
+            root,
+            db,
+            entry,
+            no_lock,
+            new_only,
+            batch_size,
+            only_changed,
+            json,
+            dataset_storage,
+            nested,
+            stream,
+            include,
+            exclude,
+            param_include,
+            param_exclude,
+            strict_markers,
+            strict,
+            marker_uid,
+            profile,
+            slowest,
+            mtime_resolution,
+            fail_fast,
+            keep_going: _,
+            lock_retries,
+            lock_retry_delay,
+            from_stdin,
+            paths_file,
+        } => {
+            use simdex::core::filter::GlobFilter;
+            let entries = match (from_stdin, paths_file) {
+                (true, _) => Some(simdex::api::read_path_list(std::io::stdin().lock())),
+                (false, Some(path)) => Some(
+                    std::fs::File::open(path)
+                        .map_err(|e| format!("cannot open {}: {}", path.display(), e))
+                        .and_then(|file| {
+                            simdex::api::read_path_list(std::io::BufReader::new(file))
+                        }),
+                ),
+                (false, None) => None,
+            };
+            let entries = match entries.transpose() {
+                Ok(entries) => entries,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            let (paths, params_filter) = match GlobFilter::new(include, exclude)
+                .and_then(|paths| Ok((paths, GlobFilter::new(param_include, param_exclude)?)))
+            {
+                Ok(filters) => filters,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            let opts = simdex::api::ScanOptions {
+                sync: simdex::core::collection::SyncOptions {
+                    load: simdex::core::entry::LoadOptions {
+                        params_filter,
+                        ..entry.load_options()
+                    },
+                    new_only: *new_only,
+                    batch_size: batch_size.map(|n| n.get()),
+                    quiet: *only_changed || *json,
+                    dataset_storage: dataset_storage.clone(),
+                    profile: *profile,
+                    mtime_resolution: *mtime_resolution,
+                    fail_fast: *fail_fast,
+                    lock_retries: *lock_retries,
+                    lock_retry_delay: *lock_retry_delay,
+                },
+                no_lock: *no_lock,
+                nested: *nested,
+                stream: *stream,
+                paths,
+                strict_markers: *strict_markers,
+                strict: *strict,
+                marker_uid: *marker_uid,
+                slowest: *slowest,
+                entries,
+            };
+            match simdex::api::scan(root, db, &opts) {
+                Ok(report) => {
+                    if *json {
+                        println!("{}", report.changes_json());
+                    } else {
+                        if *only_changed
+                            && let Err(e) = report.write_changes(&mut std::io::stdout())
+                        {
+                            eprintln!("Error: {}", e);
+                        }
+                        println!("{}", report);
+                    }
+                    if *profile {
+                        // on stderr, so that it does not mix with --json output
+                        eprintln!("{}", report.profile);
+                        if !report.slowest.is_empty() {
+                            eprintln!("Slowest entries:");
+                        }
+                        for (path, duration) in &report.slowest {
+                            eprintln!("  {:>10.3}s  {}", duration.as_secs_f64(), path.display());
+                        }
+                    }
+                    if !report.failed.is_empty() {
+                        std::process::exit(1);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Ls { db, relative_to } => {
+            if let Err(e) =
+                simdex::api::ls_collections(db, relative_to.as_deref(), &mut std::io::stdout())
+            {
+                eprintln!("Error: {}", e);
+            }
+        }
+        Commands::Tui { db } => {
+            if let Err(e) = simdex::api::tui::tui(db) {
+                eprintln!("Error: {}", e);
+            }
+        }
+        Commands::Tree { db, relative_to } => {
+            if let Err(e) = simdex::api::tree(db, relative_to.as_deref()) {
+                eprintln!("Error: {}", e);
+            }
+        }
+        Commands::Latest { db, n, by } => {
+            if let Err(e) = simdex::api::latest(db, *n, *by) {
+                eprintln!("Error: {}", e);
+            }
+        }
+        Commands::Sql {
+            db,
+            query,
+            format,
+            count,
+            count_by,
+        } => {
+            let count = match count_by {
+                Some(key) => Some(simdex::api::SqlCount::By(key.clone())),
+                None => count.then_some(simdex::api::SqlCount::Total),
+            };
+            if let Err(e) = simdex::api::sql(db, query, *format, count) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Schema {
+            db,
+            collection,
+            format,
+            max_enum,
+        } => {
+            if let Err(e) = simdex::api::schema(db, collection, *format, *max_enum) {
+                eprintln!("Error: {}", e);
+            }
+        }
+        Commands::Lint { db, collection } => match simdex::api::lint(db, collection) {
+            Ok(0) => {}
+            Ok(_) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        },
+        Commands::Serve { db, port, bind } => {
+            let addr = std::net::SocketAddr::new(*bind, *port);
+            if let Err(e) = simdex::api::serve::serve(db, addr) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::RepairJson {
+            db,
+            collection,
+            fix,
+            clear_unreadable,
+            entry,
+        } => match simdex::api::repair_json(
+            db,
+            collection.as_deref(),
+            *fix,
+            *clear_unreadable,
+            &entry.load_options(),
+        ) {
+            Ok(0) => {}
+            Ok(_) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        },
+        Commands::Archive {
+            db,
+            collection,
+            out,
+        } => match simdex::api::archive::archive(db, collection, out) {
+            Ok(report) => println!("{}", report),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        },
+        Commands::Manifest {
+            db,
+            collection,
+            out,
+            data_files,
+            jobs,
+        } => {
+            let jobs = jobs
+                .or_else(|| std::thread::available_parallelism().ok())
+                .map_or(1, |n| n.get());
+            match simdex::api::manifest::write_manifest(
+                db,
+                collection,
+                data_files,
+                out.as_deref(),
+                jobs,
+            ) {
+                Ok((manifest, out)) => println!(
+                    "Wrote checksums of {} files of {} to {}",
+                    manifest.files.len(),
+                    collection,
+                    out.display()
+                ),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::VerifyManifest {
+            manifest,
+            root,
+            jobs,
+        } => {
+            let jobs = jobs
+                .or_else(|| std::thread::available_parallelism().ok())
+                .map_or(1, |n| n.get());
+            match simdex::api::manifest::verify_manifest(manifest, root.as_deref(), jobs) {
+                Ok(drift) if drift.is_empty() => println!("All files match {}", manifest.display()),
+                Ok(drift) => {
+                    for (path, reason) in &drift {
+                        println!(" - {}: {}", path.display(), reason);
+                    }
+                    eprintln!("{} files differ from {}", drift.len(), manifest.display());
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Merge { src, dest, prefer } => {
+            match simdex::api::merge::merge(src, dest, *prefer) {
+                Ok(report) => {
+                    for (uid, src_path, dest_path) in &report.conflicts {
+                        eprintln!(
+                            "[!] collection {} is at {} in the source but {} in the destination",
+                            uid, src_path, dest_path
+                        );
+                    }
+                    println!("{}", report);
+                }
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::UpgradeDb { db } => {
+            if let Err(e) = simdex::api::upgrade_db(db) {
+                eprintln!("Error: {}", e);
+            }
+        }
+        Commands::SetStatus {
+            db,
+            collection,
+            name,
+            status,
+            clear: _,
+            dry_run,
+        } => match simdex::api::set_status(db, collection, name, status.as_deref(), *dry_run) {
+            Ok(names) if *dry_run => {
+                println!("Would change the status of {} simulations:", names.len());
+                for name in names {
+                    println!("  {}", name);
+                }
+            }
+            Ok(names) => match status {
+                Some(status) => println!(
+                    "Set the status of {} simulations to {}",
+                    names.len(),
+                    status
+                ),
+                None => println!("Cleared the status of {} simulations", names.len()),
+            },
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        },
+        Commands::Describe {
+            db,
+            uid,
+            description,
+        } => {
+            if let Err(e) = simdex::api::describe(db, uid, description) {
+                eprintln!("Error: {}", e);
+            }
+        }
+        Commands::LsParams {
+            db,
+            collection,
+            keys_only,
+            status,
+        } => {
+            if let Err(e) = simdex::api::ls_params(
+                db,
+                collection,
+                *keys_only,
+                status.as_deref(),
+                &mut std::io::stdout(),
+            ) {
+                eprintln!("Error: {}", e);
+            }
+        }
+        Commands::Stats {
+            db,
+            collection,
+            group_by,
+            count_by,
+            format,
+        } => {
+            if let Err(e) =
+                simdex::api::stats(db, collection, group_by.as_deref(), count_by, *format)
+            {
+                eprintln!("Error: {}", e);
+            }
+        }
+        Commands::Dupes { db, collection } => {
+            if let Err(e) = simdex::api::dupes(db, collection) {
+                eprintln!("Error: {}", e);
+            }
+        }
+        Commands::Migrate {
+            root,
+            output,
+            entry,
+        } => simdex::api::migrate(root, output, &entry.load_options()),
         Commands::Display {
             db_path,
             collection,
-        } => simdex::api::display(db_path, collection),
+            params_inline,
+            max_col_width,
+            style,
+            filters,
+            format,
+            strict,
+            live,
+            root,
+            parameters_case_fold,
+            output,
+            columns,
+            columns_file,
+            hdf5_version,
+            group_by,
+            hide_constant,
+            follow,
+            interval,
+        } => {
+            let columns = match columns_file {
+                Some(path) => match simdex::api::read_columns_file(path) {
+                    Ok(columns) => Some(columns),
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                },
+                None => columns.clone(),
+            };
+            let opts = simdex::api::DisplayOptions {
+                params_inline: *params_inline,
+                max_col_width: *max_col_width,
+                style: *style,
+                filters: filters.clone(),
+                format: *format,
+                strict: *strict,
+                live: *live,
+                root: root.clone(),
+                case_fold: *parameters_case_fold,
+                output: output.clone(),
+                columns,
+                hdf5_version: *hdf5_version,
+                group_by: group_by.clone(),
+                hide_constant: *hide_constant,
+            };
+            let result = if *follow {
+                simdex::api::follow(db_path, collection, &opts, *interval)
+            } else {
+                simdex::api::display(db_path, collection, &opts, &mut std::io::stdout())
+            };
+            if let Err(e) = result {
+                eprintln!("Error: {}", e);
+            }
+        }
+        Commands::Missing {
+            db,
+            collection,
+            grid,
+            format,
+        } => {
+            if let Err(e) = simdex::api::missing(db, collection, &grid.join(" "), *format) {
+                eprintln!("Error: {}", e);
+            }
+        }
+        Commands::Doctor {
+            db,
+            prune_failed,
+            older_than,
+            yes,
+            dry_run,
+        } => {
+            let opts = simdex::api::DoctorOptions {
+                prune_failed: *prune_failed,
+                older_than: older_than.clone(),
+                yes: *yes,
+                dry_run: *dry_run,
+            };
+            if let Err(e) = simdex::api::doctor(db, &opts) {
+                eprintln!("Error: {}", e);
+            }
+        }
+        Commands::Export {
+            db,
+            collection,
+            format,
+            time_format,
+            output,
+            numeric_only,
+            keep,
+            limit,
+            sample,
+            seed,
+        } => {
+            let opts = simdex::api::export::ExportOptions {
+                format: *format,
+                time_format: *time_format,
+                numeric_only: *numeric_only,
+                keep: keep.clone(),
+                limit: *limit,
+                sample: *sample,
+                seed: *seed,
+            };
+            if let Err(e) = simdex::api::export::export(db, collection, output.as_deref(), &opts) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::History {
+            db,
+            collection,
+            name,
+            relative_to,
+        } => {
+            let result = match (collection, name) {
+                (Some(collection), Some(name)) => simdex::api::history(db, collection, name),
+                _ => simdex::api::scan_history(db, relative_to.as_deref()),
+            };
+            if let Err(e) = result {
+                eprintln!("Error: {}", e);
+            }
+        }
+        Commands::Check { root, entry } => {
+            if simdex::api::check(root, &entry.load_options()) > 0 {
+                std::process::exit(1);
+            }
+        }
+        Commands::Touch {
+            db,
+            collection,
+            name,
+            now,
+            entry,
+        } => {
+            if let Err(e) = simdex::api::touch(db, collection, name, *now, &entry.load_options()) {
+                eprintln!("Error: {}", e);
+            }
+        }
         // Commands::Ds { uid } => simdex::api::display_polars(uid),
 
         // create returns a Result, so we handle the error
-        Commands::Create { path, uid } => {
-            if let Err(e) = simdex::core::discovery::new_collection(path, uid) {
+        Commands::Create {
+            path,
+            uid,
+            example,
+            parents,
+        } => {
+            if let Err(e) = simdex::core::discovery::new_collection(path, uid, *parents) {
                 eprintln!("Error: {}", e);
+                return;
+            }
+            println!("Created collection {} at {}", uid, path.display());
+            if *example {
+                match simdex::core::entry::create_example_entry(path) {
+                    Ok(entry) => println!("Created example entry {}", entry.display()),
+                    Err(e) => eprintln!("Error: {}", e),
+                }
             }
         }
+        Commands::CompleteUids { db, prefix } => {
+            // completion must stay quiet, so errors (e.g. no database here) print nothing
+            let _ = simdex::api::complete_uids(db, prefix);
+        }
     }
 }